@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use uuid::Uuid;
+
+use knowledge_graph::models::{Direction, Edge, EdgeType, Node, NodeType};
+use knowledge_graph::services::graph::KnowledgeGraph;
+
+const EDGE_TYPES: [EdgeType; 4] = [
+    EdgeType::Calls,
+    EdgeType::Imports,
+    EdgeType::Contains,
+    EdgeType::References,
+];
+
+/// Builds a graph with `nodes` nodes and `nodes * fanout` edges using a
+/// fixed-seed generator so runs are comparable.
+fn build_graph(nodes: usize, fanout: usize) -> (KnowledgeGraph, Vec<Uuid>) {
+    let mut graph = KnowledgeGraph::new();
+    let ids: Vec<Uuid> = (0..nodes)
+        .map(|i| {
+            graph
+                .add_node(Node::new(format!("fn_{}", i), NodeType::Function))
+                .unwrap()
+        })
+        .collect();
+
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize
+    };
+    for (i, source) in ids.iter().enumerate() {
+        for j in 0..fanout {
+            let target = ids[next() % nodes];
+            let edge_type = EDGE_TYPES[(i + j) % EDGE_TYPES.len()].clone();
+            graph
+                .add_edge(Edge::new(*source, target, edge_type))
+                .unwrap();
+        }
+    }
+    (graph, ids)
+}
+
+/// The pre-index implementation: every BFS step scans all edges.
+fn full_scan_neighbors(edges: &[&Edge], start: Uuid, direction: &Direction, depth: usize) -> usize {
+    let mut visited = HashSet::from([start]);
+    let mut current_level = vec![start];
+    let mut found = 0;
+    for _ in 0..depth {
+        let mut next_level = Vec::new();
+        for current in &current_level {
+            for edge in edges.iter().filter(|e| match direction {
+                Direction::Outgoing => e.source_id == *current,
+                Direction::Incoming => e.target_id == *current,
+                Direction::Both => e.source_id == *current || e.target_id == *current,
+            }) {
+                let neighbor = if edge.source_id == *current {
+                    edge.target_id
+                } else {
+                    edge.source_id
+                };
+                if visited.insert(neighbor) {
+                    found += 1;
+                    next_level.push(neighbor);
+                }
+            }
+        }
+        current_level = next_level;
+    }
+    found
+}
+
+fn bench_neighbors(c: &mut Criterion) {
+    let (graph, ids) = build_graph(5_000, 4);
+    let edges = graph.list_edges();
+    let start = ids[0];
+
+    let mut group = c.benchmark_group("find_neighbors");
+    group.sample_size(10);
+    for depth in [1, 2, 3] {
+        group.bench_with_input(BenchmarkId::new("indexed", depth), &depth, |b, &depth| {
+            b.iter(|| {
+                graph
+                    .find_neighbors(black_box(&start), None, &Direction::Both, depth)
+                    .len()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("full_scan", depth), &depth, |b, &depth| {
+            b.iter(|| full_scan_neighbors(&edges, black_box(start), &Direction::Both, depth))
+        });
+    }
+    group.bench_function("indexed_calls_only/3", |b| {
+        b.iter(|| {
+            graph
+                .find_neighbors(
+                    black_box(&start),
+                    Some(&[EdgeType::Calls]),
+                    &Direction::Outgoing,
+                    3,
+                )
+                .len()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_neighbors);
+criterion_main!(benches);
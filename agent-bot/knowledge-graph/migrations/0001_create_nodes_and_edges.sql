@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS kg_nodes (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    node_type TEXT NOT NULL,
+    path TEXT,
+    language TEXT,
+    description TEXT,
+    metadata JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS kg_nodes_node_type_idx ON kg_nodes (node_type);
+
+CREATE TABLE IF NOT EXISTS kg_edges (
+    id UUID PRIMARY KEY,
+    source_id UUID NOT NULL REFERENCES kg_nodes (id) ON DELETE CASCADE,
+    target_id UUID NOT NULL REFERENCES kg_nodes (id) ON DELETE CASCADE,
+    edge_type TEXT NOT NULL,
+    weight DOUBLE PRECISION NOT NULL DEFAULT 1.0,
+    metadata JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS kg_edges_source_id_idx ON kg_edges (source_id);
+CREATE INDEX IF NOT EXISTS kg_edges_target_id_idx ON kg_edges (target_id);
@@ -0,0 +1 @@
+ALTER TABLE kg_nodes ADD COLUMN IF NOT EXISTS embedding REAL[];
@@ -0,0 +1,21 @@
+-- Each tenant graph has its own records. Natural-key ids repeat across
+-- graphs, so ids are only unique within a graph.
+ALTER TABLE kg_edges DROP CONSTRAINT IF EXISTS kg_edges_source_id_fkey;
+ALTER TABLE kg_edges DROP CONSTRAINT IF EXISTS kg_edges_target_id_fkey;
+ALTER TABLE kg_edges DROP CONSTRAINT IF EXISTS kg_edges_pkey;
+ALTER TABLE kg_nodes DROP CONSTRAINT IF EXISTS kg_nodes_pkey;
+
+ALTER TABLE kg_nodes ADD COLUMN IF NOT EXISTS graph TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE kg_edges ADD COLUMN IF NOT EXISTS graph TEXT NOT NULL DEFAULT 'default';
+
+ALTER TABLE kg_nodes ADD PRIMARY KEY (graph, id);
+ALTER TABLE kg_edges ADD PRIMARY KEY (graph, id);
+ALTER TABLE kg_edges ADD CONSTRAINT kg_edges_source_id_fkey
+    FOREIGN KEY (graph, source_id) REFERENCES kg_nodes (graph, id) ON DELETE CASCADE;
+ALTER TABLE kg_edges ADD CONSTRAINT kg_edges_target_id_fkey
+    FOREIGN KEY (graph, target_id) REFERENCES kg_nodes (graph, id) ON DELETE CASCADE;
+
+DROP INDEX IF EXISTS kg_edges_source_id_idx;
+DROP INDEX IF EXISTS kg_edges_target_id_idx;
+CREATE INDEX IF NOT EXISTS kg_edges_source_id_idx ON kg_edges (graph, source_id);
+CREATE INDEX IF NOT EXISTS kg_edges_target_id_idx ON kg_edges (graph, target_id);
@@ -67,6 +67,33 @@ index_repo() {
     return 0
 }
 
+# Asks the knowledge-graph service to re-index the checkout; it only re-reads
+# files changed since the commit it last indexed.
+refresh_graph() {
+    local repo_name="$1"
+
+    if [ -z "$KG_API_URL" ]; then
+        return 0
+    fi
+
+    # The graph is scoped to the org that owns the repository
+    local org_id
+    org_id=$(jq -r --arg name "$repo_name" \
+        '.repositories[]? | select(.name == $name) | .org_id // empty' \
+        "$REPOS_CONFIG" 2>/dev/null || true)
+    org_id="${org_id:-${DEFAULT_ORG_ID:-default}}"
+
+    log "Refreshing knowledge graph for: $repo_name (org $org_id)"
+    local auth=()
+    if [ -n "$KG_API_KEY" ]; then
+        auth=(-H "Authorization: Bearer ${KG_API_KEY}")
+    fi
+    curl -fsS -X POST -H "X-Org-Id: ${org_id}" "${auth[@]}" \
+        "${KG_API_URL}/api/v1/repositories/${repo_name}/index" 2>&1 | while read line; do
+        log "  [kg] $line"
+    done
+}
+
 sync_local_repos() {
     log "Scanning for local repositories..."
 
@@ -82,6 +109,7 @@ sync_local_repos() {
                 cd - > /dev/null
 
                 index_repo "$repo_name" || log "Index failed for $repo_name"
+                refresh_graph "$repo_name"
             fi
         done
     fi
@@ -98,6 +126,7 @@ sync_from_config() {
     jq -r '.repositories[] | "\(.url)|\(.name)"' "$REPOS_CONFIG" 2>/dev/null | while IFS='|' read -r url name; do
         if [ -n "$url" ] && [ -n "$name" ]; then
             sync_repo "$url" "$name" && index_repo "$name"
+            refresh_graph "$name"
         fi
     done
 }
@@ -110,6 +139,7 @@ sync_from_env() {
         for repo_url in "${repos[@]}"; do
             repo_name=$(basename "$repo_url" .git)
             sync_repo "$repo_url" "$repo_name" && index_repo "$repo_name"
+            refresh_graph "$repo_name"
         done
     fi
 }
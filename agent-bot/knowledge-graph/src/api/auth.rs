@@ -0,0 +1,112 @@
+//! Authentication and authorization for `/api/v1` routes.
+//!
+//! `authenticate` resolves the request's credentials to a `Principal` and
+//! stores it in the request extensions. Each route group then `require`s a
+//! scope, and the tenant extractors check the principal may act for the
+//! requested org.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::{
+    api::tenant::{ORG_HEADER, REPOSITORY_HEADER},
+    error::KgError,
+    models::{Principal, Scope},
+    AppState,
+};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// A bearer token from `Authorization`, or a key from `X-API-Key`.
+fn credential(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let api_key = || {
+        headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+    };
+    bearer.or_else(api_key).map(str::trim)
+}
+
+fn unauthorized(error: impl Into<String>) -> Response {
+    KgError::Unauthorized(error.into()).into_response()
+}
+
+pub async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let principal = if state.auth.disabled {
+        Principal::anonymous()
+    } else {
+        let Some(credential) = credential(request.headers()) else {
+            return unauthorized("Missing credentials");
+        };
+        match state.auth.authenticate(credential) {
+            Ok(principal) => principal,
+            Err(error) => {
+                tracing::debug!("Rejected credentials: {}", error);
+                return unauthorized(error);
+            }
+        }
+    };
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+/// Rejects requests whose principal lacks `scope`, and records `scope` in
+/// the request extensions for the tenant extractors. Runs after
+/// `authenticate`.
+pub async fn require(State(scope): State<Scope>, mut request: Request, next: Next) -> Response {
+    let Some(principal) = request.extensions().get::<Principal>() else {
+        return unauthorized("Missing credentials");
+    };
+    if principal.scope < scope {
+        let error = format!("Requires {} scope", scope.as_str());
+        return KgError::Forbidden(error).into_response();
+    }
+    request.extensions_mut().insert(scope);
+    next.run(request).await
+}
+
+/// Reads `CORS_ALLOWED_ORIGINS`, a comma-separated list of origins, or `*`
+/// for any. Unset allows no cross-origin requests.
+pub fn cors_from_env() -> anyhow::Result<CorsLayer> {
+    let origins = std::env::var("CORS_ALLOWED_ORIGINS").unwrap_or_default();
+    let origins: Vec<&str> = origins
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .collect();
+    let allow_origin = if origins == ["*"] {
+        AllowOrigin::from(Any)
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static(API_KEY_HEADER),
+            HeaderName::from_static(ORG_HEADER),
+            HeaderName::from_static(REPOSITORY_HEADER),
+        ]))
+}
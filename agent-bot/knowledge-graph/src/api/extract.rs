@@ -0,0 +1,165 @@
+//! Extractors whose rejections use the `KgError` envelope instead of
+//! axum's plain-text responses.
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{
+        path::ErrorKind,
+        rejection::{PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Request,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::KgError;
+
+/// A JSON body. Deserialization errors name the offending field.
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = KgError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json(request.headers()) {
+            return Err(KgError::UnsupportedMediaType);
+        }
+        let bytes = Bytes::from_request(request, state)
+            .await
+            .map_err(|rejection| {
+                if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                    KgError::PayloadTooLarge
+                } else {
+                    KgError::InvalidBody(rejection.body_text())
+                }
+            })?;
+        parse(&bytes).map(Json)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers.get(header::CONTENT_TYPE) else {
+        return false;
+    };
+    let essence = content_type
+        .to_str()
+        .unwrap_or_default()
+        .split(';')
+        .next()
+        .unwrap_or_default();
+    let essence = essence.trim().to_ascii_lowercase();
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+fn parse<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, KgError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        if inner.is_syntax() || inner.is_eof() {
+            return KgError::MalformedJson(inner.to_string());
+        }
+        let message = inner.to_string();
+        // A missing field is reported at its parent, so it is named from
+        // the message.
+        let missing = message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.split('`').next());
+        let field = match (path.as_str(), missing) {
+            (".", Some(missing)) => missing.to_string(),
+            (path, Some(missing)) => format!("{}.{}", path, missing),
+            (".", None) => return KgError::InvalidBody(message),
+            (path, None) => path.to_string(),
+        };
+        KgError::invalid_field(field, message)
+    })?;
+    deserializer
+        .end()
+        .map_err(|err| KgError::MalformedJson(err.to_string()))?;
+    Ok(value)
+}
+
+/// Path parameters. A value that fails to parse, such as a bad UUID, is
+/// reported with its parameter name.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(KgError))]
+pub struct Path<T>(pub T);
+
+impl From<PathRejection> for KgError {
+    fn from(rejection: PathRejection) -> Self {
+        let PathRejection::FailedToDeserializePathParams(err) = &rejection else {
+            return KgError::Internal(anyhow::anyhow!(rejection.body_text()));
+        };
+        match err.kind() {
+            ErrorKind::ParseErrorAtKey {
+                key,
+                value,
+                expected_type,
+            } => KgError::InvalidPath {
+                field: key.clone(),
+                message: format!("{:?} is not a valid {}", value, short_type(expected_type)),
+            },
+            ErrorKind::Message(message) => KgError::InvalidPath {
+                field: "id".to_string(),
+                message: message.clone(),
+            },
+            _ => KgError::InvalidPath {
+                field: "path".to_string(),
+                message: err.body_text(),
+            },
+        }
+    }
+}
+
+/// Query string parameters.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(KgError))]
+pub struct Query<T>(pub T);
+
+impl From<QueryRejection> for KgError {
+    fn from(rejection: QueryRejection) -> Self {
+        KgError::InvalidQuery(rejection.body_text())
+    }
+}
+
+/// `uuid::Uuid` as `Uuid`.
+fn short_type(type_name: &str) -> &str {
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateEdgeRequest;
+
+    #[test]
+    fn test_json_errors_name_the_field() {
+        let body = br#"{"source_id": "8d3c0a8e-7f5a-4b0e-9a51-2f0c1d2b3a4c"}"#;
+        let missing = parse::<CreateEdgeRequest>(body).unwrap_err();
+        assert_eq!(missing.field(), Some("target_id"));
+
+        let bad_id = parse::<CreateEdgeRequest>(br#"{"source_id": "nope", "target_id": "x"}"#);
+        let error = bad_id.unwrap_err();
+        assert_eq!(error.code(), "invalid_field");
+        assert_eq!(error.field(), Some("source_id"));
+
+        let malformed = parse::<CreateEdgeRequest>(br#"{"source_id": "#).unwrap_err();
+        assert_eq!(malformed.code(), "malformed_json");
+        let trailing = parse::<serde_json::Value>(br#"{} {}"#).unwrap_err();
+        assert_eq!(trailing.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,22 +1,48 @@
+use std::convert::Infallible;
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
+    body::Body,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
 };
+use futures_util::{stream, StreamExt};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
+    api::{
+        extract::{Json, Path, Query},
+        tenant::TenantGraph,
+    },
+    error::KgError,
     models::{
-        CreateEdgeRequest, CreateNodeRequest, Direction, Edge, EdgeWithNodes,
-        GraphStats, NeighborsQuery, Node, NodeResponse, PathQuery, PathResult,
-        SearchQuery,
+        BulkUpsertRequest, CreateEdgeRequest, CreateNodeRequest, Edge, EdgeWithNodes,
+        ImportOptions, IndexOptions, IndexReport, IngestRequest, NeighborsFormat, NeighborsQuery,
+        Node, NodeResponse, PathMode, PathQuery, RenderQuery, RepositoryEntry, SearchQuery,
+        SimilarQuery, Tenant, TransactionRequest, UpdateEdgeRequest,
+    },
+    services::{
+        gkg,
+        graph::{depth_error, KnowledgeGraph},
+        indexer,
+        registry::{self, Registry},
+        render, search,
+        store::StoreError,
+        transfer::{self, Importer, LineSplitter},
+        vector::embedding_error,
     },
     AppState,
 };
 
+/// Records serialized per chunk of an export stream.
+const EXPORT_CHUNK: usize = 500;
+/// Lines applied per lock acquisition during an import.
+const IMPORT_BATCH: usize = 1000;
+/// Change detections run without the lock before an index holds it for one.
+const INDEX_ATTEMPTS: usize = 3;
+
 pub async fn health_check() -> impl IntoResponse {
     Json(serde_json::json!({
         "status": "healthy",
@@ -24,10 +50,8 @@ pub async fn health_check() -> impl IntoResponse {
     }))
 }
 
-pub async fn list_nodes(
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let graph = state.graph.read().await;
+pub async fn list_nodes(scope: TenantGraph) -> impl IntoResponse {
+    let graph = scope.graph.read().await;
     let nodes: Vec<&Node> = graph.list_nodes();
     Json(serde_json::json!({
         "nodes": nodes,
@@ -36,71 +60,76 @@ pub async fn list_nodes(
 }
 
 pub async fn create_node(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Json(req): Json<CreateNodeRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, KgError> {
     let node: Node = req.into();
     let id = {
-        let mut graph = state.graph.write().await;
-        graph.add_node(node.clone())
+        let mut graph = scope.graph.write().await;
+        let dimension = graph.embedding_dimension();
+        if let Some(error) = embedding_error(node.embedding.as_deref(), dimension) {
+            return Err(KgError::invalid_field("embedding", error));
+        }
+        graph.check_node(&node)?;
+        graph.add_node(node.clone())?
     };
 
-    (
+    Ok((
         StatusCode::CREATED,
         Json(serde_json::json!({
             "id": id,
             "node": node
         })),
-    )
+    ))
+}
+
+pub async fn bulk_upsert(
+    scope: TenantGraph,
+    Json(req): Json<BulkUpsertRequest>,
+) -> Result<impl IntoResponse, KgError> {
+    let mut graph = scope.graph.write().await;
+    Ok(Json(
+        graph.bulk_upsert(req).map_err(KgError::PartialUpsert)?,
+    ))
+}
+
+pub async fn apply_transaction(
+    scope: TenantGraph,
+    Json(req): Json<TransactionRequest>,
+) -> Result<impl IntoResponse, KgError> {
+    let mut graph = scope.graph.write().await;
+    let result = graph
+        .apply_transaction(req.operations)
+        .map_err(KgError::Transaction)?;
+    Ok(Json(result))
 }
 
 pub async fn get_node(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Path(id): Path<Uuid>,
-) -> impl IntoResponse {
-    let graph = state.graph.read().await;
-
-    match graph.get_node(&id) {
-        Some(node) => {
-            let edge_count = graph.get_edges_for_node(&id).len();
-            Json(serde_json::json!(NodeResponse {
-                node: node.clone(),
-                edge_count,
-            }))
-            .into_response()
-        }
-        None => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "Node not found" })),
-        )
-            .into_response(),
-    }
+) -> Result<impl IntoResponse, KgError> {
+    let graph = scope.graph.read().await;
+    let node = graph.get_node(&id).ok_or(KgError::NodeNotFound(id))?;
+    Ok(Json(NodeResponse {
+        node: node.clone(),
+        edge_count: graph.edge_count_for_node(&id),
+    }))
 }
 
 pub async fn delete_node(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Path(id): Path<Uuid>,
-) -> impl IntoResponse {
-    let mut graph = state.graph.write().await;
-
-    match graph.remove_node(&id) {
-        Some(_) => Json(serde_json::json!({
-            "status": "deleted",
-            "id": id
-        }))
-        .into_response(),
-        None => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "Node not found" })),
-        )
-            .into_response(),
-    }
+) -> Result<impl IntoResponse, KgError> {
+    let mut graph = scope.graph.write().await;
+    graph.remove_node(&id)?.ok_or(KgError::NodeNotFound(id))?;
+    Ok(Json(serde_json::json!({
+        "status": "deleted",
+        "id": id
+    })))
 }
 
-pub async fn list_edges(
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let graph = state.graph.read().await;
+pub async fn list_edges(scope: TenantGraph) -> impl IntoResponse {
+    let graph = scope.graph.read().await;
     let edges: Vec<EdgeWithNodes> = graph
         .list_edges()
         .iter()
@@ -122,52 +151,123 @@ pub async fn list_edges(
 }
 
 pub async fn create_edge(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Json(req): Json<CreateEdgeRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, KgError> {
     let edge: Edge = req.into();
-    let mut graph = state.graph.write().await;
+    let mut graph = scope.graph.write().await;
 
-    match graph.add_edge(edge.clone()) {
-        Some(id) => (
-            StatusCode::CREATED,
-            Json(serde_json::json!({
-                "id": id,
-                "edge": edge
-            })),
-        )
-            .into_response(),
-        None => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "Source or target node not found"
-            })),
-        )
-            .into_response(),
+    for (field, id) in [("source_id", edge.source_id), ("target_id", edge.target_id)] {
+        if graph.get_node(&id).is_none() {
+            return Err(KgError::ReferenceNotFound { field, id });
+        }
     }
+    graph.check_edge(&edge)?;
+    let id = graph.add_edge(edge.clone())?.expect("both endpoints exist");
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "id": id,
+            "edge": edge
+        })),
+    ))
+}
+
+pub async fn update_edge(
+    scope: TenantGraph,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateEdgeRequest>,
+) -> Result<impl IntoResponse, KgError> {
+    let mut graph = scope.graph.write().await;
+    graph.check_edge_update(&id, &req)?;
+    let edge = graph
+        .update_edge(&id, req)?
+        .ok_or(KgError::EdgeNotFound(id))?;
+    Ok(Json(serde_json::json!({
+        "id": id,
+        "edge": edge
+    })))
+}
+
+pub async fn delete_edge(
+    scope: TenantGraph,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, KgError> {
+    let mut graph = scope.graph.write().await;
+    graph.remove_edge(&id)?.ok_or(KgError::EdgeNotFound(id))?;
+    Ok(Json(serde_json::json!({
+        "status": "deleted",
+        "id": id
+    })))
 }
 
 pub async fn find_path(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Json(query): Json<PathQuery>,
-) -> impl IntoResponse {
-    let graph = state.graph.read().await;
+) -> Result<Response, KgError> {
+    // Negative costs would break Dijkstra, like negative weights.
+    if query
+        .edge_costs
+        .iter()
+        .flat_map(|costs| costs.values())
+        .any(|cost| *cost < 0.0)
+    {
+        return Err(KgError::invalid_field(
+            "edge_costs",
+            "costs must not be negative",
+        ));
+    }
+    if let Some(error) = depth_error(query.max_depth) {
+        return Err(KgError::invalid_field("max_depth", error));
+    }
+    let graph = scope.graph.read().await;
+    if let Some(error) = graph
+        .schema()
+        .edge_filter_error(query.edge_types.iter().flatten())
+    {
+        return Err(KgError::invalid_field("edge_types", error));
+    }
+    let costed = query.edge_costs.iter().flat_map(|costs| costs.keys());
+    if let Some(error) = graph.schema().edge_filter_error(costed) {
+        return Err(KgError::invalid_field("edge_costs", error));
+    }
+    for id in [query.source_id, query.target_id] {
+        if graph.get_node(&id).is_none() {
+            return Err(KgError::NodeNotFound(id));
+        }
+    }
+    let paths = graph.find_paths(&query);
 
-    match graph.find_path(&query.source_id, &query.target_id) {
-        Some(result) => Json(serde_json::json!(result)).into_response(),
-        None => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "No path found" })),
-        )
-            .into_response(),
+    match query.mode.unwrap_or_default() {
+        PathMode::Shortest => {
+            let result = paths.into_iter().next().ok_or(KgError::PathNotFound)?;
+            Ok(Json(result).into_response())
+        }
+        _ => Ok(Json(serde_json::json!({
+            "paths": paths,
+            "count": paths.len()
+        }))
+        .into_response()),
     }
 }
 
 pub async fn find_neighbors(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Json(query): Json<NeighborsQuery>,
-) -> impl IntoResponse {
-    let graph = state.graph.read().await;
+) -> Result<impl IntoResponse, KgError> {
+    if let Some(error) = depth_error(query.depth) {
+        return Err(KgError::invalid_field("depth", error));
+    }
+    let graph = scope.graph.read().await;
+    if let Some(error) = graph
+        .schema()
+        .edge_filter_error(query.edge_types.iter().flatten())
+    {
+        return Err(KgError::invalid_field("edge_types", error));
+    }
+    if graph.get_node(&query.node_id).is_none() {
+        return Err(KgError::NodeNotFound(query.node_id));
+    }
 
     let direction = query.direction.unwrap_or_default();
     let depth = query.depth.unwrap_or(1);
@@ -175,34 +275,438 @@ pub async fn find_neighbors(
 
     let neighbors = graph.find_neighbors(&query.node_id, edge_types, &direction, depth);
 
-    Json(serde_json::json!({
-        "neighbors": neighbors,
-        "count": neighbors.len()
-    }))
+    Ok(match query.format.unwrap_or_default() {
+        NeighborsFormat::List => Json(serde_json::json!({
+            "neighbors": neighbors,
+            "count": neighbors.len()
+        })),
+        NeighborsFormat::Subgraph => {
+            let node_ids: Vec<Uuid> = std::iter::once(query.node_id)
+                .chain(neighbors.iter().map(|n| n.node.id))
+                .collect();
+            let subgraph = graph.subgraph(&node_ids, edge_types);
+            Json(serde_json::json!({
+                "root_id": query.node_id,
+                "nodes": subgraph.nodes,
+                "edges": subgraph.edges
+            }))
+        }
+    })
 }
 
 pub async fn search_nodes(
-    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
     Json(query): Json<SearchQuery>,
-) -> impl IntoResponse {
-    let graph = state.graph.read().await;
+) -> Result<impl IntoResponse, KgError> {
+    if let Some(error) = search::cursor_error(query.cursor.as_deref()) {
+        return Err(KgError::invalid_field("cursor", error));
+    }
+    let graph = scope.graph.read().await;
+    if let Some(error) = graph
+        .schema()
+        .node_filter_error(query.node_types.iter().flatten())
+    {
+        return Err(KgError::invalid_field("node_types", error));
+    }
 
-    let limit = query.limit.unwrap_or(20);
-    let node_types = query.node_types.as_deref();
-    let language = query.language.as_deref();
+    if let Some(hybrid) = &query.hybrid {
+        let dimension = graph.embedding_dimension();
+        if let Some(error) = embedding_error(hybrid.vector.as_deref(), dimension) {
+            return Err(KgError::invalid_field("hybrid.vector", error));
+        }
+        if let Some(error) = depth_error(hybrid.anchor_depth) {
+            return Err(KgError::invalid_field("hybrid.anchor_depth", error));
+        }
+        if let Some(error) = graph
+            .schema()
+            .edge_filter_error(hybrid.edge_types.iter().flatten())
+        {
+            return Err(KgError::invalid_field("hybrid.edge_types", error));
+        }
+        let anchors = hybrid.anchors.as_deref().unwrap_or_default();
+        if let Some(&id) = anchors.iter().find(|id| graph.get_node(id).is_none()) {
+            return Err(KgError::ReferenceNotFound {
+                field: "hybrid.anchors",
+                id,
+            });
+        }
+    }
 
-    let results = graph.search_nodes(&query.query, node_types, language, limit);
+    Ok(Json(graph.search_nodes(&query)))
+}
 
-    Json(serde_json::json!({
+pub async fn find_similar(
+    scope: TenantGraph,
+    Json(query): Json<SimilarQuery>,
+) -> Result<impl IntoResponse, KgError> {
+    let graph = scope.graph.read().await;
+
+    if let Some(error) = embedding_error(Some(&query.vector), graph.embedding_dimension()) {
+        return Err(KgError::invalid_field("vector", error));
+    }
+    if let Some(error) = graph
+        .schema()
+        .node_filter_error(query.node_types.iter().flatten())
+    {
+        return Err(KgError::invalid_field("node_types", error));
+    }
+
+    let results = graph.find_similar(&query);
+    Ok(Json(serde_json::json!({
         "results": results,
         "count": results.len()
-    }))
+    })))
 }
 
-pub async fn get_stats(
+/// Streams the whole graph as NDJSON, nodes first. The read lock is taken
+/// per chunk, so a slow client never holds it; records are written as they
+/// are when their chunk is read.
+pub async fn export_graph(scope: TenantGraph) -> impl IntoResponse {
+    let order = transfer::export_order(&*scope.graph.read().await);
+
+    let state = (scope.graph, order, 0);
+    let chunks = stream::unfold(state, |(graph, order, position)| async move {
+        if position >= order.len() {
+            return None;
+        }
+        let end = (position + EXPORT_CHUNK).min(order.len());
+        let mut chunk = Vec::new();
+        {
+            let graph = graph.read().await;
+            for id in &order[position..end] {
+                transfer::write_record(&graph, id, &mut chunk);
+            }
+        }
+        Some((Ok::<_, Infallible>(chunk), (graph, order, end)))
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(chunks),
+    )
+}
+
+/// Loads gkg output for one repository, or for every repository under the
+/// gkg data dir when none is named, into the tenant's graph. Only
+/// repositories owned by the tenant's org are loaded.
+pub async fn ingest_gkg(
     State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
+    body: Option<Json<IngestRequest>>,
+) -> Result<impl IntoResponse, KgError> {
+    let repositories = {
+        let registry = state.registry.read().await;
+        let owned = |repository: &str| {
+            registry.owner(repository, &state.tenants.default_org) == scope.tenant.org_id
+        };
+        match body.and_then(|Json(request)| request.repository) {
+            Some(repository)
+                if !owned(&repository) || state.gkg.repository_dir(&repository).is_none() =>
+            {
+                return Err(KgError::GkgOutputNotFound(repository));
+            }
+            Some(repository) => vec![repository],
+            None => state
+                .gkg
+                .repositories()
+                .into_iter()
+                .filter(|r| owned(r))
+                .collect(),
+        }
+    };
+
+    let mut reports = Vec::with_capacity(repositories.len());
+    for repository in &repositories {
+        reports.push(gkg::ingest_repository(&scope.graph, &state.gkg, repository).await?);
+    }
+    Ok(Json(serde_json::json!({
+        "reports": reports,
+        "count": reports.len()
+    })))
+}
+
+/// Whether the tenant's org owns repository `name`.
+fn owns(state: &AppState, registry: &Registry, scope: &TenantGraph, name: &str) -> bool {
+    registry.owner(name, &state.tenants.default_org) == scope.tenant.org_id
+}
+
+/// The repositories owned by the tenant's org.
+pub async fn list_repositories(
+    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
 ) -> impl IntoResponse {
-    let graph = state.graph.read().await;
+    let registry = state.registry.read().await;
+    let entries: Vec<RepositoryEntry> = registry
+        .repositories()
+        .iter()
+        .filter(|entry| owns(&state, &registry, &scope, &entry.name))
+        .cloned()
+        .collect();
+    let graph = scope.graph.read().await;
+    let repositories = registry::statuses(&graph, &state.indexer, &entries);
+
+    Json(serde_json::json!({
+        "count": repositories.len(),
+        "repositories": repositories,
+    }))
+}
+
+/// Adds a repository to `config/repos.json`, owned by the tenant's org, and
+/// creates its `Repository` node. The sync script clones it on its next run.
+pub async fn register_repository(
+    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
+    Json(mut entry): Json<RepositoryEntry>,
+) -> Result<impl IntoResponse, KgError> {
+    if !registry::is_valid_name(&entry.name) {
+        return Err(KgError::invalid_field(
+            "name",
+            "not a valid repository name",
+        ));
+    }
+
+    entry.org_id = Some(scope.tenant.org_id.clone());
+    let mut registry = state.registry.write().await;
+    if registry.get(&entry.name).is_some() {
+        return Err(KgError::RepositoryExists(entry.name));
+    }
+    registry.register(entry.clone())?;
+
+    let mut graph = scope.graph.write().await;
+    registry::ensure_node(&mut graph, &entry.name)?;
+    let status = registry::statuses(&graph, &state.indexer, &[entry]).remove(0);
+    Ok((StatusCode::CREATED, Json(status)))
+}
+
+pub async fn get_repository(
+    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, KgError> {
+    let registry = state.registry.read().await;
+    let entry = registry
+        .get(&name)
+        .filter(|_| owns(&state, &registry, &scope, &name))
+        .ok_or_else(|| KgError::RepositoryNotFound(name.clone()))?;
+
+    let graph = scope.graph.read().await;
+    let status = registry::statuses(&graph, &state.indexer, std::slice::from_ref(entry)).remove(0);
+    Ok(Json(status))
+}
+
+/// Removes a repository from the registry along with its nodes. The checkout
+/// under `REPOS_DIR` is left for the operator to delete.
+pub async fn delete_repository(
+    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, KgError> {
+    let mut registry = state.registry.write().await;
+    if !owns(&state, &registry, &scope, &name) || !registry.remove(&name)? {
+        return Err(KgError::RepositoryNotFound(name));
+    }
+
+    let removed = registry::remove_nodes(&mut *scope.graph.write().await, &name)?;
+    Ok(Json(serde_json::json!({
+        "status": "deleted",
+        "name": name,
+        "nodes_removed": removed,
+    })))
+}
+
+/// Parses a checkout under `REPOS_DIR` with the built-in indexer. Only files
+/// changed since the indexed commit are re-read unless `full` is set.
+pub async fn index_repository(
+    State(state): State<Arc<AppState>>,
+    scope: TenantGraph,
+    Path(name): Path<String>,
+    Query(options): Query<IndexOptions>,
+) -> Result<impl IntoResponse, KgError> {
+    let owned = owns(&state, &*state.registry.read().await, &scope, &name);
+    let Some(root) = state.indexer.repository_dir(&name).filter(|_| owned) else {
+        if owned {
+            let error = KgError::CheckoutNotFound(name.clone()).to_string();
+            registry::record_error(&mut *scope.graph.write().await, &name, &error)?;
+        }
+        return Err(KgError::CheckoutNotFound(name));
+    };
+
+    // Changes are detected without the lock held, so another index of the
+    // same repository may land first; detect again against what it left,
+    // and under the lock once that keeps happening.
+    for _ in 1..INDEX_ATTEMPTS {
+        let previous = indexer::indexed_state(&*scope.graph.read().await, &name);
+        let detected = indexer::detect_changes(root.clone(), previous.clone(), options.full).await;
+        let mut graph = scope.graph.write().await;
+        if indexer::indexed_state(&graph, &name) == previous {
+            return apply_changes(&mut graph, &name, detected);
+        }
+    }
+    let mut graph = scope.graph.write().await;
+    let previous = indexer::indexed_state(&graph, &name);
+    let detected = indexer::detect_changes(root, previous, options.full).await;
+    apply_changes(&mut graph, &name, detected)
+}
+
+fn apply_changes(
+    graph: &mut KnowledgeGraph,
+    name: &str,
+    detected: anyhow::Result<indexer::Changes>,
+) -> Result<Json<IndexReport>, KgError> {
+    match detected {
+        Ok(changes) => Ok(Json(indexer::index_repository(graph, name, &changes)?)),
+        Err(err) => {
+            registry::record_error(graph, name, &err.to_string())?;
+            Err(err.into())
+        }
+    }
+}
+
+/// Renders the whole graph, or the subgraph selected by the query, as
+/// GraphML, DOT or Mermaid text.
+pub async fn render_graph(
+    scope: TenantGraph,
+    Json(query): Json<RenderQuery>,
+) -> Result<impl IntoResponse, KgError> {
+    if let Some(error) = depth_error(query.depth) {
+        return Err(KgError::invalid_field("depth", error));
+    }
+    let graph = scope.graph.read().await;
+    if let Some(error) = graph
+        .schema()
+        .edge_filter_error(query.edge_types.iter().flatten())
+    {
+        return Err(KgError::invalid_field("edge_types", error));
+    }
+
+    let Some(subgraph) = render::select(&graph, &query) else {
+        // Only a missing root leaves nothing to select.
+        return Err(KgError::ReferenceNotFound {
+            field: "root_id",
+            id: query.root_id.unwrap_or_default(),
+        });
+    };
+
+    Ok((
+        [(header::CONTENT_TYPE, render::content_type(query.format))],
+        render::render(&subgraph, query.format, graph.schema()),
+    ))
+}
+
+/// Reads an NDJSON export line by line, applying it in batches so neither
+/// the body nor the lock is held for the whole upload.
+pub async fn import_graph(
+    scope: TenantGraph,
+    Query(options): Query<ImportOptions>,
+    body: Body,
+) -> Result<impl IntoResponse, KgError> {
+    let mut importer = Importer::new(options);
+    let mut data = body.into_data_stream();
+    let mut splitter = LineSplitter::new();
+    let mut batch: Vec<(usize, Option<String>)> = Vec::new();
+    let mut line_no = 0;
+
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk.map_err(|err| KgError::InvalidBody(err.to_string()))?;
+        for line in splitter.push(&chunk) {
+            line_no += 1;
+            batch.push((line_no, line));
+        }
+        if batch.len() >= IMPORT_BATCH {
+            import_batch(&scope.graph, &mut importer, &mut batch).await?;
+        }
+    }
+    if let Some(line) = splitter.finish() {
+        batch.push((line_no + 1, line));
+    }
+    import_batch(&scope.graph, &mut importer, &mut batch).await?;
+
+    Ok(Json(importer.finish()))
+}
+
+async fn import_batch(
+    graph: &RwLock<KnowledgeGraph>,
+    importer: &mut Importer,
+    batch: &mut Vec<(usize, Option<String>)>,
+) -> Result<(), StoreError> {
+    if importer.dry_run() {
+        let graph = graph.read().await;
+        for (line_no, line) in batch.drain(..) {
+            match line {
+                Some(line) => importer.check_line(&graph, line_no, &line),
+                None => importer.skip_long_line(line_no),
+            }
+        }
+    } else {
+        let pending = {
+            let mut graph = graph.write().await;
+            for (line_no, line) in batch.drain(..) {
+                match line {
+                    Some(line) => importer.import_line(&mut graph, line_no, &line)?,
+                    None => importer.skip_long_line(line_no),
+                }
+            }
+            graph.pending_writes()
+        };
+        // Keeps a long import from running ahead of the database.
+        if let Some(pending) = pending {
+            pending.wait().await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_stats(scope: TenantGraph) -> impl IntoResponse {
+    let graph = scope.graph.read().await;
     let stats = graph.get_stats();
     Json(stats)
 }
+
+/// The schema writes are checked against.
+pub async fn get_schema(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.tenants.schema.as_ref().clone())
+}
+
+/// Audits the tenant's graph against the schema, reporting records written
+/// before a rule existed and nodes missing required edges.
+pub async fn validate_graph(scope: TenantGraph) -> impl IntoResponse {
+    let graph = scope.graph.read().await;
+    Json(graph.validate())
+}
+
+/// Every graph of the tenant's org: the org graph and any per-repository
+/// graphs.
+pub async fn list_graphs(
+    State(state): State<Arc<AppState>>,
+    tenant: Tenant,
+) -> Result<impl IntoResponse, KgError> {
+    let graphs = state.tenants.list(&tenant.org_id).await?;
+    Ok(Json(serde_json::json!({
+        "count": graphs.len(),
+        "graphs": graphs,
+    })))
+}
+
+pub async fn get_graph(scope: TenantGraph) -> impl IntoResponse {
+    let graph = scope.graph.read().await;
+    Json(serde_json::json!({
+        "name": scope.tenant.graph_name(),
+        "tenant": scope.tenant,
+        "stats": graph.get_stats(),
+    }))
+}
+
+/// Drops the tenant's graph and its stored records. Registry entries are
+/// kept; re-indexing a repository rebuilds its nodes.
+pub async fn delete_graph(
+    State(state): State<Arc<AppState>>,
+    tenant: Tenant,
+) -> Result<impl IntoResponse, KgError> {
+    if !state.tenants.remove(&tenant).await? {
+        return Err(KgError::GraphNotFound(tenant.graph_name()));
+    }
+    Ok(Json(serde_json::json!({
+        "status": "deleted",
+        "name": tenant.graph_name(),
+    })))
+}
@@ -1 +1,4 @@
+pub mod auth;
+pub mod extract;
 pub mod handlers;
+pub mod tenant;
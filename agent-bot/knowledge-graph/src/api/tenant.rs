@@ -0,0 +1,135 @@
+//! Tenant scoping for `/api/v1` routes.
+//!
+//! Every request names its tenant in the `X-Org-Id` header, optionally
+//! narrowed by `X-Repository`, and handlers only ever see that tenant's
+//! graph. Either header may be left out when the caller's credentials
+//! name the org or repository, and a tenant the credentials do not cover
+//! is rejected.
+
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, Extensions, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::RwLock;
+
+use crate::{
+    error::KgError,
+    models::{Principal, Scope, Tenant},
+    services::graph::KnowledgeGraph,
+    AppState,
+};
+
+pub const ORG_HEADER: &str = "x-org-id";
+pub const REPOSITORY_HEADER: &str = "x-repository";
+
+fn tenant_from_headers(headers: &HeaderMap, principal: &Principal) -> Result<Tenant, KgError> {
+    let header = |name: &'static str, field: &'static str| -> Result<Option<&str>, KgError> {
+        headers
+            .get(name)
+            .map(|value| {
+                value.to_str().map_err(|_| KgError::InvalidTenant {
+                    field,
+                    value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                })
+            })
+            .transpose()
+    };
+    let org_id = match header(ORG_HEADER, "org_id")? {
+        Some(org_id) => org_id,
+        None if principal.org_id != Principal::ANY_ORG => &principal.org_id,
+        None => return Err(KgError::MissingTenant),
+    };
+    let repository = header(REPOSITORY_HEADER, "repository")?.or(principal.repository.as_deref());
+    Tenant::new(org_id, repository)
+}
+
+/// The tenant named by the request, if its principal may act for it.
+fn tenant_from_parts(parts: &Parts) -> Result<Tenant, KgError> {
+    tenant_from_request(&parts.headers, &parts.extensions)
+}
+
+fn tenant_from_request(headers: &HeaderMap, extensions: &Extensions) -> Result<Tenant, KgError> {
+    let Some(principal) = extensions.get::<Principal>() else {
+        return Err(KgError::Unauthorized("Missing credentials".to_string()));
+    };
+    let tenant = tenant_from_headers(headers, principal)?;
+    if !principal.allows(&tenant) {
+        let denied = format!("Credentials do not cover graph {}", tenant.graph_name());
+        return Err(KgError::Forbidden(denied));
+    }
+    Ok(tenant)
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Tenant {
+    type Rejection = KgError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        tenant_from_parts(parts)
+    }
+}
+
+/// The request's tenant together with its graph. Routes that require write
+/// scope create the graph if needed; other routes get `graph_not_found` for
+/// a tenant without one.
+pub struct TenantGraph {
+    pub tenant: Tenant,
+    pub graph: Arc<RwLock<KnowledgeGraph>>,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for TenantGraph {
+    type Rejection = KgError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let tenant = tenant_from_parts(parts)?;
+        let writes = parts
+            .extensions
+            .get::<Scope>()
+            .is_some_and(|scope| *scope >= Scope::Write);
+        let graph = if writes {
+            state.tenants.graph(&tenant).await?
+        } else {
+            let graph = state.tenants.existing(&tenant).await?;
+            graph.ok_or_else(|| KgError::GraphNotFound(tenant.graph_name()))?
+        };
+        Ok(Self { tenant, graph })
+    }
+}
+
+/// Holds the response of a write route until the tenant graph's queued
+/// writes have been persisted, so a write is only acknowledged once it is
+/// stored. A write that fails to persist turns the response into a
+/// `storage` error.
+pub async fn await_writes(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let tenant = tenant_from_request(request.headers(), request.extensions()).ok();
+    let response = next.run(request).await;
+    let Some(tenant) = tenant else {
+        return response;
+    };
+    let Some(graph) = state.tenants.loaded_graph(&tenant).await else {
+        return response;
+    };
+    let Some(pending) = graph.read().await.pending_writes() else {
+        return response;
+    };
+    match pending.wait().await {
+        Ok(()) => response,
+        Err(err) => KgError::from(err).into_response(),
+    }
+}
@@ -0,0 +1,282 @@
+//! Errors returned by the API.
+//!
+//! Every error renders as the same JSON envelope: a readable `error`
+//! message, a stable `code` clients can match on, and the offending
+//! `field` or `id` when there is one.
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::models::{FailureKind, TransactionError, Violation};
+use crate::services::graph::PartialUpsert;
+use crate::services::store::StoreError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KgError {
+    #[error("Malformed JSON body: {0}")]
+    MalformedJson(String),
+    #[error("Expected a request with Content-Type: application/json")]
+    UnsupportedMediaType,
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+    #[error("Request body too large")]
+    PayloadTooLarge,
+    #[error("Invalid {field}: {message}")]
+    InvalidField { field: String, message: String },
+    #[error("Invalid {field} in path: {message}")]
+    InvalidPath { field: String, message: String },
+    #[error("Invalid query string: {0}")]
+    InvalidQuery(String),
+    #[error("Invalid {field}: {value:?}")]
+    InvalidTenant { field: &'static str, value: String },
+    #[error("Missing X-Org-Id header")]
+    MissingTenant,
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("Node not found")]
+    NodeNotFound(Uuid),
+    #[error("Edge not found")]
+    EdgeNotFound(Uuid),
+    /// A node named in the request body does not exist.
+    #[error("Node {id} in {field} not found")]
+    ReferenceNotFound { field: &'static str, id: Uuid },
+    #[error("No path found")]
+    PathNotFound,
+    #[error("Repository not registered")]
+    RepositoryNotFound(String),
+    #[error("Repository already registered")]
+    RepositoryExists(String),
+    #[error("Repository checkout not found")]
+    CheckoutNotFound(String),
+    #[error("Repository output not found")]
+    GkgOutputNotFound(String),
+    #[error("Graph not found")]
+    GraphNotFound(String),
+    #[error("{}", .0.error)]
+    Transaction(TransactionError),
+    /// The write would break the graph schema.
+    #[error("{0}")]
+    SchemaViolation(Violation),
+    /// The storage backend could not record a write; nothing was changed.
+    #[error(transparent)]
+    Storage(#[from] StoreError),
+    /// The store failed part way through a bulk upsert.
+    #[error(transparent)]
+    PartialUpsert(PartialUpsert),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl KgError {
+    pub fn invalid_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::InvalidField {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MalformedJson(_)
+            | Self::InvalidBody(_)
+            | Self::InvalidPath { .. }
+            | Self::InvalidQuery(_)
+            | Self::InvalidTenant { .. }
+            | Self::MissingTenant => StatusCode::BAD_REQUEST,
+            Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::InvalidField { .. }
+            | Self::ReferenceNotFound { .. }
+            | Self::SchemaViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NodeNotFound(_)
+            | Self::EdgeNotFound(_)
+            | Self::PathNotFound
+            | Self::RepositoryNotFound(_)
+            | Self::CheckoutNotFound(_)
+            | Self::GkgOutputNotFound(_)
+            | Self::GraphNotFound(_) => StatusCode::NOT_FOUND,
+            Self::RepositoryExists(_) => StatusCode::CONFLICT,
+            Self::Transaction(error) => match error.kind {
+                FailureKind::NodeNotFound | FailureKind::EdgeNotFound => StatusCode::NOT_FOUND,
+                FailureKind::ReferenceNotFound
+                | FailureKind::InvalidField
+                | FailureKind::SchemaViolation => StatusCode::UNPROCESSABLE_ENTITY,
+                FailureKind::Conflict => StatusCode::CONFLICT,
+                FailureKind::Storage => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            Self::Storage(_) | Self::PartialUpsert(_) | Self::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Machine-readable code; stable across releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MalformedJson(_) => "malformed_json",
+            Self::UnsupportedMediaType => "unsupported_media_type",
+            Self::InvalidBody(_) => "invalid_body",
+            Self::PayloadTooLarge => "payload_too_large",
+            Self::InvalidField { .. } => "invalid_field",
+            Self::InvalidPath { .. } => "invalid_path",
+            Self::InvalidQuery(_) => "invalid_query",
+            Self::InvalidTenant { .. } => "invalid_tenant",
+            Self::MissingTenant => "missing_tenant",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NodeNotFound(_) => "node_not_found",
+            Self::EdgeNotFound(_) => "edge_not_found",
+            Self::ReferenceNotFound { .. } => "reference_not_found",
+            Self::PathNotFound => "path_not_found",
+            Self::RepositoryNotFound(_) => "repository_not_found",
+            Self::RepositoryExists(_) => "repository_exists",
+            Self::CheckoutNotFound(_) => "checkout_not_found",
+            Self::GkgOutputNotFound(_) => "gkg_output_not_found",
+            Self::GraphNotFound(_) => "graph_not_found",
+            Self::Transaction(error) => match error.kind {
+                FailureKind::NodeNotFound => "node_not_found",
+                FailureKind::EdgeNotFound => "edge_not_found",
+                FailureKind::ReferenceNotFound => "reference_not_found",
+                FailureKind::InvalidField => "invalid_field",
+                FailureKind::SchemaViolation => "schema_violation",
+                FailureKind::Conflict => "transaction_failed",
+                FailureKind::Storage => "storage",
+            },
+            Self::SchemaViolation(_) => "schema_violation",
+            Self::Storage(_) | Self::PartialUpsert(_) => "storage",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// The request field at fault.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Self::InvalidField { field, .. } | Self::InvalidPath { field, .. } => Some(field),
+            Self::InvalidTenant { field, .. } | Self::ReferenceNotFound { field, .. } => {
+                Some(field)
+            }
+            Self::SchemaViolation(violation) => Some(&violation.field),
+            Self::Transaction(error) => error.field.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Id or name of the missing or conflicting record.
+    pub fn id(&self) -> Option<String> {
+        match self {
+            Self::NodeNotFound(id) | Self::EdgeNotFound(id) => Some(id.to_string()),
+            Self::ReferenceNotFound { id, .. } => Some(id.to_string()),
+            Self::RepositoryNotFound(name)
+            | Self::RepositoryExists(name)
+            | Self::CheckoutNotFound(name)
+            | Self::GkgOutputNotFound(name)
+            | Self::GraphNotFound(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for KgError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("{:#}", self);
+        }
+
+        let mut body = serde_json::json!({
+            "error": self.to_string(),
+            "code": self.code(),
+        });
+        if let Some(field) = self.field() {
+            body["field"] = field.into();
+        }
+        if let Some(id) = self.id() {
+            body["id"] = id.into();
+        }
+        if let Self::Transaction(error) = &self {
+            body["committed"] = false.into();
+            body["index"] = error.index.into();
+            body["op"] = error.op.clone().into();
+        }
+        if let Self::PartialUpsert(partial) = &self {
+            body["report"] = serde_json::json!(partial.report);
+        }
+        if let Self::SchemaViolation(violation) = &self {
+            body["rule"] = serde_json::json!(violation.rule);
+        }
+
+        if let Self::Unauthorized(_) = self {
+            return (status, [(header::WWW_AUTHENTICATE, "Bearer")], Json(body)).into_response();
+        }
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<Violation> for KgError {
+    fn from(violation: Violation) -> Self {
+        Self::SchemaViolation(violation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(error: KgError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_envelope() {
+        let id = Uuid::new_v4();
+        let (status, json) = body(KgError::ReferenceNotFound {
+            field: "target_id",
+            id,
+        })
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["code"], "reference_not_found");
+        assert_eq!(json["field"], "target_id");
+        assert_eq!(json["id"], id.to_string());
+
+        let failed = TransactionError {
+            index: 2,
+            op: "create_edge".to_string(),
+            error: format!("Node {} in source_id not found", id),
+            kind: FailureKind::ReferenceNotFound,
+            field: Some("source_id".to_string()),
+        };
+        let (status, json) = body(KgError::Transaction(failed)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["code"], "reference_not_found");
+        assert_eq!(json["field"], "source_id");
+        assert_eq!(
+            (json["committed"].clone(), json["index"].clone()),
+            (false.into(), 2.into())
+        );
+
+        let (status, json) = body(KgError::Storage(StoreError::WriterStopped)).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(json["code"], "storage");
+
+        let (status, json) = body(KgError::Internal(anyhow::anyhow!("disk full"))).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            (json["error"].as_str(), json.get("field")),
+            (Some("disk full"), None)
+        );
+    }
+}
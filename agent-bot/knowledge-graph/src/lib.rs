@@ -0,0 +1,20 @@
+pub mod api;
+pub mod error;
+pub mod models;
+pub mod services;
+
+use tokio::sync::RwLock;
+
+use services::auth::AuthConfig;
+use services::gkg::GkgConfig;
+use services::indexer::IndexerConfig;
+use services::registry::Registry;
+use services::tenants::Tenants;
+
+pub struct AppState {
+    pub tenants: Tenants,
+    pub auth: AuthConfig,
+    pub gkg: GkgConfig,
+    pub indexer: IndexerConfig,
+    pub registry: RwLock<Registry>,
+}
@@ -1,23 +1,30 @@
-mod api;
-mod models;
-mod services;
-
 use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{get, post},
     Router,
 };
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use services::graph::KnowledgeGraph;
+use knowledge_graph::api;
+use knowledge_graph::models::{Scope, Tenant};
+use knowledge_graph::services::auth::AuthConfig;
+use knowledge_graph::services::gkg::{self, GkgConfig};
+use knowledge_graph::services::graph::KnowledgeGraph;
+use knowledge_graph::services::indexer::IndexerConfig;
+use knowledge_graph::services::registry::{self, Registry};
+use knowledge_graph::services::schema::GraphSchema;
+use knowledge_graph::services::store::StorageConfig;
+use knowledge_graph::services::tenants::Tenants;
+use knowledge_graph::AppState;
 
-pub struct AppState {
-    pub graph: Arc<RwLock<KnowledgeGraph>>,
-}
+/// Request body limit for bulk upserts, which carry whole indexing runs.
+const BULK_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
 #[tokio::main]
 async fn main() {
@@ -31,24 +38,192 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let graph = Arc::new(RwLock::new(KnowledgeGraph::new()));
-    let state = Arc::new(AppState { graph });
+    let storage_config = StorageConfig::from_env().expect("Invalid storage configuration");
+    let storage = storage_config
+        .open()
+        .await
+        .expect("Failed to open graph storage");
+    tracing::info!("Using {:?} storage", storage_config);
 
-    let app = Router::new()
-        .route("/health", get(api::handlers::health_check))
+    let default_org = std::env::var("DEFAULT_ORG_ID").unwrap_or_else(|_| "default".to_string());
+    Tenant::new(&default_org, None).expect("Invalid DEFAULT_ORG_ID");
+    let schema = GraphSchema::from_env().expect("Invalid graph schema");
+    let tenants = Tenants::new(storage, default_org, schema);
+
+    let registry = Registry::from_env().expect("Invalid repository registry");
+    for entry in registry.repositories() {
+        let graph = owner_graph(&tenants, &registry, &entry.name).await;
+        registry::ensure_node(&mut *graph.write().await, &entry.name)
+            .expect("Failed to record registered repository");
+    }
+    tracing::info!("{} registered repositories", registry.repositories().len());
+
+    let auth = AuthConfig::from_env().expect("Invalid authentication configuration");
+    if auth.disabled {
+        tracing::warn!("Authentication is disabled; every request acts as admin of every org");
+    } else if !auth.has_credentials() {
+        tracing::warn!("No API keys or JWT keys configured; every API request will be rejected");
+    }
+    let cors = api::auth::cors_from_env().expect("Invalid CORS_ALLOWED_ORIGINS");
+
+    let gkg = GkgConfig::from_env();
+    let state = Arc::new(AppState {
+        tenants,
+        auth,
+        gkg: gkg.clone(),
+        indexer: IndexerConfig::from_env(),
+        registry: RwLock::new(registry),
+    });
+
+    let idle_secs: u64 = std::env::var("GRAPH_IDLE_TIMEOUT_SECS")
+        .unwrap_or_else(|_| "1800".to_string())
+        .parse()
+        .expect("GRAPH_IDLE_TIMEOUT_SECS must be a number of seconds");
+    if idle_secs > 0 {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let idle = Duration::from_secs(idle_secs);
+            let mut ticks = tokio::time::interval(idle.min(Duration::from_secs(60)));
+            loop {
+                ticks.tick().await;
+                state.tenants.evict_idle(idle).await;
+            }
+        });
+    }
+
+    if gkg.ingest_on_startup {
+        let state = state.clone();
+        tokio::spawn(async move {
+            for repository in gkg.repositories() {
+                let graph =
+                    owner_graph(&state.tenants, &*state.registry.read().await, &repository).await;
+                let report = match gkg::ingest_repository(&graph, &gkg, &repository).await {
+                    Ok(report) => report,
+                    Err(err) => {
+                        tracing::error!("gkg ingest of {} failed: {}", repository, err);
+                        continue;
+                    }
+                };
+                match &report.error {
+                    Some(error) => tracing::warn!("gkg ingest of {} failed: {}", repository, error),
+                    None => tracing::info!(
+                        "Ingested gkg output for {}: {} nodes, {} edges",
+                        repository,
+                        report.nodes,
+                        report.edges
+                    ),
+                }
+            }
+        });
+    }
+
+    let read = Router::new()
         .route("/api/v1/nodes", get(api::handlers::list_nodes))
-        .route("/api/v1/nodes", post(api::handlers::create_node))
         .route("/api/v1/nodes/:id", get(api::handlers::get_node))
-        .route("/api/v1/nodes/:id", axum::routing::delete(api::handlers::delete_node))
         .route("/api/v1/edges", get(api::handlers::list_edges))
-        .route("/api/v1/edges", post(api::handlers::create_edge))
         .route("/api/v1/query/path", post(api::handlers::find_path))
-        .route("/api/v1/query/neighbors", post(api::handlers::find_neighbors))
+        .route(
+            "/api/v1/query/neighbors",
+            post(api::handlers::find_neighbors),
+        )
         .route("/api/v1/query/search", post(api::handlers::search_nodes))
+        .route("/api/v1/query/similar", post(api::handlers::find_similar))
         .route("/api/v1/stats", get(api::handlers::get_stats))
-        .layer(CorsLayer::permissive())
+        .route("/api/v1/schema", get(api::handlers::get_schema))
+        .route("/api/v1/validate", get(api::handlers::validate_graph))
+        .route("/api/v1/graphs", get(api::handlers::list_graphs))
+        .route("/api/v1/graph", get(api::handlers::get_graph))
+        .route(
+            "/api/v1/repositories",
+            get(api::handlers::list_repositories),
+        )
+        .route(
+            "/api/v1/repositories/:name",
+            get(api::handlers::get_repository),
+        )
+        .route("/api/v1/export", get(api::handlers::export_graph))
+        .route("/api/v1/export/render", post(api::handlers::render_graph))
+        .route_layer(middleware::from_fn_with_state(
+            Scope::Read,
+            api::auth::require,
+        ));
+
+    let write = Router::new()
+        .route("/api/v1/nodes", post(api::handlers::create_node))
+        .route(
+            "/api/v1/bulk",
+            post(api::handlers::bulk_upsert).layer(DefaultBodyLimit::max(BULK_BODY_LIMIT)),
+        )
+        .route(
+            "/api/v1/nodes/:id",
+            axum::routing::delete(api::handlers::delete_node),
+        )
+        .route(
+            "/api/v1/transactions",
+            post(api::handlers::apply_transaction).layer(DefaultBodyLimit::max(BULK_BODY_LIMIT)),
+        )
+        .route("/api/v1/edges", post(api::handlers::create_edge))
+        .route(
+            "/api/v1/edges/:id",
+            axum::routing::patch(api::handlers::update_edge),
+        )
+        .route(
+            "/api/v1/edges/:id",
+            axum::routing::delete(api::handlers::delete_edge),
+        )
+        .route(
+            "/api/v1/repositories/:name/index",
+            post(api::handlers::index_repository),
+        )
+        .route(
+            "/api/v1/import",
+            post(api::handlers::import_graph).layer(DefaultBodyLimit::disable()),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api::tenant::await_writes,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            Scope::Write,
+            api::auth::require,
+        ));
+
+    let admin = Router::new()
+        .route(
+            "/api/v1/graph",
+            axum::routing::delete(api::handlers::delete_graph),
+        )
+        .route("/api/v1/admin/ingest/gkg", post(api::handlers::ingest_gkg))
+        .route(
+            "/api/v1/repositories",
+            post(api::handlers::register_repository),
+        )
+        .route(
+            "/api/v1/repositories/:name",
+            axum::routing::delete(api::handlers::delete_repository),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api::tenant::await_writes,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            Scope::Admin,
+            api::auth::require,
+        ));
+
+    let app = Router::new()
+        .route("/health", get(api::handlers::health_check))
+        .merge(
+            read.merge(write)
+                .merge(admin)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    api::auth::authenticate,
+                )),
+        )
+        .layer(cors)
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state.clone());
 
     let port: u16 = std::env::var("PORT")
         .unwrap_or_else(|_| "9010".to_string())
@@ -59,5 +234,50 @@ async fn main() {
     tracing::info!("Knowledge Graph starting on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    // Requests have finished; let background writers catch up before exiting.
+    state.tenants.flush().await;
+    tracing::info!("Knowledge Graph stopped");
+}
+
+/// Resolves on Ctrl-C or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl-C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutting down");
+}
+
+/// The org graph of the org that owns repository `name`.
+async fn owner_graph(
+    tenants: &Tenants,
+    registry: &Registry,
+    name: &str,
+) -> Arc<RwLock<KnowledgeGraph>> {
+    let org_id = registry.owner(name, &tenants.default_org);
+    let tenant = Tenant::new(org_id, None).expect("Invalid org id in repository registry");
+    tenants
+        .graph(&tenant)
+        .await
+        .expect("Failed to open graph storage")
 }
@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::Tenant;
+
+/// What a credential may do. Each scope includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Reads and queries.
+    Read,
+    /// Node, edge and index changes.
+    Write,
+    /// Registry changes, graph deletion and gkg ingestion.
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("Unknown scope: {}", other)),
+        }
+    }
+}
+
+/// An entry of the API key file. Only the SHA-256 of the key is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyEntry {
+    /// Name used in logs.
+    pub id: String,
+    /// Hex SHA-256 of the key.
+    pub sha256: String,
+    /// Org the key acts for. Unused when `all_orgs` is set.
+    #[serde(default)]
+    pub org_id: String,
+    /// Lets the key act for every org. The only way to grant that.
+    #[serde(default)]
+    pub all_orgs: bool,
+    /// Repository graph the key is limited to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    pub scope: Scope,
+}
+
+/// The caller behind a request's credentials.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Principal {
+    /// API key id or JWT subject.
+    pub subject: String,
+    /// Org the caller acts for; `*` for every org, which only anonymous
+    /// callers and `all_orgs` keys get.
+    pub org_id: String,
+    /// Repository graph the caller is limited to.
+    pub repository: Option<String>,
+    pub scope: Scope,
+}
+
+impl Principal {
+    pub const ANY_ORG: &'static str = "*";
+
+    /// The caller when authentication is disabled: an admin of every org.
+    pub fn anonymous() -> Self {
+        Self {
+            subject: "anonymous".to_string(),
+            org_id: Self::ANY_ORG.to_string(),
+            repository: None,
+            scope: Scope::Admin,
+        }
+    }
+
+    /// Whether the caller may act as `tenant`.
+    pub fn allows(&self, tenant: &Tenant) -> bool {
+        (self.org_id == Self::ANY_ORG || self.org_id == tenant.org_id)
+            && (self.repository.is_none() || self.repository == tenant.repository)
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{EdgeType, NodeType};
+
+/// Namespace for ids derived from natural keys. Changing it re-keys every
+/// upserted node and edge.
+const NATURAL_KEY_NAMESPACE: Uuid = Uuid::from_u128(0xd1a7_5039_c9e0_4b01_ad63_6b69_16a5_9942);
+
+/// Identity of a code entity that is stable across re-indexing runs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct NodeKey {
+    pub repository: String,
+    pub path: Option<String>,
+    pub qualified_name: String,
+    pub node_type: NodeType,
+}
+
+impl NodeKey {
+    /// The node id for this key; the same key always maps to the same id.
+    pub fn node_id(&self) -> Uuid {
+        let node_type = serde_json::to_value(&self.node_type)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let name = [
+            self.repository.as_str(),
+            self.path.as_deref().unwrap_or_default(),
+            self.qualified_name.as_str(),
+            node_type.as_str(),
+        ]
+        .join("\0");
+        Uuid::new_v5(&NATURAL_KEY_NAMESPACE, name.as_bytes())
+    }
+
+    /// Last segment of the qualified name, e.g. `retry` for `billing.Payment.retry`.
+    pub fn short_name(&self) -> &str {
+        self.qualified_name
+            .rsplit(['.', ':', '/', '#'])
+            .find(|segment| !segment.is_empty())
+            .unwrap_or(&self.qualified_name)
+    }
+}
+
+/// Id of the edge of `edge_type` from `source_id` to `target_id` when it is
+/// created by upsert, so repeating the upsert updates rather than duplicates.
+pub fn natural_edge_id(source_id: &Uuid, target_id: &Uuid, edge_type: &EdgeType) -> Uuid {
+    let edge_type = serde_json::to_value(edge_type)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let name = format!("{}\0{}\0{}", source_id, target_id, edge_type);
+    Uuid::new_v5(&NATURAL_KEY_NAMESPACE, name.as_bytes())
+}
+
+/// A node endpoint given either by id or by natural key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NodeRef {
+    Id(Uuid),
+    Key(NodeKey),
+}
+
+impl NodeRef {
+    pub fn node_id(&self) -> Uuid {
+        match self {
+            NodeRef::Id(id) => *id,
+            NodeRef::Key(key) => key.node_id(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertNode {
+    #[serde(flatten)]
+    pub key: NodeKey,
+    /// Defaults to the last segment of `qualified_name`.
+    pub name: Option<String>,
+    pub language: Option<String>,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertEdge {
+    pub source: NodeRef,
+    pub target: NodeRef,
+    pub edge_type: EdgeType,
+    pub weight: Option<f64>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkUpsertRequest {
+    #[serde(default)]
+    pub nodes: Vec<UpsertNode>,
+    #[serde(default)]
+    pub edges: Vec<UpsertEdge>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpsertStatus {
+    Created,
+    Updated,
+    Failed,
+}
+
+/// Outcome for one item, in request order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertResult {
+    pub index: usize,
+    pub status: UpsertStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkUpsertReport {
+    pub nodes: Vec<UpsertResult>,
+    pub edges: Vec<UpsertResult>,
+    pub created: usize,
+    pub updated: usize,
+    pub failed: usize,
+}
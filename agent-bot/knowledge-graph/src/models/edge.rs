@@ -16,6 +16,29 @@ pub enum EdgeType {
     References,
     Handles,
     Delegates,
+    /// A type registered in the graph schema.
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl EdgeType {
+    /// The name used in the API, e.g. `depends_on`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Contains => "contains",
+            Self::Imports => "imports",
+            Self::Calls => "calls",
+            Self::Inherits => "inherits",
+            Self::Implements => "implements",
+            Self::Uses => "uses",
+            Self::DependsOn => "depends_on",
+            Self::DefinedIn => "defined_in",
+            Self::References => "references",
+            Self::Handles => "handles",
+            Self::Delegates => "delegates",
+            Self::Custom(name) => name,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,6 +98,13 @@ impl From<CreateEdgeRequest> for Edge {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateEdgeRequest {
+    pub edge_type: Option<EdgeType>,
+    pub weight: Option<f64>,
+    pub metadata: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EdgeWithNodes {
     pub edge: Edge,
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IngestRequest {
+    /// Repository directory under the gkg data dir; all of them when unset.
+    pub repository: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IndexOptions {
+    /// Re-read every file instead of only those changed since the last run.
+    #[serde(default)]
+    pub full: bool,
+}
+
+/// Outcome of indexing one repository checkout.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexReport {
+    pub repository: String,
+    /// `HEAD` of the checkout, when it is a git repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_commit: Option<String>,
+    /// Whether only files in the diff from `previous_commit` were read.
+    pub incremental: bool,
+    /// Files parsed and replaced.
+    pub files: usize,
+    pub removed_files: usize,
+    pub nodes: usize,
+    pub edges: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub failed: usize,
+    /// Nodes of removed files and definitions that disappeared.
+    pub removed_nodes: usize,
+    /// Cross-file `calls` and `imports` edges added and removed on re-linking.
+    pub linked: usize,
+    pub unlinked: usize,
+}
+
+/// Outcome of loading one repository's gkg output.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub repository: String,
+    pub nodes: usize,
+    pub edges: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub failed: usize,
+    /// gkg relationships with no matching `EdgeType` or unknown endpoints.
+    pub skipped: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
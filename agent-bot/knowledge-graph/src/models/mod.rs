@@ -1,7 +1,23 @@
-mod node;
+mod auth;
+mod bulk;
 mod edge;
+mod ingest;
+mod node;
 mod query;
+mod registry;
+mod schema;
+mod tenant;
+mod transaction;
+mod transfer;
 
-pub use node::*;
+pub use auth::*;
+pub use bulk::*;
 pub use edge::*;
+pub use ingest::*;
+pub use node::*;
 pub use query::*;
+pub use registry::*;
+pub use schema::*;
+pub use tenant::*;
+pub use transaction::*;
+pub use transfer::*;
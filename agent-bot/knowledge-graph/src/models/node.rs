@@ -16,6 +16,29 @@ pub enum NodeType {
     Agent,
     Skill,
     Task,
+    /// A type registered in the graph schema, such as `method`.
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl NodeType {
+    /// The name used in the API, e.g. `function`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Repository => "repository",
+            Self::File => "file",
+            Self::Function => "function",
+            Self::Class => "class",
+            Self::Module => "module",
+            Self::Variable => "variable",
+            Self::Constant => "constant",
+            Self::Import => "import",
+            Self::Agent => "agent",
+            Self::Skill => "skill",
+            Self::Task => "task",
+            Self::Custom(name) => name,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +50,9 @@ pub struct Node {
     pub language: Option<String>,
     pub description: Option<String>,
     pub metadata: serde_json::Value,
+    /// Client-supplied embedding used by similarity queries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -42,6 +68,7 @@ impl Node {
             language: None,
             description: None,
             metadata: serde_json::json!({}),
+            embedding: None,
             created_at: now,
             updated_at: now,
         }
@@ -66,6 +93,11 @@ impl Node {
         self.metadata = metadata;
         self
     }
+
+    pub fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +108,7 @@ pub struct CreateNodeRequest {
     pub language: Option<String>,
     pub description: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub embedding: Option<Vec<f32>>,
 }
 
 impl From<CreateNodeRequest> for Node {
@@ -93,10 +126,23 @@ impl From<CreateNodeRequest> for Node {
         if let Some(metadata) = req.metadata {
             node = node.with_metadata(metadata);
         }
+        if let Some(embedding) = req.embedding {
+            node = node.with_embedding(embedding);
+        }
         node
     }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateNodeRequest {
+    pub name: Option<String>,
+    pub path: Option<String>,
+    pub language: Option<String>,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub embedding: Option<Vec<f32>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeResponse {
     pub node: Node,
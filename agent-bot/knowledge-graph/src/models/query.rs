@@ -1,13 +1,50 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use super::{EdgeType, NodeType};
+use super::{Edge, EdgeType, Node, NodeType};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PathQuery {
     pub source_id: Uuid,
     pub target_id: Uuid,
+    /// Maximum number of edges in a returned path.
     pub max_depth: Option<usize>,
+    pub edge_types: Option<Vec<EdgeType>>,
+    /// Which way edges may be walked; defaults to `outgoing`.
+    pub direction: Option<Direction>,
+    /// Multiplier applied to the weight of edges of each type.
+    pub edge_costs: Option<HashMap<EdgeType, f64>>,
+    pub mode: Option<PathMode>,
+    /// Number of paths for `k_shortest` and `all_simple`.
+    pub k: Option<usize>,
+}
+
+impl PathQuery {
+    pub fn new(source_id: Uuid, target_id: Uuid) -> Self {
+        Self {
+            source_id,
+            target_id,
+            max_depth: None,
+            edge_types: None,
+            direction: None,
+            edge_costs: None,
+            mode: None,
+            k: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathMode {
+    /// The single cheapest path.
+    #[default]
+    Shortest,
+    /// The `k` cheapest loopless paths (Yen's algorithm).
+    KShortest,
+    /// Up to `k` loopless paths within `max_depth`, cheapest first.
+    AllSimple,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,34 +53,195 @@ pub struct NeighborsQuery {
     pub edge_types: Option<Vec<EdgeType>>,
     pub direction: Option<Direction>,
     pub depth: Option<usize>,
+    pub format: Option<NeighborsFormat>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighborsFormat {
+    /// One entry per reached node with the edge it was reached through.
+    #[default]
+    List,
+    /// The reached nodes plus every matching edge between them.
+    Subgraph,
+}
+
+/// A node reached by a neighbor traversal and how it was reached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Neighbor {
+    pub node: Node,
+    pub depth: usize,
+    pub parent_id: Uuid,
+    pub edge: Edge,
+    /// Direction the edge was walked from `parent_id`: `outgoing` when the
+    /// parent is the edge source, `incoming` when it is the target.
+    pub direction: Direction,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subgraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Direction {
     Incoming,
     Outgoing,
+    #[default]
     Both,
 }
 
-impl Default for Direction {
-    fn default() -> Self {
-        Self::Both
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub query: String,
     pub node_types: Option<Vec<NodeType>>,
     pub language: Option<String>,
     pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    /// `next_cursor` from a previous page; takes precedence over `offset`.
+    pub cursor: Option<String>,
+    pub mode: Option<SearchMode>,
+    /// Fuse text relevance with embedding similarity and graph proximity.
+    pub hybrid: Option<HybridOptions>,
+}
+
+/// How query tokens are matched against indexed terms.
+///
+/// Both sides are split into identifier tokens first, so `handle auth`,
+/// `HandleAuth` and `handle_auth` are the same query in every mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Only nodes whose whole name tokenizes to the query.
+    Exact,
+    /// Query tokens match terms starting with them.
+    Prefix,
+    /// Query tokens match terms within a small edit distance.
+    Fuzzy,
+    /// Query tokens match equal terms.
+    #[default]
+    Token,
+}
+
+impl SearchQuery {
+    pub fn new(query: String) -> Self {
+        Self {
+            query,
+            node_types: None,
+            language: None,
+            limit: None,
+            offset: None,
+            cursor: None,
+            mode: None,
+            hybrid: None,
+        }
+    }
+}
+
+/// Nearest-neighbor lookup over node embeddings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarQuery {
+    pub vector: Vec<f32>,
+    pub node_types: Option<Vec<NodeType>>,
+    pub language: Option<String>,
+    pub k: Option<usize>,
+    /// HNSW beam width; larger trades latency for recall.
+    pub ef: Option<usize>,
+}
+
+/// Extra ranking signals for a hybrid search. Each signal ranks candidates
+/// on its own; the ranks are combined with weighted reciprocal-rank fusion.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HybridOptions {
+    /// Query embedding; candidates are ranked by cosine similarity.
+    pub vector: Option<Vec<f32>>,
+    /// Nodes to stay close to; candidates are ranked by hop distance to the
+    /// nearest anchor.
+    pub anchors: Option<Vec<Uuid>>,
+    /// Hop limit around the anchors (default 3).
+    pub anchor_depth: Option<usize>,
+    pub edge_types: Option<Vec<EdgeType>>,
+    pub direction: Option<Direction>,
+    pub weights: Option<HybridWeights>,
+    /// Whether hits must match the query text (default true). When false,
+    /// nodes found only by the vector or graph signal are returned too.
+    pub require_text: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HybridWeights {
+    pub text: f64,
+    pub vector: f64,
+    pub graph: f64,
+}
+
+impl Default for HybridWeights {
+    fn default() -> Self {
+        Self {
+            text: 1.0,
+            vector: 1.0,
+            graph: 1.0,
+        }
+    }
+}
+
+/// Why a hybrid search hit was retrieved; absent signals did not rank it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub text: Option<SignalScore>,
+    pub vector: Option<SignalScore>,
+    pub graph: Option<GraphProximity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalScore {
+    /// 1-based position in this signal's own ranking.
+    pub rank: usize,
+    /// BM25 score or cosine similarity.
+    pub score: f64,
+    /// Share of the fused score contributed by this signal.
+    pub contribution: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphProximity {
+    pub rank: usize,
+    pub distance: usize,
+    pub anchor_id: Uuid,
+    pub contribution: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub node: Node,
+    pub score: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<ScoreBreakdown>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub results: Vec<SearchHit>,
+    pub count: usize,
+    /// Number of matches across all pages.
+    pub total: usize,
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PathResult {
     pub path: Vec<Uuid>,
     pub node_names: Vec<String>,
+    pub edge_ids: Vec<Uuid>,
+    pub edge_types: Vec<EdgeType>,
+    /// How each edge was walked: `outgoing` from its source to its target,
+    /// `incoming` against it.
+    pub directions: Vec<Direction>,
+    /// Sum of the traversal costs of the edges.
     pub total_weight: f64,
 }
 
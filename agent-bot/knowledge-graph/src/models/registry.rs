@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// An entry of `config/repos.json`, as read by `scripts/sync-repos.sh`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Org whose graph the repository is indexed into. Entries without one
+    /// belong to `DEFAULT_ORG_ID`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+}
+
+/// A registered repository and what the graph holds for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryStatus {
+    #[serde(flatten)]
+    pub entry: RepositoryEntry,
+    /// Id of the `Repository` node.
+    pub node_id: Uuid,
+    /// Whether a checkout exists under `REPOS_DIR`.
+    pub checked_out: bool,
+    pub last_indexed: Option<DateTime<Utc>>,
+    pub commit: Option<String>,
+    pub nodes: usize,
+    pub edges: usize,
+    pub last_error: Option<String>,
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Which schema constraint a node or edge breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaRule {
+    /// A custom node or edge type the schema does not register.
+    UnknownType,
+    /// Names must contain something other than whitespace.
+    EmptyName,
+    NameTooLong,
+    /// Weights must be finite and within the schema's bounds.
+    Weight,
+    /// The edge type may not connect these node types.
+    EdgeEndpoints,
+    SelfLoop,
+    /// Too few or too many edges of a type at a node.
+    Cardinality,
+}
+
+/// One broken constraint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub rule: SchemaRule,
+    /// Field of the node or edge at fault, e.g. `weight` or `target_id`.
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edge_id: Option<Uuid>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Result of auditing a whole graph against its schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub nodes_checked: usize,
+    pub edges_checked: usize,
+    /// Number of violations found; only the first few are listed.
+    pub total: usize,
+    pub violations: Vec<Violation>,
+}
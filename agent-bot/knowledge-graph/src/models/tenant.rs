@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::KgError;
+
+/// Longest accepted org id or repository name.
+const MAX_NAME_LEN: usize = 128;
+
+/// Who a graph belongs to: an org, optionally narrowed to one repository.
+/// Each tenant has its own graph, isolated from every other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Tenant {
+    pub org_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+}
+
+impl Tenant {
+    /// Validates both names: ASCII letters, digits, `-`, `_` and `.`, not
+    /// starting with `.`.
+    pub fn new(org_id: &str, repository: Option<&str>) -> Result<Self, KgError> {
+        if !is_valid_name(org_id) {
+            return Err(KgError::InvalidTenant {
+                field: "org_id",
+                value: org_id.to_string(),
+            });
+        }
+        if let Some(repository) = repository.filter(|r| !is_valid_name(r)) {
+            return Err(KgError::InvalidTenant {
+                field: "repository",
+                value: repository.to_string(),
+            });
+        }
+        Ok(Self {
+            org_id: org_id.to_string(),
+            repository: repository.map(str::to_string),
+        })
+    }
+
+    /// Storage name of the graph: `org` or `org/repository`.
+    pub fn graph_name(&self) -> String {
+        match &self.repository {
+            Some(repository) => format!("{}/{}", self.org_id, repository),
+            None => self.org_id.clone(),
+        }
+    }
+
+    pub fn from_graph_name(name: &str) -> Option<Self> {
+        match name.split_once('/') {
+            Some((org_id, repository)) => Self::new(org_id, Some(repository)).ok(),
+            None => Self::new(name, None).ok(),
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// A tenant graph and, when it is loaded, its size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub tenant: Tenant,
+    pub loaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edges: Option<usize>,
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{
+    CreateEdgeRequest, CreateNodeRequest, UpdateEdgeRequest, UpdateNodeRequest, UpsertEdge,
+    UpsertNode,
+};
+
+/// One step of a transaction. Steps run in order and may refer to nodes and
+/// edges created by earlier steps, either by a client-chosen `id` or by
+/// natural key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    CreateNode {
+        /// Optional client-chosen id; must not exist yet.
+        id: Option<Uuid>,
+        #[serde(flatten)]
+        node: CreateNodeRequest,
+    },
+    UpdateNode {
+        id: Uuid,
+        #[serde(flatten)]
+        update: UpdateNodeRequest,
+    },
+    DeleteNode {
+        id: Uuid,
+    },
+    UpsertNode {
+        #[serde(flatten)]
+        node: UpsertNode,
+    },
+    CreateEdge {
+        id: Option<Uuid>,
+        #[serde(flatten)]
+        edge: CreateEdgeRequest,
+    },
+    UpdateEdge {
+        id: Uuid,
+        #[serde(flatten)]
+        update: UpdateEdgeRequest,
+    },
+    DeleteEdge {
+        id: Uuid,
+    },
+    UpsertEdge {
+        #[serde(flatten)]
+        edge: UpsertEdge,
+    },
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::CreateNode { .. } => "create_node",
+            Operation::UpdateNode { .. } => "update_node",
+            Operation::DeleteNode { .. } => "delete_node",
+            Operation::UpsertNode { .. } => "upsert_node",
+            Operation::CreateEdge { .. } => "create_edge",
+            Operation::UpdateEdge { .. } => "update_edge",
+            Operation::DeleteEdge { .. } => "delete_edge",
+            Operation::UpsertEdge { .. } => "upsert_edge",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRequest {
+    pub operations: Vec<Operation>,
+}
+
+/// Id touched by each operation, in request order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionResult {
+    pub committed: bool,
+    pub ids: Vec<Uuid>,
+}
+
+/// Why an operation was refused. Each kind reports the same code and status
+/// as the matching single-item request would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    NodeNotFound,
+    EdgeNotFound,
+    /// An edge endpoint does not exist.
+    ReferenceNotFound,
+    InvalidField,
+    SchemaViolation,
+    /// A `create_*` id is already taken.
+    Conflict,
+    /// The store failed to record the write.
+    Storage,
+}
+
+/// The operation that aborted a transaction; nothing was applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionError {
+    pub index: usize,
+    pub op: String,
+    pub error: String,
+    pub kind: FailureKind,
+    /// The operation field at fault, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
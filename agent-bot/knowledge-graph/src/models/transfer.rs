@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Direction, Edge, EdgeType, Node};
+
+/// One line of an NDJSON graph export. Exports list every node before any
+/// edge, and imports expect the same order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GraphRecord {
+    Node(Node),
+    Edge(Edge),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Validate every record without changing the graph.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Give every imported node and edge a fresh id, rewriting edge
+    /// endpoints to match, instead of keeping (and overwriting) the ids in
+    /// the file.
+    #[serde(default)]
+    pub remap_ids: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportLineError {
+    /// 1-based line number in the request body.
+    pub line: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub nodes: usize,
+    pub edges: usize,
+    pub failed: usize,
+    /// The first failures, up to a fixed cap; `failed` has the full count.
+    pub errors: Vec<ImportLineError>,
+}
+
+/// Text formats a graph or subgraph can be rendered to for external viewers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderFormat {
+    /// GraphML, for Gephi and yEd.
+    Graphml,
+    /// Graphviz DOT.
+    Dot,
+    /// Mermaid flowchart, for Markdown on PRs.
+    Mermaid,
+}
+
+/// Selects what to render: the whole graph by default, the listed
+/// `node_ids`, and/or the neighborhood of `root_id` as a neighbors query
+/// would return it. Edges are those between selected nodes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderQuery {
+    pub format: RenderFormat,
+    pub root_id: Option<Uuid>,
+    pub depth: Option<usize>,
+    pub direction: Option<Direction>,
+    pub node_ids: Option<Vec<Uuid>>,
+    pub edge_types: Option<Vec<EdgeType>>,
+}
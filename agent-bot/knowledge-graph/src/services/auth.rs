@@ -0,0 +1,423 @@
+//! Credential checks for the API.
+//!
+//! Static API keys come from a JSON file that stores only their SHA-256, so
+//! the file can be committed or mounted read-only without leaking keys.
+//! JWTs are signed with HS256 or RS256 and map to a tenant through their
+//! claims. Both resolve to a `Principal` with a scope.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::signature::Verifier;
+use rsa::RsaPublicKey;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::models::{ApiKeyEntry, Principal};
+
+/// Clock skew tolerated on `exp` and `nbf`, in seconds.
+const LEEWAY_SECS: i64 = 60;
+
+#[derive(Debug, Default, Deserialize)]
+struct KeyFile {
+    #[serde(default)]
+    keys: Vec<ApiKeyEntry>,
+}
+
+/// How JWT claims are checked and mapped to a principal.
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    pub hs256_secret: Option<Vec<u8>>,
+    pub rs256_key: Option<VerifyingKey<Sha256>>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    /// Claim holding the org id.
+    pub org_claim: String,
+    /// Claim holding the scope: `read`, `write` or `admin`, or several of
+    /// them separated by spaces.
+    pub scope_claim: String,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            hs256_secret: None,
+            rs256_key: None,
+            issuer: None,
+            audience: None,
+            org_claim: "org_id".to_string(),
+            scope_claim: "scope".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuthConfig {
+    /// Lets every request through as `Principal::anonymous`.
+    pub disabled: bool,
+    /// API keys by the hex SHA-256 of the key.
+    keys: HashMap<String, ApiKeyEntry>,
+    pub jwt: JwtConfig,
+}
+
+impl AuthConfig {
+    /// Reads `AUTH_DISABLED`, `AUTH_KEYS_FILE`, `JWT_HS256_SECRET`,
+    /// `JWT_RS256_PUBLIC_KEY_FILE`, `JWT_ISSUER`, `JWT_AUDIENCE`,
+    /// `JWT_ORG_CLAIM` and `JWT_SCOPE_CLAIM`. Empty values count as unset.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let keys_file =
+            var("AUTH_KEYS_FILE").unwrap_or_else(|| "/app/config/api-keys.json".to_string());
+
+        let rs256_key = match var("JWT_RS256_PUBLIC_KEY_FILE") {
+            Some(path) => Some(parse_public_key(&std::fs::read_to_string(&path)?)?),
+            None => None,
+        };
+        let defaults = JwtConfig::default();
+        let jwt = JwtConfig {
+            hs256_secret: var("JWT_HS256_SECRET").map(String::into_bytes),
+            rs256_key,
+            issuer: var("JWT_ISSUER"),
+            audience: var("JWT_AUDIENCE"),
+            org_claim: var("JWT_ORG_CLAIM").unwrap_or(defaults.org_claim),
+            scope_claim: var("JWT_SCOPE_CLAIM").unwrap_or(defaults.scope_claim),
+        };
+
+        let mut config = Self::load_keys(keys_file)?;
+        config.disabled = var("AUTH_DISABLED").is_some_and(|v| v == "true" || v == "1");
+        config.jwt = jwt;
+        Ok(config)
+    }
+
+    /// Loads the API key file at `path`; a missing file has no keys.
+    pub fn load_keys(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let file: KeyFile = config::Config::builder()
+            .add_source(
+                config::File::from(path.as_path())
+                    .format(config::FileFormat::Json)
+                    .required(false),
+            )
+            .build()?
+            .try_deserialize()?;
+        let mut keys = HashMap::new();
+        for key in file.keys {
+            let hash = key.sha256.to_ascii_lowercase();
+            if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                anyhow::bail!("API key {} has an invalid sha256", key.id);
+            }
+            if key.org_id == Principal::ANY_ORG {
+                anyhow::bail!(
+                    "API key {} has org_id \"*\"; set \"all_orgs\": true",
+                    key.id
+                );
+            }
+            if key.org_id.is_empty() && !key.all_orgs {
+                anyhow::bail!("API key {} has no org_id", key.id);
+            }
+            keys.insert(hash, key);
+        }
+        Ok(Self {
+            keys,
+            ..Default::default()
+        })
+    }
+
+    /// Whether any credential could be accepted.
+    pub fn has_credentials(&self) -> bool {
+        !self.keys.is_empty() || self.jwt.hs256_secret.is_some() || self.jwt.rs256_key.is_some()
+    }
+
+    /// Resolves a bearer token or API key. Tokens with three dot-separated
+    /// parts are read as JWTs.
+    pub fn authenticate(&self, credential: &str) -> Result<Principal, String> {
+        if credential.split('.').count() == 3 {
+            self.verify_jwt(credential)
+        } else {
+            self.verify_key(credential)
+        }
+    }
+
+    fn verify_key(&self, key: &str) -> Result<Principal, String> {
+        let hash = hex::encode(Sha256::digest(key.as_bytes()));
+        let entry = self
+            .keys
+            .get(&hash)
+            .ok_or_else(|| "Invalid API key".to_string())?;
+        let org_id = if entry.all_orgs {
+            Principal::ANY_ORG
+        } else {
+            &entry.org_id
+        };
+        Ok(Principal {
+            subject: entry.id.clone(),
+            org_id: org_id.to_string(),
+            repository: entry.repository.clone(),
+            scope: entry.scope,
+        })
+    }
+
+    fn verify_jwt(&self, token: &str) -> Result<Principal, String> {
+        let invalid = |reason: &str| format!("Invalid token: {}", reason);
+        let (signing_input, signature) = token.rsplit_once('.').ok_or_else(|| invalid("format"))?;
+        let (header, payload) = signing_input
+            .split_once('.')
+            .ok_or_else(|| invalid("format"))?;
+        let decode = |part: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|_| invalid("encoding"))
+        };
+        let header: serde_json::Value =
+            serde_json::from_slice(&decode(header)?).map_err(|_| invalid("header"))?;
+        let signature = decode(signature)?;
+
+        // The algorithm must be one configured here, so a token cannot pick
+        // `none` or verify an RS256 public key as an HS256 secret.
+        let verified = match (header["alg"].as_str(), &self.jwt) {
+            (
+                Some("HS256"),
+                JwtConfig {
+                    hs256_secret: Some(secret),
+                    ..
+                },
+            ) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(|e| e.to_string())?;
+                mac.update(signing_input.as_bytes());
+                mac.verify_slice(&signature).is_ok()
+            }
+            (
+                Some("RS256"),
+                JwtConfig {
+                    rs256_key: Some(key),
+                    ..
+                },
+            ) => Signature::try_from(signature.as_slice())
+                .is_ok_and(|s| key.verify(signing_input.as_bytes(), &s).is_ok()),
+            _ => return Err(invalid("unsupported algorithm")),
+        };
+        if !verified {
+            return Err(invalid("bad signature"));
+        }
+
+        let claims: serde_json::Value =
+            serde_json::from_slice(&decode(payload)?).map_err(|_| invalid("claims"))?;
+        self.check_claims(&claims)
+            .map_err(|reason| invalid(&reason))?;
+
+        let text = |name: &str| {
+            claims
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        let org_id = text(&self.jwt.org_claim)
+            .ok_or_else(|| invalid(&format!("missing {} claim", self.jwt.org_claim)))?;
+        // Tokens come from outside issuers, so they never get every org.
+        if org_id == Principal::ANY_ORG {
+            return Err(invalid(&format!(
+                "{} may not be {:?}",
+                self.jwt.org_claim, org_id
+            )));
+        }
+        let scope = text(&self.jwt.scope_claim)
+            .and_then(|scopes| {
+                scopes
+                    .split_whitespace()
+                    .filter_map(|s| s.parse().ok())
+                    .max()
+            })
+            .ok_or_else(|| invalid("no known scope"))?;
+        Ok(Principal {
+            subject: text("sub").unwrap_or_else(|| "jwt".to_string()),
+            org_id,
+            repository: text("repository"),
+            scope,
+        })
+    }
+
+    /// Checks `exp`, which is required, and `nbf`, `iss` and `aud` when set.
+    fn check_claims(&self, claims: &serde_json::Value) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        let exp = claims["exp"].as_i64().ok_or("missing exp claim")?;
+        if exp + LEEWAY_SECS < now {
+            return Err("expired".to_string());
+        }
+        if claims["nbf"]
+            .as_i64()
+            .is_some_and(|nbf| nbf - LEEWAY_SECS > now)
+        {
+            return Err("not yet valid".to_string());
+        }
+        if let Some(issuer) = &self.jwt.issuer {
+            if claims["iss"].as_str() != Some(issuer) {
+                return Err("wrong issuer".to_string());
+            }
+        }
+        if let Some(audience) = &self.jwt.audience {
+            let matches = match &claims["aud"] {
+                serde_json::Value::String(aud) => aud == audience,
+                serde_json::Value::Array(auds) => auds.iter().any(|a| a == audience.as_str()),
+                _ => false,
+            };
+            if !matches {
+                return Err("wrong audience".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads an RSA public key in SPKI (`BEGIN PUBLIC KEY`) or PKCS#1
+/// (`BEGIN RSA PUBLIC KEY`) PEM.
+pub fn parse_public_key(pem: &str) -> anyhow::Result<VerifyingKey<Sha256>> {
+    let key = RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|err| anyhow::anyhow!("Invalid RS256 public key: {}", err))?;
+    Ok(VerifyingKey::new(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use rsa::RsaPrivateKey;
+
+    use super::*;
+    use crate::models::Scope;
+
+    const RSA_PRIVATE_KEY: &str = include_str!("../../tests/fixtures/auth/rs256.pem");
+    const RSA_PUBLIC_KEY: &str = include_str!("../../tests/fixtures/auth/rs256.pub.pem");
+
+    fn token(alg: &str, claims: serde_json::Value, sign: impl Fn(&[u8]) -> Vec<u8>) -> String {
+        let header = serde_json::json!({ "alg": alg, "typ": "JWT" });
+        let input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = URL_SAFE_NO_PAD.encode(sign(input.as_bytes()));
+        format!("{}.{}", input, signature)
+    }
+
+    fn hs256(secret: &[u8]) -> impl Fn(&[u8]) -> Vec<u8> + '_ {
+        move |input| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+            mac.update(input);
+            mac.finalize().into_bytes().to_vec()
+        }
+    }
+
+    fn claims(exp_offset: i64) -> serde_json::Value {
+        serde_json::json!({
+            "sub": "ci",
+            "org_id": "acme",
+            "scope": "read write",
+            "exp": chrono::Utc::now().timestamp() + exp_offset,
+        })
+    }
+
+    #[test]
+    fn test_api_keys_are_matched_by_hash() {
+        let dir = std::env::temp_dir().join(format!("kg-auth-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("api-keys.json");
+        let hash = hex::encode(Sha256::digest(b"kg_live_secret"));
+        let admin_hash = hex::encode(Sha256::digest(b"kg_admin_secret"));
+        let file = serde_json::json!({ "keys": [
+            { "id": "sync", "sha256": hash, "org_id": "acme", "scope": "write" },
+            { "id": "ops", "sha256": admin_hash, "all_orgs": true, "scope": "admin" },
+        ]});
+        std::fs::write(&path, file.to_string()).unwrap();
+
+        let config = AuthConfig::load_keys(&path).unwrap();
+        let principal = config.authenticate("kg_live_secret").unwrap();
+        assert_eq!(principal.subject, "sync");
+        assert_eq!(principal.scope, Scope::Write);
+        assert!(config.authenticate(&hash).is_err());
+        let ops = config.authenticate("kg_admin_secret").unwrap();
+        assert_eq!(ops.org_id, Principal::ANY_ORG);
+
+        // The wildcard has to be asked for by name.
+        let file = serde_json::json!({ "keys": [
+            { "id": "sync", "sha256": hash, "org_id": "*", "scope": "write" },
+        ]});
+        std::fs::write(&path, file.to_string()).unwrap();
+        assert!(AuthConfig::load_keys(&path).is_err());
+        assert!(!AuthConfig::load_keys(dir.join("missing.json"))
+            .unwrap()
+            .has_credentials());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hs256_tokens() {
+        let config = AuthConfig {
+            jwt: JwtConfig {
+                hs256_secret: Some(b"shared-secret".to_vec()),
+                audience: Some("knowledge-graph".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut valid = claims(300);
+        valid["aud"] = serde_json::json!(["dashboard", "knowledge-graph"]);
+        let signed = token("HS256", valid.clone(), hs256(b"shared-secret"));
+        let principal = config.authenticate(&signed).unwrap();
+        assert_eq!(
+            (principal.org_id.as_str(), principal.scope),
+            ("acme", Scope::Write)
+        );
+
+        let forged = token("HS256", valid.clone(), hs256(b"guessed"));
+        assert!(config.authenticate(&forged).is_err());
+        let unsigned = token("none", valid.clone(), |_| Vec::new());
+        assert!(config.authenticate(&unsigned).is_err());
+
+        let mut expired = claims(-3600);
+        expired["aud"] = serde_json::json!("knowledge-graph");
+        let expired = token("HS256", expired, hs256(b"shared-secret"));
+        assert_eq!(
+            config.authenticate(&expired).unwrap_err(),
+            "Invalid token: expired"
+        );
+        let other_audience = token("HS256", claims(300), hs256(b"shared-secret"));
+        assert!(config.authenticate(&other_audience).is_err());
+
+        let mut every_org = valid.clone();
+        every_org["org_id"] = serde_json::json!("*");
+        let every_org = token("HS256", every_org, hs256(b"shared-secret"));
+        let error = config.authenticate(&every_org).unwrap_err();
+        assert_eq!(error, "Invalid token: org_id may not be \"*\"");
+    }
+
+    #[test]
+    fn test_rs256_tokens() {
+        let config = AuthConfig {
+            jwt: JwtConfig {
+                rs256_key: Some(parse_public_key(RSA_PUBLIC_KEY).unwrap()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let private_key = RsaPrivateKey::from_pkcs8_pem(RSA_PRIVATE_KEY).unwrap();
+        let signing_key = SigningKey::<Sha256>::new(private_key);
+        let rs256 = |input: &[u8]| signing_key.sign(input).to_vec();
+
+        let principal = config
+            .authenticate(&token("RS256", claims(300), rs256))
+            .unwrap();
+        assert_eq!(principal.subject, "ci");
+
+        // An HS256 token keyed with the public key must not verify.
+        let confused = token("HS256", claims(300), hs256(RSA_PUBLIC_KEY.as_bytes()));
+        assert!(config.authenticate(&confused).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! Loads `gkg index` output into the graph.
+//!
+//! gkg stores each repository's index as a database directory with
+//! `DirectoryNode`, `FileNode`, `DefinitionNode` and `ImportedSymbolNode`
+//! tables plus typed relationships between them. The tables are read with
+//! `gkg query --format json`, or, when the directory already holds JSON
+//! exports of the same queries (`directories.json`, `files.json`, ...), from
+//! those files. Rows become natural-key upserts, so ingesting the same output
+//! twice updates rather than duplicates.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::models::{
+    BulkUpsertRequest, EdgeType, IngestReport, NodeKey, NodeRef, NodeType, UpsertEdge, UpsertNode,
+};
+use crate::services::graph::KnowledgeGraph;
+use crate::services::registry;
+use crate::services::store::StoreError;
+
+#[derive(Debug, Clone)]
+pub struct GkgConfig {
+    /// One gkg output directory per repository, named after it.
+    pub data_dir: PathBuf,
+    pub binary: String,
+    pub ingest_on_startup: bool,
+}
+
+impl GkgConfig {
+    /// Reads `GKG_DATA_DIR`, `GKG_BINARY` and `GKG_INGEST_ON_STARTUP`.
+    pub fn from_env() -> Self {
+        Self {
+            data_dir: std::env::var("GKG_DATA_DIR")
+                .unwrap_or_else(|_| "/data/graphs".to_string())
+                .into(),
+            binary: std::env::var("GKG_BINARY").unwrap_or_else(|_| "gkg".to_string()),
+            ingest_on_startup: std::env::var("GKG_INGEST_ON_STARTUP")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+        }
+    }
+
+    /// Repository names with an output directory, sorted.
+    pub fn repositories(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.data_dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The output directory for `repository`, if it exists. Names that could
+    /// escape the data dir are rejected.
+    pub fn repository_dir(&self, repository: &str) -> Option<PathBuf> {
+        if !registry::is_valid_name(repository) {
+            return None;
+        }
+        let dir = self.data_dir.join(repository);
+        dir.is_dir().then_some(dir)
+    }
+}
+
+const DIRECTORY: &str = "DirectoryNode";
+const FILE: &str = "FileNode";
+const DEFINITION: &str = "DefinitionNode";
+const IMPORTED_SYMBOL: &str = "ImportedSymbolNode";
+
+/// Export file name and gkg query for each table read.
+const DIRECTORIES: (&str, &str) = (
+    "directories.json",
+    "MATCH (n:DirectoryNode) RETURN n.id AS id, n.path AS path, n.name AS name",
+);
+const FILES: (&str, &str) = (
+    "files.json",
+    "MATCH (n:FileNode) RETURN n.id AS id, n.path AS path, n.name AS name, \
+     n.language AS language",
+);
+const DEFINITIONS: (&str, &str) = (
+    "definitions.json",
+    "MATCH (n:DefinitionNode) RETURN n.id AS id, n.fqn AS fqn, n.name AS name, \
+     n.definition_type AS definition_type, n.primary_file_path AS primary_file_path, \
+     n.start_line AS start_line",
+);
+const IMPORTED_SYMBOLS: (&str, &str) = (
+    "imported_symbols.json",
+    "MATCH (n:ImportedSymbolNode) RETURN n.id AS id, n.import_path AS import_path, \
+     n.name AS name, n.alias AS alias, n.file_path AS file_path",
+);
+const RELATIONSHIPS: (&str, &str) = (
+    "relationships.json",
+    "MATCH (a)-[r]->(b) RETURN label(a) AS source_table, a.id AS source_id, \
+     label(b) AS target_table, b.id AS target_id, r.type AS type",
+);
+
+#[derive(Debug, Deserialize)]
+struct DirectoryRow {
+    id: u64,
+    path: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileRow {
+    id: u64,
+    path: String,
+    name: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefinitionRow {
+    id: u64,
+    fqn: String,
+    name: Option<String>,
+    definition_type: String,
+    primary_file_path: Option<String>,
+    start_line: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportedSymbolRow {
+    id: u64,
+    import_path: String,
+    name: Option<String>,
+    alias: Option<String>,
+    file_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelationshipRow {
+    source_table: String,
+    source_id: u64,
+    target_table: String,
+    target_id: u64,
+    #[serde(rename = "type")]
+    relationship_type: String,
+}
+
+/// The tables of one repository's gkg output.
+#[derive(Debug, Default)]
+pub struct GkgOutput {
+    directories: Vec<DirectoryRow>,
+    files: Vec<FileRow>,
+    definitions: Vec<DefinitionRow>,
+    imported_symbols: Vec<ImportedSymbolRow>,
+    relationships: Vec<RelationshipRow>,
+}
+
+impl GkgOutput {
+    /// Reads `dir` from its JSON exports when present, and through the gkg
+    /// CLI otherwise.
+    pub async fn load(config: &GkgConfig, dir: &Path) -> anyhow::Result<Self> {
+        let exported = dir.join(RELATIONSHIPS.0).is_file();
+        let source = |table| Table {
+            config,
+            dir,
+            exported,
+            table,
+        };
+        Ok(Self {
+            directories: source(DIRECTORIES).rows().await?,
+            files: source(FILES).rows().await?,
+            definitions: source(DEFINITIONS).rows().await?,
+            imported_symbols: source(IMPORTED_SYMBOLS).rows().await?,
+            relationships: source(RELATIONSHIPS).rows().await?,
+        })
+    }
+
+    /// Upserts for every row, plus a repository node containing the
+    /// top-level directories and files. Also returns the number of
+    /// relationships that could not be mapped.
+    pub fn into_request(self, repository: &str) -> (BulkUpsertRequest, usize) {
+        let mut request = BulkUpsertRequest::default();
+        let mut keys: HashMap<(&str, u64), NodeKey> = HashMap::new();
+        let key = |path: Option<&str>, qualified_name: &str, node_type| NodeKey {
+            repository: repository.to_string(),
+            path: path.map(str::to_string),
+            qualified_name: qualified_name.to_string(),
+            node_type,
+        };
+
+        let root = key(None, repository, NodeType::Repository);
+        request.nodes.push(upsert(
+            root.clone(),
+            Some(repository.to_string()),
+            None,
+            None,
+        ));
+
+        for row in &self.directories {
+            let node_key = key(Some(&row.path), &row.path, NodeType::Module);
+            request
+                .nodes
+                .push(upsert(node_key.clone(), row.name.clone(), None, None));
+            keys.insert((DIRECTORY, row.id), node_key);
+        }
+        for row in &self.files {
+            let node_key = key(Some(&row.path), &row.path, NodeType::File);
+            request.nodes.push(upsert(
+                node_key.clone(),
+                row.name.clone(),
+                row.language.clone(),
+                None,
+            ));
+            keys.insert((FILE, row.id), node_key);
+        }
+        for row in &self.definitions {
+            let node_type = definition_node_type(&row.definition_type);
+            let node_key = key(row.primary_file_path.as_deref(), &row.fqn, node_type);
+            let metadata = serde_json::json!({
+                "definition_type": row.definition_type,
+                "start_line": row.start_line,
+            });
+            request.nodes.push(upsert(
+                node_key.clone(),
+                row.name.clone(),
+                None,
+                Some(metadata),
+            ));
+            keys.insert((DEFINITION, row.id), node_key);
+        }
+        for row in &self.imported_symbols {
+            let qualified_name = match &row.name {
+                Some(name) => format!("{}#{}", row.import_path, name),
+                None => row.import_path.clone(),
+            };
+            let node_key = key(Some(&row.file_path), &qualified_name, NodeType::Import);
+            let name = row
+                .alias
+                .clone()
+                .or_else(|| row.name.clone())
+                .unwrap_or_else(|| row.import_path.clone());
+            let metadata = serde_json::json!({ "import_path": row.import_path });
+            request
+                .nodes
+                .push(upsert(node_key.clone(), Some(name), None, Some(metadata)));
+            keys.insert((IMPORTED_SYMBOL, row.id), node_key);
+        }
+
+        let mut contained = HashSet::new();
+        let mut skipped = 0;
+        for row in &self.relationships {
+            let source = keys.get(&(table_name(&row.source_table), row.source_id));
+            let target = keys.get(&(table_name(&row.target_table), row.target_id));
+            let edge_type = relationship_edge_type(&row.relationship_type);
+            let (Some(source), Some(target), Some(edge_type)) = (source, target, edge_type) else {
+                skipped += 1;
+                continue;
+            };
+            if edge_type == EdgeType::Contains && row.source_table == DIRECTORY {
+                contained.insert((table_name(&row.target_table), row.target_id));
+            }
+            request.edges.push(UpsertEdge {
+                source: NodeRef::Key(source.clone()),
+                target: NodeRef::Key(target.clone()),
+                edge_type,
+                weight: None,
+                metadata: Some(serde_json::json!({ "gkg_type": row.relationship_type })),
+            });
+        }
+
+        let top_level = self
+            .directories
+            .iter()
+            .map(|row| (DIRECTORY, row.id))
+            .chain(self.files.iter().map(|row| (FILE, row.id)))
+            .filter(|id| !contained.contains(id));
+        for id in top_level {
+            request.edges.push(UpsertEdge {
+                source: NodeRef::Key(root.clone()),
+                target: NodeRef::Key(keys[&id].clone()),
+                edge_type: EdgeType::Contains,
+                weight: None,
+                metadata: None,
+            });
+        }
+
+        (request, skipped)
+    }
+}
+
+fn upsert(
+    key: NodeKey,
+    name: Option<String>,
+    language: Option<String>,
+    metadata: Option<serde_json::Value>,
+) -> UpsertNode {
+    UpsertNode {
+        key,
+        name,
+        language,
+        description: None,
+        metadata,
+        embedding: None,
+    }
+}
+
+/// The table constant for a label returned by gkg, so keys can borrow it.
+fn table_name(label: &str) -> &'static str {
+    match label {
+        DIRECTORY => DIRECTORY,
+        FILE => FILE,
+        DEFINITION => DEFINITION,
+        IMPORTED_SYMBOL => IMPORTED_SYMBOL,
+        _ => "",
+    }
+}
+
+fn definition_node_type(definition_type: &str) -> NodeType {
+    match definition_type.to_ascii_lowercase().as_str() {
+        "class" | "interface" | "trait" | "struct" | "enum" | "type" | "typealias" | "record" => {
+            NodeType::Class
+        }
+        "module" | "namespace" | "package" => NodeType::Module,
+        "constant" | "enumconstant" | "enum_constant" => NodeType::Constant,
+        "variable" | "field" | "property" | "attribute" => NodeType::Variable,
+        _ => NodeType::Function,
+    }
+}
+
+fn relationship_edge_type(relationship_type: &str) -> Option<EdgeType> {
+    let name = relationship_type.to_ascii_uppercase();
+    let edge_type = if name.contains("CALLS") {
+        EdgeType::Calls
+    } else if name.contains("IMPORT") {
+        EdgeType::Imports
+    } else if name.contains("INHERIT") || name.contains("EXTENDS") {
+        EdgeType::Inherits
+    } else if name.contains("IMPLEMENT") {
+        EdgeType::Implements
+    } else if name.contains("REFERENCE") {
+        EdgeType::References
+    } else if name.contains("CONTAINS") || name.contains("DEFINES") || name.contains("_TO_") {
+        EdgeType::Contains
+    } else {
+        return None;
+    };
+    Some(edge_type)
+}
+
+/// One table, read from its export file or through the CLI.
+struct Table<'a> {
+    config: &'a GkgConfig,
+    dir: &'a Path,
+    exported: bool,
+    table: (&'static str, &'static str),
+}
+
+impl Table<'_> {
+    async fn rows<T: DeserializeOwned>(&self) -> anyhow::Result<Vec<T>> {
+        let (file, query) = self.table;
+        let json = if self.exported {
+            let path = self.dir.join(file);
+            if !path.is_file() {
+                return Ok(Vec::new());
+            }
+            tokio::fs::read_to_string(&path).await?
+        } else {
+            let output = async_process::Command::new(&self.config.binary)
+                .arg("query")
+                .arg("--db")
+                .arg(self.dir)
+                .args(["--format", "json", query])
+                .output()
+                .await?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "gkg query for {} failed: {}",
+                    file,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            String::from_utf8(output.stdout)?
+        };
+        if json.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(&json).map_err(|err| anyhow::anyhow!("Invalid {}: {}", file, err))
+    }
+}
+
+/// Loads `repository`'s gkg output into `graph`. The output is read before
+/// the write lock is taken; failures to read it are reported in the returned
+/// report and recorded on the repository node. Store failures are returned.
+pub async fn ingest_repository(
+    graph: &RwLock<KnowledgeGraph>,
+    config: &GkgConfig,
+    repository: &str,
+) -> Result<IngestReport, StoreError> {
+    let mut report = IngestReport {
+        repository: repository.to_string(),
+        ..Default::default()
+    };
+    let output = match config.repository_dir(repository) {
+        Some(dir) => GkgOutput::load(config, &dir).await,
+        None => Err(anyhow::anyhow!("Repository output not found")),
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            let error = err.to_string();
+            registry::record_error(&mut *graph.write().await, repository, &error)?;
+            report.error = Some(error);
+            return Ok(report);
+        }
+    };
+
+    let (mut request, skipped) = output.into_request(repository);
+    let mut graph = graph.write().await;
+    let status = serde_json::json!({
+        "indexed_at": chrono::Utc::now(),
+        "last_error": null,
+        "last_error_at": null,
+    });
+    if let Some(root) = request
+        .nodes
+        .iter_mut()
+        .find(|node| node.key.node_type == NodeType::Repository)
+    {
+        *root = registry::repository_node(&graph, repository, status);
+    }
+    let upserted = graph.bulk_upsert(request)?;
+    report.nodes = upserted.nodes.len();
+    report.edges = upserted.edges.len();
+    report.created = upserted.created;
+    report.updated = upserted.updated;
+    report.failed = upserted.failed;
+    report.skipped = skipped;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_config() -> GkgConfig {
+        GkgConfig {
+            data_dir: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/gkg"),
+            binary: "gkg".to_string(),
+            ingest_on_startup: false,
+        }
+    }
+
+    #[test]
+    fn test_type_mapping() {
+        assert_eq!(definition_node_type("Class"), NodeType::Class);
+        assert_eq!(definition_node_type("Method"), NodeType::Function);
+        assert_eq!(definition_node_type("Interface"), NodeType::Class);
+        assert_eq!(
+            relationship_edge_type("DIR_CONTAINS_FILE"),
+            Some(EdgeType::Contains)
+        );
+        assert_eq!(
+            relationship_edge_type("CLASS_TO_METHOD"),
+            Some(EdgeType::Contains)
+        );
+        assert_eq!(
+            relationship_edge_type("AMBIGUOUSLY_CALLS"),
+            Some(EdgeType::Calls)
+        );
+        assert_eq!(
+            relationship_edge_type("FILE_IMPORTS"),
+            Some(EdgeType::Imports)
+        );
+        assert_eq!(relationship_edge_type("UNKNOWN"), None);
+    }
+
+    #[test]
+    fn test_repository_dir_rejects_escapes() {
+        let config = fixture_config();
+        assert_eq!(config.repositories(), vec!["acme-billing".to_string()]);
+        assert!(config.repository_dir("acme-billing").is_some());
+        assert!(config.repository_dir("..").is_none());
+        assert!(config.repository_dir("../fixtures").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_fixture_is_idempotent() {
+        let config = fixture_config();
+        let graph = RwLock::new(KnowledgeGraph::new());
+
+        let report = ingest_repository(&graph, &config, "acme-billing")
+            .await
+            .unwrap();
+        assert!(report.error.is_none(), "{:?}", report.error);
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.created, report.nodes + report.edges);
+
+        let again = ingest_repository(&graph, &config, "acme-billing")
+            .await
+            .unwrap();
+        assert_eq!(again.created, 0);
+        assert_eq!(again.updated, report.created);
+
+        let graph = graph.read().await;
+        let stats = graph.get_stats();
+        assert_eq!(stats.total_nodes, report.nodes);
+        assert_eq!(stats.total_edges, report.edges);
+
+        let repo = NodeKey {
+            repository: "acme-billing".to_string(),
+            path: None,
+            qualified_name: "acme-billing".to_string(),
+            node_type: NodeType::Repository,
+        };
+        let retry = NodeKey {
+            repository: "acme-billing".to_string(),
+            path: Some("billing/payments.py".to_string()),
+            qualified_name: "billing.payments.PaymentService.retry".to_string(),
+            node_type: NodeType::Function,
+        };
+        let record = NodeKey {
+            qualified_name: "billing.audit.record".to_string(),
+            path: Some("billing/audit.py".to_string()),
+            ..retry.clone()
+        };
+        assert_eq!(graph.get_node(&retry.node_id()).unwrap().name, "retry");
+        assert!(graph
+            .get_edges_for_node(&retry.node_id())
+            .iter()
+            .any(|e| e.edge_type == EdgeType::Calls && e.target_id == record.node_id()));
+
+        let path = graph.find_path(&repo.node_id(), &record.node_id()).unwrap();
+        assert_eq!(
+            path.node_names,
+            vec!["acme-billing", "billing", "audit.py", "record"]
+        );
+    }
+}
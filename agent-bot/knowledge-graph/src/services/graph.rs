@@ -1,188 +1,353 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::models::{
-    Direction, Edge, EdgeType, GraphStats, Node, NodeType, PathResult,
+    BulkUpsertReport, Direction, Edge, EdgeType, FailureKind, GraphStats, Neighbor, Node,
+    ScoreBreakdown, SearchHit, SearchQuery, SearchResults, SimilarQuery, Subgraph,
+    UpdateEdgeRequest, UpdateNodeRequest, Violation,
 };
+use crate::services::schema::GraphSchema;
+use crate::services::search::{self, SearchIndex};
+use crate::services::store::{GraphStore, MemoryStore, PendingWrites, StoreError};
+use crate::services::vector::{VectorIndex, DEFAULT_EF_SEARCH};
 
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::algo::dijkstra;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableDiGraph};
 
+mod bulk;
+mod hybrid;
+mod paths;
+mod transaction;
+mod validate;
+
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+const DEFAULT_SIMILAR_K: usize = 10;
+/// Deepest breadth-first traversal a query may ask for.
+pub const MAX_TRAVERSAL_DEPTH: usize = 32;
+
+/// Why a requested traversal depth is rejected, if it is.
+pub fn depth_error(depth: Option<usize>) -> Option<String> {
+    depth
+        .filter(|depth| *depth > MAX_TRAVERSAL_DEPTH)
+        .map(|_| format!("must be at most {}", MAX_TRAVERSAL_DEPTH))
+}
+
+/// A bulk upsert the store failed part way through. The report covers the
+/// items up to the failing one, which is marked `failed`; later items were
+/// not attempted.
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct PartialUpsert {
+    pub report: BulkUpsertReport,
+    pub error: StoreError,
+}
+
+impl From<PartialUpsert> for StoreError {
+    fn from(partial: PartialUpsert) -> Self {
+        partial.error
+    }
+}
+
+/// Why a write was not applied: the request was rejected, or the store
+/// failed to record it.
+#[derive(Debug, thiserror::Error)]
+enum WriteError {
+    #[error("{message}")]
+    Rejected {
+        kind: FailureKind,
+        field: Option<String>,
+        message: String,
+    },
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl WriteError {
+    fn rejected(kind: FailureKind, field: Option<&str>, message: impl Into<String>) -> Self {
+        Self::Rejected {
+            kind,
+            field: field.map(str::to_string),
+            message: message.into(),
+        }
+    }
+
+    fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        Self::rejected(FailureKind::InvalidField, Some(field), message)
+    }
+}
+
+impl From<Violation> for WriteError {
+    fn from(violation: Violation) -> Self {
+        let message = violation.to_string();
+        Self::rejected(
+            FailureKind::SchemaViolation,
+            Some(&violation.field),
+            message,
+        )
+    }
+}
+
+/// Node and edge records live in the store; `graph` mirrors their topology
+/// for path algorithms. It is a `StableDiGraph` so that removing a node never
+/// renumbers the `NodeIndex` values held in `node_indices`, and each graph
+/// edge carries the id of the `Edge` record it stands for.
 pub struct KnowledgeGraph {
-    graph: DiGraph<Uuid, f64>,
-    nodes: HashMap<Uuid, Node>,
-    edges: HashMap<Uuid, Edge>,
+    graph: StableDiGraph<Uuid, Uuid>,
+    store: Box<dyn GraphStore>,
     node_indices: HashMap<Uuid, NodeIndex>,
+    edge_indices: HashMap<Uuid, EdgeIndex>,
+    search_index: SearchIndex,
+    vector_index: VectorIndex,
+    /// Checked by API writes; `add_node` and `add_edge` do not consult it.
+    schema: Arc<GraphSchema>,
 }
 
 impl KnowledgeGraph {
     pub fn new() -> Self {
+        Self::with_store(Box::new(MemoryStore::new()))
+    }
+
+    /// Builds a graph over an existing store, indexing every stored node and
+    /// edge. Edges whose endpoints are missing are dropped from the store.
+    pub fn with_store(mut store: Box<dyn GraphStore>) -> Self {
+        let mut graph = StableDiGraph::new();
+        let mut node_indices = HashMap::new();
+        let mut edge_indices = HashMap::new();
+        let mut search_index = SearchIndex::new();
+        let mut vector_index = VectorIndex::new();
+        for node in store.nodes() {
+            node_indices.insert(node.id, graph.add_node(node.id));
+            search_index.insert(node);
+            if let Some(embedding) = &node.embedding {
+                vector_index.insert(node.id, embedding);
+            }
+        }
+
+        let mut dangling = Vec::new();
+        for edge in store.edges() {
+            match (
+                node_indices.get(&edge.source_id),
+                node_indices.get(&edge.target_id),
+            ) {
+                (Some(source_idx), Some(target_idx)) => {
+                    edge_indices.insert(edge.id, graph.add_edge(*source_idx, *target_idx, edge.id));
+                }
+                _ => dangling.push(edge.id),
+            }
+        }
+        for id in dangling {
+            tracing::warn!("Dropping edge {} with missing endpoint", id);
+            if let Err(err) = store.remove_edge(&id) {
+                tracing::error!("Failed to drop edge {}: {}", id, err);
+            }
+        }
+
         Self {
-            graph: DiGraph::new(),
-            nodes: HashMap::new(),
-            edges: HashMap::new(),
-            node_indices: HashMap::new(),
+            graph,
+            store,
+            node_indices,
+            edge_indices,
+            search_index,
+            vector_index,
+            schema: Arc::new(GraphSchema::default()),
         }
     }
 
-    pub fn add_node(&mut self, node: Node) -> Uuid {
+    /// Adds a node, or replaces the node with the same id in place, keeping
+    /// its edges.
+    ///
+    /// Every mutation writes to the store first and only updates the indices
+    /// once that succeeded, so a failed write leaves the graph unchanged.
+    pub fn add_node(&mut self, node: Node) -> Result<Uuid, StoreError> {
         let id = node.id;
-        let idx = self.graph.add_node(id);
-        self.node_indices.insert(id, idx);
-        self.nodes.insert(id, node);
-        id
+        self.store.insert_node(node)?;
+        if !self.node_indices.contains_key(&id) {
+            let idx = self.graph.add_node(id);
+            self.node_indices.insert(id, idx);
+        }
+        if let Some(node) = self.store.get_node(&id) {
+            self.search_index.insert(node);
+            match &node.embedding {
+                Some(embedding) => self.vector_index.insert(id, embedding),
+                None => self.vector_index.remove(&id),
+            }
+        }
+        Ok(id)
     }
 
     pub fn get_node(&self, id: &Uuid) -> Option<&Node> {
-        self.nodes.get(id)
+        self.store.get_node(id)
     }
 
-    pub fn remove_node(&mut self, id: &Uuid) -> Option<Node> {
-        if let Some(idx) = self.node_indices.remove(id) {
-            self.graph.remove_node(idx);
-            self.edges.retain(|_, e| e.source_id != *id && e.target_id != *id);
-            self.nodes.remove(id)
-        } else {
-            None
+    /// Applies a partial update to a node; its type and edges are unchanged.
+    pub fn update_node(
+        &mut self,
+        id: &Uuid,
+        update: UpdateNodeRequest,
+    ) -> Result<Option<&Node>, StoreError> {
+        let Some(mut node) = self.store.get_node(id).cloned() else {
+            return Ok(None);
+        };
+        if let Some(name) = update.name {
+            node.name = name;
+        }
+        if let Some(path) = update.path {
+            node.path = Some(path);
+        }
+        if let Some(language) = update.language {
+            node.language = Some(language);
+        }
+        if let Some(description) = update.description {
+            node.description = Some(description);
+        }
+        if let Some(metadata) = update.metadata {
+            node.metadata = metadata;
+        }
+        if let Some(embedding) = update.embedding {
+            node.embedding = Some(embedding);
+        }
+        node.updated_at = chrono::Utc::now();
+        self.add_node(node)?;
+        Ok(self.store.get_node(id))
+    }
+
+    /// Removes a node and its edges. If the store fails part way, the edges
+    /// already removed stay removed and the node is kept.
+    pub fn remove_node(&mut self, id: &Uuid) -> Result<Option<Node>, StoreError> {
+        let Some(&idx) = self.node_indices.get(id) else {
+            return Ok(None);
+        };
+        let edge_ids: Vec<Uuid> = self
+            .graph
+            .edges_directed(idx, petgraph::Outgoing)
+            .chain(self.graph.edges_directed(idx, petgraph::Incoming))
+            .map(|e| *e.weight())
+            .collect();
+        for edge_id in edge_ids {
+            self.remove_edge(&edge_id)?;
         }
+        let node = self.store.remove_node(id)?;
+        self.node_indices.remove(id);
+        self.graph.remove_node(idx);
+        self.search_index.remove(id);
+        self.vector_index.remove(id);
+        Ok(node)
     }
 
     pub fn list_nodes(&self) -> Vec<&Node> {
-        self.nodes.values().collect()
+        self.store.nodes().collect()
     }
 
-    pub fn add_edge(&mut self, edge: Edge) -> Option<Uuid> {
-        let source_idx = self.node_indices.get(&edge.source_id)?;
-        let target_idx = self.node_indices.get(&edge.target_id)?;
+    /// Adds an edge, or replaces the edge with the same id. Returns `None`
+    /// when either endpoint is missing.
+    pub fn add_edge(&mut self, edge: Edge) -> Result<Option<Uuid>, StoreError> {
+        let (Some(&source_idx), Some(&target_idx)) = (
+            self.node_indices.get(&edge.source_id),
+            self.node_indices.get(&edge.target_id),
+        ) else {
+            return Ok(None);
+        };
 
-        self.graph.add_edge(*source_idx, *target_idx, edge.weight);
         let id = edge.id;
-        self.edges.insert(id, edge);
-        Some(id)
+        self.store.insert_edge(edge)?;
+        if let Some(old_idx) = self.edge_indices.remove(&id) {
+            self.graph.remove_edge(old_idx);
+        }
+        let idx = self.graph.add_edge(source_idx, target_idx, id);
+        self.edge_indices.insert(id, idx);
+        Ok(Some(id))
     }
 
     pub fn get_edge(&self, id: &Uuid) -> Option<&Edge> {
-        self.edges.get(id)
-    }
-
-    pub fn list_edges(&self) -> Vec<&Edge> {
-        self.edges.values().collect()
+        self.store.get_edge(id)
     }
 
-    pub fn get_edges_for_node(&self, node_id: &Uuid) -> Vec<&Edge> {
-        self.edges
-            .values()
-            .filter(|e| e.source_id == *node_id || e.target_id == *node_id)
-            .collect()
+    /// Applies a partial update to an edge's type, weight or metadata.
+    /// Endpoints are fixed; re-pointing an edge means deleting and recreating it.
+    pub fn update_edge(
+        &mut self,
+        id: &Uuid,
+        update: UpdateEdgeRequest,
+    ) -> Result<Option<&Edge>, StoreError> {
+        let Some(mut edge) = self.store.get_edge(id).cloned() else {
+            return Ok(None);
+        };
+        if let Some(edge_type) = update.edge_type {
+            edge.edge_type = edge_type;
+        }
+        if let Some(weight) = update.weight {
+            edge.weight = weight;
+        }
+        if let Some(metadata) = update.metadata {
+            edge.metadata = metadata;
+        }
+        self.store.insert_edge(edge)?;
+        Ok(self.store.get_edge(id))
     }
 
-    pub fn find_path(&self, source_id: &Uuid, target_id: &Uuid) -> Option<PathResult> {
-        let source_idx = self.node_indices.get(source_id)?;
-        let target_idx = self.node_indices.get(target_id)?;
-
-        let distances = dijkstra(&self.graph, *source_idx, Some(*target_idx), |e| *e.weight());
-
-        if !distances.contains_key(target_idx) {
-            return None;
+    pub fn remove_edge(&mut self, id: &Uuid) -> Result<Option<Edge>, StoreError> {
+        if !self.edge_indices.contains_key(id) {
+            return Ok(None);
         }
-
-        let mut path = vec![*target_id];
-        let mut current = *target_idx;
-        let mut visited = HashSet::new();
-        visited.insert(current);
-
-        while current != *source_idx {
-            let mut found_prev = false;
-            for neighbor in self.graph.neighbors_directed(current, petgraph::Incoming) {
-                if visited.contains(&neighbor) {
-                    continue;
-                }
-                if let Some(&dist) = distances.get(&neighbor) {
-                    let current_dist = distances.get(&current).unwrap();
-                    if let Some(edge) = self.graph.find_edge(neighbor, current) {
-                        let weight = self.graph.edge_weight(edge).unwrap();
-                        if (dist + weight - current_dist).abs() < 0.0001 {
-                            current = neighbor;
-                            let node_id = self.graph[neighbor];
-                            path.push(node_id);
-                            visited.insert(neighbor);
-                            found_prev = true;
-                            break;
-                        }
-                    }
-                }
-            }
-            if !found_prev {
-                break;
-            }
+        let edge = self.store.remove_edge(id)?;
+        if let Some(idx) = self.edge_indices.remove(id) {
+            self.graph.remove_edge(idx);
         }
+        Ok(edge)
+    }
 
-        path.reverse();
-
-        let node_names: Vec<String> = path
-            .iter()
-            .filter_map(|id| self.nodes.get(id).map(|n| n.name.clone()))
-            .collect();
+    pub fn list_edges(&self) -> Vec<&Edge> {
+        self.store.edges().collect()
+    }
 
-        let total_weight = distances.get(target_idx).copied().unwrap_or(0.0);
+    pub fn get_edges_for_node(&self, node_id: &Uuid) -> Vec<&Edge> {
+        self.store.edges_for_node(node_id, &Direction::Both, None)
+    }
 
-        Some(PathResult {
-            path,
-            node_names,
-            total_weight,
-        })
+    pub fn edge_count_for_node(&self, node_id: &Uuid) -> usize {
+        self.store.degree(node_id)
     }
 
+    /// Breadth-first traversal from `node_id`. Each node is reported once,
+    /// at the depth it was first reached, with the edge that reached it.
+    /// Filtering on an edge type also follows its subtypes.
     pub fn find_neighbors(
         &self,
         node_id: &Uuid,
         edge_types: Option<&[EdgeType]>,
         direction: &Direction,
         depth: usize,
-    ) -> Vec<&Node> {
+    ) -> Vec<Neighbor> {
+        let edge_types = edge_types.map(|types| self.schema.edge_subtypes(types));
+        let edge_types = edge_types.as_deref();
         let mut result = Vec::new();
         let mut visited = HashSet::new();
         let mut current_level = vec![*node_id];
         visited.insert(*node_id);
 
-        for _ in 0..depth {
+        for level in 1..=depth {
+            if current_level.is_empty() {
+                break;
+            }
             let mut next_level = Vec::new();
 
             for current_id in &current_level {
-                let edges: Vec<&Edge> = match direction {
-                    Direction::Outgoing => self
-                        .edges
-                        .values()
-                        .filter(|e| e.source_id == *current_id)
-                        .collect(),
-                    Direction::Incoming => self
-                        .edges
-                        .values()
-                        .filter(|e| e.target_id == *current_id)
-                        .collect(),
-                    Direction::Both => self
-                        .edges
-                        .values()
-                        .filter(|e| e.source_id == *current_id || e.target_id == *current_id)
-                        .collect(),
-                };
-
-                for edge in edges {
-                    if let Some(types) = edge_types {
-                        if !types.contains(&edge.edge_type) {
-                            continue;
-                        }
-                    }
-
-                    let neighbor_id = if edge.source_id == *current_id {
-                        edge.target_id
+                for edge in self.store.edges_for_node(current_id, direction, edge_types) {
+                    let (neighbor_id, walked) = if edge.source_id == *current_id {
+                        (edge.target_id, Direction::Outgoing)
                     } else {
-                        edge.source_id
+                        (edge.source_id, Direction::Incoming)
                     };
 
                     if visited.insert(neighbor_id) {
-                        if let Some(node) = self.nodes.get(&neighbor_id) {
-                            result.push(node);
+                        if let Some(node) = self.store.get_node(&neighbor_id) {
+                            result.push(Neighbor {
+                                node: node.clone(),
+                                depth: level,
+                                parent_id: *current_id,
+                                edge: edge.clone(),
+                                direction: walked,
+                            });
                             next_level.push(neighbor_id);
                         }
                     }
@@ -195,25 +360,43 @@ impl KnowledgeGraph {
         result
     }
 
-    pub fn search_nodes(
-        &self,
-        query: &str,
-        node_types: Option<&[NodeType]>,
-        language: Option<&str>,
-        limit: usize,
-    ) -> Vec<&Node> {
-        let query_lower = query.to_lowercase();
-
-        self.nodes
-            .values()
-            .filter(|node| {
-                let name_match = node.name.to_lowercase().contains(&query_lower);
-                let desc_match = node
-                    .description
-                    .as_ref()
-                    .map(|d| d.to_lowercase().contains(&query_lower))
-                    .unwrap_or(false);
+    /// The given nodes plus every edge between two of them, optionally
+    /// restricted to some edge types.
+    pub fn subgraph(&self, node_ids: &[Uuid], edge_types: Option<&[EdgeType]>) -> Subgraph {
+        let edge_types = edge_types.map(|types| self.schema.edge_subtypes(types));
+        let edge_types = edge_types.as_deref();
+        let included: HashSet<Uuid> = node_ids.iter().copied().collect();
+        let nodes = node_ids
+            .iter()
+            .filter_map(|id| self.store.get_node(id).cloned())
+            .collect();
+        let edges = node_ids
+            .iter()
+            .flat_map(|id| {
+                self.store
+                    .edges_for_node(id, &Direction::Outgoing, edge_types)
+            })
+            .filter(|e| included.contains(&e.target_id))
+            .cloned()
+            .collect();
+        Subgraph { nodes, edges }
+    }
+
+    /// Ranked full-text search, or a fused hybrid ranking when
+    /// `query.hybrid` is set. Pages are cut either by `offset` or, when
+    /// given, by the keyset `cursor` returned with the previous page.
+    pub fn search_nodes(&self, query: &SearchQuery) -> SearchResults {
+        let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        let node_types = query
+            .node_types
+            .as_deref()
+            .map(|types| self.schema.node_subtypes(types));
+        let node_types = node_types.as_deref();
+        let language = query.language.as_deref();
+        let cursor = query.cursor.as_deref().and_then(search::decode_cursor);
 
+        let accept = |id: &Uuid| {
+            self.store.get_node(id).is_some_and(|node| {
                 let type_match = node_types
                     .map(|types| types.contains(&node.node_type))
                     .unwrap_or(true);
@@ -222,9 +405,98 @@ impl KnowledgeGraph {
                     .map(|lang| node.language.as_deref() == Some(lang))
                     .unwrap_or(true);
 
-                (name_match || desc_match) && type_match && lang_match
+                type_match && lang_match
+            })
+        };
+
+        let matches: Vec<(Uuid, f64, Option<ScoreBreakdown>)> = match &query.hybrid {
+            Some(options) => self
+                .hybrid_rank(query, options, accept)
+                .into_iter()
+                .map(|(id, score, breakdown)| (id, score, Some(breakdown)))
+                .collect(),
+            None => self
+                .search_index
+                .search(&query.query, query.mode.unwrap_or_default())
+                .into_iter()
+                .filter(|(id, _)| accept(id))
+                .map(|(id, score)| (id, score, None))
+                .collect(),
+        };
+
+        let total = matches.len();
+        let start = match &cursor {
+            Some(cursor) => matches
+                .iter()
+                .position(|(id, score, _)| search::is_after_cursor(*score, id, cursor))
+                .unwrap_or(total),
+            None => query.offset.unwrap_or(0).min(total),
+        };
+        let end = (start + limit).min(total);
+
+        let results: Vec<SearchHit> = matches[start..end]
+            .iter()
+            .filter_map(|(id, score, breakdown)| {
+                Some(SearchHit {
+                    node: self.store.get_node(id)?.clone(),
+                    score: *score,
+                    breakdown: breakdown.clone(),
+                })
+            })
+            .collect();
+        let next_cursor = (end < total)
+            .then(|| results.last())
+            .flatten()
+            .map(|hit| search::encode_cursor(hit.score, &hit.node.id));
+
+        SearchResults {
+            count: results.len(),
+            results,
+            total,
+            next_cursor,
+        }
+    }
+
+    /// Dimension shared by all indexed embeddings, once any exist.
+    pub fn embedding_dimension(&self) -> Option<usize> {
+        self.vector_index.dimension()
+    }
+
+    /// Writes the store has accepted but not yet persisted. Wait on them
+    /// after releasing the graph lock.
+    pub fn pending_writes(&self) -> Option<PendingWrites> {
+        self.store.pending_writes()
+    }
+
+    /// Nodes whose embeddings are nearest to `query.vector`, most similar
+    /// first; `score` is the cosine similarity.
+    pub fn find_similar(&self, query: &SimilarQuery) -> Vec<SearchHit> {
+        let k = query.k.unwrap_or(DEFAULT_SIMILAR_K);
+        let ef = query.ef.unwrap_or(DEFAULT_EF_SEARCH);
+        let node_types = query
+            .node_types
+            .as_deref()
+            .map(|types| self.schema.node_subtypes(types));
+        let node_types = node_types.as_deref();
+        let language = query.language.as_deref();
+
+        let accept = |id: &Uuid| {
+            self.store.get_node(id).is_some_and(|node| {
+                node_types.is_none_or(|types| types.contains(&node.node_type))
+                    && language.is_none_or(|lang| node.language.as_deref() == Some(lang))
+            })
+        };
+
+        self.vector_index
+            .search(&query.vector, k, ef, accept)
+            .into_iter()
+            .filter_map(|(id, score)| {
+                Some(SearchHit {
+                    node: self.store.get_node(&id)?.clone(),
+                    score: score as f64,
+                    breakdown: None,
+                })
             })
-            .take(limit)
             .collect()
     }
 
@@ -232,18 +504,18 @@ impl KnowledgeGraph {
         let mut nodes_by_type: HashMap<String, usize> = HashMap::new();
         let mut edges_by_type: HashMap<String, usize> = HashMap::new();
 
-        for node in self.nodes.values() {
-            let type_str = format!("{:?}", node.node_type).to_lowercase();
+        for node in self.store.nodes() {
+            let type_str = node.node_type.as_str().to_string();
             *nodes_by_type.entry(type_str).or_insert(0) += 1;
         }
 
-        for edge in self.edges.values() {
-            let type_str = format!("{:?}", edge.edge_type).to_lowercase();
+        for edge in self.store.edges() {
+            let type_str = edge.edge_type.as_str().to_string();
             *edges_by_type.entry(type_str).or_insert(0) += 1;
         }
 
-        let total_nodes = self.nodes.len();
-        let total_edges = self.edges.len();
+        let total_nodes = self.store.node_count();
+        let total_edges = self.store.edge_count();
         let avg_edges_per_node = if total_nodes > 0 {
             total_edges as f64 / total_nodes as f64
         } else {
@@ -269,13 +541,15 @@ impl Default for KnowledgeGraph {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::NodeType;
+    use crate::services::schema::TypeDefinition;
 
     #[test]
     fn test_add_and_get_node() {
         let mut graph = KnowledgeGraph::new();
         let node = Node::new("test".to_string(), NodeType::Function);
         let id = node.id;
-        graph.add_node(node);
+        graph.add_node(node).unwrap();
 
         let retrieved = graph.get_node(&id).unwrap();
         assert_eq!(retrieved.name, "test");
@@ -290,11 +564,11 @@ mod tests {
         let id1 = node1.id;
         let id2 = node2.id;
 
-        graph.add_node(node1);
-        graph.add_node(node2);
+        graph.add_node(node1).unwrap();
+        graph.add_node(node2).unwrap();
 
         let edge = Edge::new(id1, id2, EdgeType::Calls);
-        let edge_id = graph.add_edge(edge).unwrap();
+        let edge_id = graph.add_edge(edge).unwrap().unwrap();
 
         let retrieved = graph.get_edge(&edge_id).unwrap();
         assert_eq!(retrieved.source_id, id1);
@@ -312,16 +586,78 @@ mod tests {
         let id2 = node2.id;
         let id3 = node3.id;
 
-        graph.add_node(node1);
-        graph.add_node(node2);
-        graph.add_node(node3);
+        graph.add_node(node1).unwrap();
+        graph.add_node(node2).unwrap();
+        graph.add_node(node3).unwrap();
 
-        graph.add_edge(Edge::new(id1, id2, EdgeType::Calls));
-        graph.add_edge(Edge::new(id2, id3, EdgeType::Calls));
+        graph
+            .add_edge(Edge::new(id1, id2, EdgeType::Calls))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(id2, id3, EdgeType::Calls))
+            .unwrap();
 
         let neighbors = graph.find_neighbors(&id1, None, &Direction::Outgoing, 1);
         assert_eq!(neighbors.len(), 1);
-        assert_eq!(neighbors[0].name, "B");
+        assert_eq!(neighbors[0].node.name, "B");
+
+        // The walk ends with the graph, not the requested depth.
+        let all = graph.find_neighbors(&id1, None, &Direction::Outgoing, usize::MAX);
+        assert_eq!(all.len(), 2);
+        assert!(depth_error(Some(MAX_TRAVERSAL_DEPTH)).is_none());
+        assert!(depth_error(Some(MAX_TRAVERSAL_DEPTH + 1)).is_some());
+    }
+
+    #[test]
+    fn test_find_neighbors_reports_traversal_structure() {
+        let mut graph = KnowledgeGraph::new();
+        let controller = graph
+            .add_node(Node::new("controller".to_string(), NodeType::Function))
+            .unwrap();
+        let auth = graph
+            .add_node(Node::new("handleAuth".to_string(), NodeType::Function))
+            .unwrap();
+        let payment = graph
+            .add_node(Node::new("processPayment".to_string(), NodeType::Function))
+            .unwrap();
+        let module = graph
+            .add_node(Node::new("payments".to_string(), NodeType::Module))
+            .unwrap();
+
+        graph
+            .add_edge(Edge::new(controller, auth, EdgeType::Calls))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(controller, payment, EdgeType::Calls))
+            .unwrap();
+        let imports = graph
+            .add_edge(Edge::new(module, payment, EdgeType::Imports).with_weight(0.5))
+            .unwrap()
+            .unwrap();
+
+        let neighbors = graph.find_neighbors(&auth, None, &Direction::Both, 3);
+        assert_eq!(neighbors.len(), 3);
+
+        let first = &neighbors[0];
+        assert_eq!(first.node.id, controller);
+        assert_eq!(first.depth, 1);
+        assert_eq!(first.parent_id, auth);
+        assert_eq!(first.direction, Direction::Incoming);
+        assert_eq!(first.edge.edge_type, EdgeType::Calls);
+
+        let via_import = neighbors.iter().find(|n| n.node.id == module).unwrap();
+        assert_eq!(via_import.depth, 3);
+        assert_eq!(via_import.parent_id, payment);
+        assert_eq!(via_import.edge.id, imports);
+        assert_eq!(via_import.edge.weight, 0.5);
+        assert_eq!(via_import.direction, Direction::Incoming);
+
+        let ids: Vec<Uuid> = std::iter::once(auth)
+            .chain(neighbors.iter().map(|n| n.node.id))
+            .collect();
+        let subgraph = graph.subgraph(&ids, Some(&[EdgeType::Calls]));
+        assert_eq!(subgraph.nodes.len(), 4);
+        assert_eq!(subgraph.edges.len(), 2);
     }
 
     #[test]
@@ -333,11 +669,405 @@ mod tests {
         let node2 = Node::new("processPayment".to_string(), NodeType::Function)
             .with_language("typescript".to_string());
 
-        graph.add_node(node1);
-        graph.add_node(node2);
+        graph.add_node(node1).unwrap();
+        graph.add_node(node2).unwrap();
 
-        let results = graph.search_nodes("auth", None, None, 10);
+        let results = graph
+            .search_nodes(&SearchQuery::new("auth".to_string()))
+            .results;
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].name, "handleAuth");
+        assert_eq!(results[0].node.name, "handleAuth");
+    }
+
+    #[test]
+    fn test_find_similar_filters_and_follows_removal() {
+        let mut graph = KnowledgeGraph::new();
+        let close = graph
+            .add_node(
+                Node::new("close".to_string(), NodeType::Function)
+                    .with_embedding(vec![1.0, 0.1, 0.0]),
+            )
+            .unwrap();
+        let class = graph
+            .add_node(
+                Node::new("class".to_string(), NodeType::Class).with_embedding(vec![1.0, 0.0, 0.0]),
+            )
+            .unwrap();
+        let far = graph
+            .add_node(
+                Node::new("far".to_string(), NodeType::Function)
+                    .with_embedding(vec![0.0, 0.0, 1.0]),
+            )
+            .unwrap();
+        graph
+            .add_node(Node::new("plain".to_string(), NodeType::Function))
+            .unwrap();
+        assert_eq!(graph.embedding_dimension(), Some(3));
+
+        let mut query = SimilarQuery {
+            vector: vec![1.0, 0.0, 0.0],
+            node_types: None,
+            language: None,
+            k: Some(2),
+            ef: None,
+        };
+        let hits: Vec<Uuid> = graph
+            .find_similar(&query)
+            .iter()
+            .map(|h| h.node.id)
+            .collect();
+        assert_eq!(hits, vec![class, close]);
+
+        query.node_types = Some(vec![NodeType::Function]);
+        let hits: Vec<Uuid> = graph
+            .find_similar(&query)
+            .iter()
+            .map(|h| h.node.id)
+            .collect();
+        assert_eq!(hits, vec![close, far]);
+
+        graph.remove_node(&close).unwrap();
+        let hits: Vec<Uuid> = graph
+            .find_similar(&query)
+            .iter()
+            .map(|h| h.node.id)
+            .collect();
+        assert_eq!(hits, vec![far]);
+    }
+
+    #[test]
+    fn test_type_filters_match_subtypes() {
+        let mut schema = GraphSchema::default();
+        schema.edge_types.push(TypeDefinition {
+            name: EdgeType::Custom("awaits".to_string()),
+            parent: Some(EdgeType::Calls),
+        });
+        let mut graph = KnowledgeGraph::new().with_schema(Arc::new(schema));
+        let method = NodeType::Custom("method".to_string());
+        let charge = graph
+            .add_node(Node::new("charge".to_string(), NodeType::Function))
+            .unwrap();
+        let refund = graph
+            .add_node(Node::new("charge_refund".to_string(), method.clone()))
+            .unwrap();
+        graph
+            .add_node(Node::new("Charge".to_string(), NodeType::Class))
+            .unwrap();
+        let awaits = EdgeType::Custom("awaits".to_string());
+        graph.add_edge(Edge::new(charge, refund, awaits)).unwrap();
+
+        let mut query = SearchQuery::new("charge".to_string());
+        query.node_types = Some(vec![NodeType::Function]);
+        let mut hits: Vec<Uuid> = graph
+            .search_nodes(&query)
+            .results
+            .iter()
+            .map(|h| h.node.id)
+            .collect();
+        hits.sort();
+        let mut expected = vec![charge, refund];
+        expected.sort();
+        assert_eq!(hits, expected);
+        query.node_types = Some(vec![method]);
+        let hits = graph.search_nodes(&query).results;
+        assert_eq!((hits.len(), hits[0].node.id), (1, refund));
+
+        let calls = [EdgeType::Calls];
+        let neighbors = graph.find_neighbors(&charge, Some(&calls), &Direction::Outgoing, 1);
+        assert_eq!(neighbors[0].node.id, refund);
+        assert_eq!(graph.get_stats().edges_by_type.get("awaits"), Some(&1));
+    }
+
+    #[test]
+    fn test_search_pagination_is_stable() {
+        let mut graph = KnowledgeGraph::new();
+        for i in 0..7 {
+            graph
+                .add_node(
+                    Node::new(format!("payment_retry_{}", i), NodeType::Function)
+                        .with_language(if i % 2 == 0 { "python" } else { "rust" }.to_string()),
+                )
+                .unwrap();
+        }
+        graph
+            .add_node(Node::new("unrelated".to_string(), NodeType::Function))
+            .unwrap();
+
+        let all: Vec<Uuid> = graph
+            .search_nodes(&SearchQuery::new("payment".to_string()))
+            .results
+            .iter()
+            .map(|hit| hit.node.id)
+            .collect();
+        assert_eq!(all.len(), 7);
+
+        let mut query = SearchQuery::new("payment".to_string());
+        query.limit = Some(3);
+        let mut paged = Vec::new();
+        loop {
+            let page = graph.search_nodes(&query);
+            assert_eq!(page.total, 7);
+            paged.extend(page.results.iter().map(|hit| hit.node.id));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(paged, all);
+
+        query.cursor = None;
+        query.offset = Some(6);
+        let last = graph.search_nodes(&query);
+        assert_eq!(last.results.len(), 1);
+        assert_eq!(last.results[0].node.id, all[6]);
+        assert!(last.next_cursor.is_none());
+
+        query.offset = None;
+        query.language = Some("python".to_string());
+        assert_eq!(graph.search_nodes(&query).total, 4);
+    }
+
+    #[test]
+    fn test_add_node_with_existing_id_replaces_in_place() {
+        let mut graph = KnowledgeGraph::new();
+        let a = Node::new("A".to_string(), NodeType::Function);
+        let a_id = graph.add_node(a.clone()).unwrap();
+        let b_id = graph
+            .add_node(Node::new("B".to_string(), NodeType::Function))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(a_id, b_id, EdgeType::Calls))
+            .unwrap()
+            .unwrap();
+
+        let mut renamed = a;
+        renamed.name = "Renamed".to_string();
+        graph.add_node(renamed).unwrap();
+
+        assert_eq!(graph.graph.node_count(), 2);
+        assert_eq!(graph.get_node(&a_id).unwrap().name, "Renamed");
+        assert!(graph.find_path(&a_id, &b_id).is_some());
+        assert_eq!(
+            graph.search_nodes(&SearchQuery::new("A".to_string())).total,
+            0
+        );
+        assert_eq!(
+            graph
+                .search_nodes(&SearchQuery::new("renamed".to_string()))
+                .total,
+            1
+        );
+    }
+
+    #[test]
+    fn test_remove_node_keeps_other_indices_valid() {
+        let mut graph = KnowledgeGraph::new();
+
+        let ids: Vec<Uuid> = ["A", "B", "C", "D"]
+            .iter()
+            .map(|name| {
+                graph
+                    .add_node(Node::new(name.to_string(), NodeType::Function))
+                    .unwrap()
+            })
+            .collect();
+
+        // Removing the first node used to move "D" into its slot.
+        graph.remove_node(&ids[0]).unwrap();
+        graph
+            .add_edge(Edge::new(ids[1], ids[3], EdgeType::Calls))
+            .unwrap()
+            .unwrap();
+
+        let path = graph.find_path(&ids[1], &ids[3]).unwrap();
+        assert_eq!(path.path, vec![ids[1], ids[3]]);
+        assert!(graph.find_path(&ids[1], &ids[2]).is_none());
+    }
+
+    #[test]
+    fn test_remove_edge_keeps_graph_in_sync() {
+        let mut graph = KnowledgeGraph::new();
+        let a = graph
+            .add_node(Node::new("A".to_string(), NodeType::Function))
+            .unwrap();
+        let b = graph
+            .add_node(Node::new("B".to_string(), NodeType::Function))
+            .unwrap();
+
+        let edge_id = graph
+            .add_edge(Edge::new(a, b, EdgeType::Calls))
+            .unwrap()
+            .unwrap();
+        assert!(graph.find_path(&a, &b).is_some());
+
+        assert!(graph.remove_edge(&edge_id).unwrap().is_some());
+        assert!(graph.get_edge(&edge_id).is_none());
+        assert!(graph.find_path(&a, &b).is_none());
+        assert!(graph.remove_edge(&edge_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_path_reports_cheapest_parallel_edge() {
+        let mut graph = KnowledgeGraph::new();
+        let a = graph
+            .add_node(Node::new("A".to_string(), NodeType::Function))
+            .unwrap();
+        let b = graph
+            .add_node(Node::new("B".to_string(), NodeType::Function))
+            .unwrap();
+
+        let imports = graph
+            .add_edge(Edge::new(a, b, EdgeType::Imports).with_weight(5.0))
+            .unwrap()
+            .unwrap();
+        let calls = graph
+            .add_edge(Edge::new(a, b, EdgeType::Calls).with_weight(1.0))
+            .unwrap()
+            .unwrap();
+
+        let path = graph.find_path(&a, &b).unwrap();
+        assert_eq!(path.edge_ids, vec![calls]);
+        assert_eq!(path.edge_types, vec![EdgeType::Calls]);
+
+        graph
+            .update_edge(
+                &imports,
+                UpdateEdgeRequest {
+                    edge_type: None,
+                    weight: Some(0.5),
+                    metadata: None,
+                },
+            )
+            .unwrap();
+
+        let path = graph.find_path(&a, &b).unwrap();
+        assert_eq!(path.edge_ids, vec![imports]);
+        assert_eq!(path.total_weight, 0.5);
+    }
+
+    mod model {
+        use super::*;
+        use proptest::prelude::*;
+        use std::collections::VecDeque;
+
+        #[derive(Debug, Clone)]
+        enum Op {
+            AddNode,
+            RemoveNode(usize),
+            AddEdge(usize, usize),
+            RemoveEdge(usize),
+        }
+
+        fn op() -> impl Strategy<Value = Op> {
+            prop_oneof![
+                2 => Just(Op::AddNode),
+                1 => any::<usize>().prop_map(Op::RemoveNode),
+                3 => (any::<usize>(), any::<usize>()).prop_map(|(a, b)| Op::AddEdge(a, b)),
+                1 => any::<usize>().prop_map(Op::RemoveEdge),
+            ]
+        }
+
+        /// Reference model: the live node ids and the live (edge, source, target) triples.
+        #[derive(Default)]
+        struct Model {
+            nodes: Vec<Uuid>,
+            edges: Vec<(Uuid, Uuid, Uuid)>,
+        }
+
+        impl Model {
+            fn successors(&self, id: Uuid) -> HashSet<Uuid> {
+                self.edges
+                    .iter()
+                    .filter(|(_, s, t)| *s == id && *t != id)
+                    .map(|(_, _, t)| *t)
+                    .collect()
+            }
+
+            fn distance(&self, from: Uuid, to: Uuid) -> Option<usize> {
+                let mut seen = HashSet::from([from]);
+                let mut queue = VecDeque::from([(from, 0)]);
+                while let Some((id, dist)) = queue.pop_front() {
+                    if id == to {
+                        return Some(dist);
+                    }
+                    for next in self.successors(id) {
+                        if seen.insert(next) {
+                            queue.push_back((next, dist + 1));
+                        }
+                    }
+                }
+                None
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn interleaved_add_remove_matches_model(ops in prop::collection::vec(op(), 1..60)) {
+                let mut graph = KnowledgeGraph::new();
+                let mut model = Model::default();
+
+                for op in ops {
+                    match op {
+                        Op::AddNode => {
+                            let id = graph
+                                .add_node(Node::new("n".to_string(), NodeType::Function))
+                                .unwrap();
+                            model.nodes.push(id);
+                        }
+                        Op::RemoveNode(i) if !model.nodes.is_empty() => {
+                            let id = model.nodes.remove(i % model.nodes.len());
+                            prop_assert!(graph.remove_node(&id).unwrap().is_some());
+                            model.edges.retain(|(_, s, t)| *s != id && *t != id);
+                        }
+                        Op::AddEdge(a, b) if !model.nodes.is_empty() => {
+                            let source = model.nodes[a % model.nodes.len()];
+                            let target = model.nodes[b % model.nodes.len()];
+                            let edge_id = graph
+                                .add_edge(Edge::new(source, target, EdgeType::Calls))
+                                .unwrap();
+                            prop_assert!(edge_id.is_some());
+                            model.edges.push((edge_id.unwrap(), source, target));
+                        }
+                        Op::RemoveEdge(i) if !model.edges.is_empty() => {
+                            let (edge_id, _, _) = model.edges.remove(i % model.edges.len());
+                            prop_assert!(graph.remove_edge(&edge_id).unwrap().is_some());
+                        }
+                        _ => {}
+                    }
+                }
+
+                prop_assert_eq!(graph.list_nodes().len(), model.nodes.len());
+                prop_assert_eq!(graph.list_edges().len(), model.edges.len());
+
+                for &id in &model.nodes {
+                    let neighbors: HashSet<Uuid> = graph
+                        .find_neighbors(&id, None, &Direction::Outgoing, 1)
+                        .iter()
+                        .map(|n| n.node.id)
+                        .collect();
+                    prop_assert_eq!(neighbors, model.successors(id));
+                }
+
+                for &from in &model.nodes {
+                    for &to in &model.nodes {
+                        let found = graph.find_path(&from, &to);
+                        match model.distance(from, to) {
+                            None => prop_assert!(found.is_none()),
+                            Some(dist) => {
+                                let found = found.unwrap();
+                                prop_assert_eq!(found.path.first(), Some(&from));
+                                prop_assert_eq!(found.path.last(), Some(&to));
+                                prop_assert_eq!(found.path.len(), dist + 1);
+                                prop_assert_eq!(found.total_weight, dist as f64);
+                                prop_assert_eq!(found.edge_ids.len(), dist);
+                                for (hop, edge_id) in found.path.windows(2).zip(&found.edge_ids) {
+                                    prop_assert!(model.edges.contains(&(*edge_id, hop[0], hop[1])));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,314 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::models::{
+    natural_edge_id, BulkUpsertReport, BulkUpsertRequest, Edge, FailureKind, Node, UpsertEdge,
+    UpsertNode, UpsertResult, UpsertStatus,
+};
+use crate::services::store::StoreError;
+use crate::services::vector::embedding_error;
+
+use super::{KnowledgeGraph, PartialUpsert, WriteError};
+
+impl KnowledgeGraph {
+    /// Upserts every node, then every edge, keyed by natural key so that
+    /// repeating a batch updates the same records.
+    ///
+    /// Items are applied independently: a rejected item is reported and
+    /// skipped without rolling back the rest. Edges may reference nodes
+    /// created earlier in the same batch. A store failure stops the batch
+    /// and is returned with the report so far; the items before it stay
+    /// applied.
+    pub fn bulk_upsert(
+        &mut self,
+        request: BulkUpsertRequest,
+    ) -> Result<BulkUpsertReport, PartialUpsert> {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let outcome = request
+            .nodes
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(index, item)| push_result(&mut nodes, index, self.upsert_node(item)))
+            .and_then(|()| {
+                request
+                    .edges
+                    .into_iter()
+                    .enumerate()
+                    .try_for_each(|(index, item)| {
+                        push_result(&mut edges, index, self.upsert_edge(item))
+                    })
+            });
+
+        let count = |status| {
+            nodes
+                .iter()
+                .chain(&edges)
+                .filter(|r| r.status == status)
+                .count()
+        };
+        let report = BulkUpsertReport {
+            created: count(UpsertStatus::Created),
+            updated: count(UpsertStatus::Updated),
+            failed: count(UpsertStatus::Failed),
+            nodes,
+            edges,
+        };
+        match outcome {
+            Ok(()) => Ok(report),
+            Err(error) => Err(PartialUpsert { report, error }),
+        }
+    }
+
+    pub(super) fn upsert_node(
+        &mut self,
+        item: UpsertNode,
+    ) -> Result<(Uuid, UpsertStatus), WriteError> {
+        for (field, value) in [
+            ("repository", &item.key.repository),
+            ("qualified_name", &item.key.qualified_name),
+        ] {
+            if value.is_empty() {
+                let message = "repository and qualified_name are required";
+                return Err(WriteError::invalid_field(field, message));
+            }
+        }
+        let mut metadata = match item.metadata {
+            Some(serde_json::Value::Object(map)) => map,
+            Some(_) => {
+                return Err(WriteError::invalid_field(
+                    "metadata",
+                    "metadata must be an object",
+                ))
+            }
+            None => serde_json::Map::new(),
+        };
+        let dimension = self.embedding_dimension();
+        if let Some(error) = embedding_error(item.embedding.as_deref(), dimension) {
+            return Err(WriteError::invalid_field("embedding", error));
+        }
+
+        let id = item.key.node_id();
+        let existing = self.get_node(&id);
+        let status = if existing.is_some() {
+            UpsertStatus::Updated
+        } else {
+            UpsertStatus::Created
+        };
+
+        metadata.insert("repository".to_string(), item.key.repository.clone().into());
+        metadata.insert(
+            "qualified_name".to_string(),
+            item.key.qualified_name.clone().into(),
+        );
+
+        let now = Utc::now();
+        let node = Node {
+            id,
+            name: item
+                .name
+                .unwrap_or_else(|| item.key.short_name().to_string()),
+            node_type: item.key.node_type,
+            path: item.key.path,
+            language: item.language,
+            description: item.description,
+            metadata: serde_json::Value::Object(metadata),
+            embedding: item.embedding,
+            created_at: existing.map_or(now, |node| node.created_at),
+            updated_at: now,
+        };
+        self.check_node(&node)?;
+        self.add_node(node)?;
+        Ok((id, status))
+    }
+
+    pub(super) fn upsert_edge(
+        &mut self,
+        item: UpsertEdge,
+    ) -> Result<(Uuid, UpsertStatus), WriteError> {
+        let source_id = item.source.node_id();
+        let target_id = item.target.node_id();
+        let endpoints = [
+            ("source", "Source", source_id),
+            ("target", "Target", target_id),
+        ];
+        for (field, label, id) in endpoints {
+            if self.get_node(&id).is_none() {
+                let message = format!("{} node {} not found", label, id);
+                let kind = FailureKind::ReferenceNotFound;
+                return Err(WriteError::rejected(kind, Some(field), message));
+            }
+        }
+
+        let id = natural_edge_id(&source_id, &target_id, &item.edge_type);
+        let existing = self.get_edge(&id);
+        let status = if existing.is_some() {
+            UpsertStatus::Updated
+        } else {
+            UpsertStatus::Created
+        };
+
+        let mut edge = Edge::new(source_id, target_id, item.edge_type);
+        edge.id = id;
+        if let Some(existing) = existing {
+            edge.created_at = existing.created_at;
+        }
+        if let Some(weight) = item.weight {
+            edge = edge.with_weight(weight);
+        }
+        if let Some(metadata) = item.metadata {
+            edge = edge.with_metadata(metadata);
+        }
+        self.check_edge(&edge)?;
+        self.add_edge(edge)?;
+        Ok((id, status))
+    }
+}
+
+fn push_result(
+    results: &mut Vec<UpsertResult>,
+    index: usize,
+    result: Result<(Uuid, UpsertStatus), WriteError>,
+) -> Result<(), StoreError> {
+    let (status, id, error, stored) = match result {
+        Ok((id, status)) => (status, Some(id), None, Ok(())),
+        Err(WriteError::Rejected { message, .. }) => {
+            (UpsertStatus::Failed, None, Some(message), Ok(()))
+        }
+        Err(WriteError::Store(error)) => (
+            UpsertStatus::Failed,
+            None,
+            Some(error.to_string()),
+            Err(error),
+        ),
+    };
+    results.push(UpsertResult {
+        index,
+        status,
+        id,
+        error,
+    });
+    stored
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{EdgeType, NodeKey, NodeRef, NodeType};
+    use crate::services::store::{FailingStore, MemoryStore};
+
+    use super::*;
+
+    fn key(qualified_name: &str, node_type: NodeType) -> NodeKey {
+        NodeKey {
+            repository: "acme/billing".to_string(),
+            path: Some("src/payments.py".to_string()),
+            qualified_name: qualified_name.to_string(),
+            node_type,
+        }
+    }
+
+    fn upsert_node(key: NodeKey) -> UpsertNode {
+        UpsertNode {
+            key,
+            name: None,
+            language: Some("python".to_string()),
+            description: None,
+            metadata: None,
+            embedding: None,
+        }
+    }
+
+    fn batch() -> BulkUpsertRequest {
+        let file = key("payments", NodeType::File);
+        let retry = key("payments.retry", NodeType::Function);
+        BulkUpsertRequest {
+            nodes: vec![upsert_node(file.clone()), upsert_node(retry.clone())],
+            edges: vec![
+                UpsertEdge {
+                    source: NodeRef::Key(file),
+                    target: NodeRef::Key(retry.clone()),
+                    edge_type: EdgeType::Contains,
+                    weight: None,
+                    metadata: None,
+                },
+                UpsertEdge {
+                    source: NodeRef::Key(retry),
+                    target: NodeRef::Key(key("payments.missing", NodeType::Function)),
+                    edge_type: EdgeType::Calls,
+                    weight: None,
+                    metadata: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_repeated_upsert_is_idempotent() {
+        let mut graph = KnowledgeGraph::new();
+
+        let first = graph.bulk_upsert(batch()).unwrap();
+        assert_eq!((first.created, first.updated, first.failed), (3, 0, 1));
+        assert_eq!(first.edges[1].status, UpsertStatus::Failed);
+        assert!(first.edges[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("not found"));
+
+        let retry_id = key("payments.retry", NodeType::Function).node_id();
+        let created_at = graph.get_node(&retry_id).unwrap().created_at;
+        assert_eq!(graph.get_node(&retry_id).unwrap().name, "retry");
+
+        let second = graph.bulk_upsert(batch()).unwrap();
+        assert_eq!((second.created, second.updated, second.failed), (0, 3, 1));
+        assert_eq!(second.nodes[1].id, Some(retry_id));
+        assert_eq!(graph.get_stats().total_nodes, 2);
+        assert_eq!(graph.get_stats().total_edges, 1);
+        assert_eq!(graph.get_node(&retry_id).unwrap().created_at, created_at);
+        assert_eq!(
+            graph.get_node(&retry_id).unwrap().metadata["repository"],
+            "acme/billing"
+        );
+    }
+
+    #[test]
+    fn test_node_type_is_part_of_the_key() {
+        let module = key("payments", NodeType::Module);
+        let file = key("payments", NodeType::File);
+        assert_ne!(module.node_id(), file.node_id());
+        assert_eq!(file.node_id(), key("payments", NodeType::File).node_id());
+
+        let mut graph = KnowledgeGraph::new();
+        let mut item = upsert_node(file);
+        item.metadata = Some(serde_json::json!(["not", "an", "object"]));
+        let report = graph
+            .bulk_upsert(BulkUpsertRequest {
+                nodes: vec![item],
+                edges: Vec::new(),
+            })
+            .unwrap();
+        assert_eq!(report.failed, 1);
+        assert_eq!(graph.get_stats().total_nodes, 0);
+    }
+
+    #[test]
+    fn test_store_failure_returns_partial_report() {
+        // The file node is stored and the retry node is not.
+        let store = FailingStore::new(MemoryStore::new(), 2);
+        let mut graph = KnowledgeGraph::with_store(Box::new(store));
+
+        let partial = graph.bulk_upsert(batch()).unwrap_err();
+        assert!(matches!(partial.error, StoreError::Persist(_)));
+        let report = partial.report;
+        assert_eq!((report.created, report.updated, report.failed), (1, 0, 1));
+        assert_eq!(report.nodes[0].status, UpsertStatus::Created);
+        assert_eq!(report.nodes[1].status, UpsertStatus::Failed);
+        assert!(report.nodes[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("injected failure"));
+        assert!(report.edges.is_empty());
+        assert_eq!(graph.get_stats().total_nodes, 1);
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::models::{GraphProximity, HybridOptions, ScoreBreakdown, SearchQuery, SignalScore};
+use crate::services::vector::DEFAULT_EF_SEARCH;
+
+use super::KnowledgeGraph;
+
+/// Reciprocal-rank fusion constant from the original paper.
+const RRF_K: f64 = 60.0;
+const DEFAULT_ANCHOR_DEPTH: usize = 3;
+/// Nearest embeddings taken as vector candidates.
+const VECTOR_CANDIDATES: usize = 100;
+
+impl KnowledgeGraph {
+    /// Ranks nodes accepted by `accept` by weighted reciprocal-rank fusion of
+    /// the text, vector and graph signals, best first.
+    ///
+    /// Each signal ranks its own candidates; a node scores
+    /// `sum(weight / (RRF_K + rank))` over the signals that ranked it, so a
+    /// node found by several signals beats one found by a single signal.
+    /// Unless `require_text` is off, the vector and graph signals only rank
+    /// nodes that match the text.
+    pub(super) fn hybrid_rank(
+        &self,
+        query: &SearchQuery,
+        options: &HybridOptions,
+        accept: impl Fn(&Uuid) -> bool,
+    ) -> Vec<(Uuid, f64, ScoreBreakdown)> {
+        let weights = options.weights.clone().unwrap_or_default();
+        let mut fused: HashMap<Uuid, (f64, ScoreBreakdown)> = HashMap::new();
+        let mut add = |id: Uuid, contribution: f64, record: &dyn Fn(&mut ScoreBreakdown)| {
+            let entry = fused.entry(id).or_default();
+            entry.0 += contribution;
+            record(&mut entry.1);
+        };
+
+        let text: Vec<(Uuid, f64)> = self
+            .search_index
+            .search(&query.query, query.mode.unwrap_or_default())
+            .into_iter()
+            .filter(|(id, _)| accept(id))
+            .collect();
+        let matched: HashSet<Uuid> = text.iter().map(|(id, _)| *id).collect();
+        let require_text = options.require_text.unwrap_or(true);
+        let candidate = |id: &Uuid| accept(id) && (!require_text || matched.contains(id));
+
+        for (index, (id, score)) in text.into_iter().enumerate() {
+            let rank = index + 1;
+            let contribution = weights.text / (RRF_K + rank as f64);
+            add(id, contribution, &|breakdown| {
+                breakdown.text = Some(SignalScore {
+                    rank,
+                    score,
+                    contribution,
+                })
+            });
+        }
+
+        if let Some(vector) = &options.vector {
+            let hits =
+                self.vector_index
+                    .search(vector, VECTOR_CANDIDATES, DEFAULT_EF_SEARCH, candidate);
+            for (index, (id, similarity)) in hits.into_iter().enumerate() {
+                let rank = index + 1;
+                let contribution = weights.vector / (RRF_K + rank as f64);
+                add(id, contribution, &|breakdown| {
+                    breakdown.vector = Some(SignalScore {
+                        rank,
+                        score: similarity as f64,
+                        contribution,
+                    })
+                });
+            }
+        }
+
+        if let Some(anchors) = &options.anchors {
+            let reached = self.anchor_distances(anchors, options);
+            let mut closer = 0;
+            for (index, (id, distance, anchor_id)) in reached.iter().enumerate() {
+                // Nodes at the same distance share a rank.
+                if index > 0 && reached[index - 1].1 < *distance {
+                    closer = index;
+                }
+                if !candidate(id) {
+                    continue;
+                }
+                let rank = closer + 1;
+                let contribution = weights.graph / (RRF_K + rank as f64);
+                add(*id, contribution, &|breakdown| {
+                    breakdown.graph = Some(GraphProximity {
+                        rank,
+                        distance: *distance,
+                        anchor_id: *anchor_id,
+                        contribution,
+                    })
+                });
+            }
+        }
+
+        let mut ranked: Vec<(Uuid, f64, ScoreBreakdown)> = fused
+            .into_iter()
+            .map(|(id, (score, breakdown))| (id, score, breakdown))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    fn anchor_distances(
+        &self,
+        anchors: &[Uuid],
+        options: &HybridOptions,
+    ) -> Vec<(Uuid, usize, Uuid)> {
+        let depth = options.anchor_depth.unwrap_or(DEFAULT_ANCHOR_DEPTH);
+        let direction = options.direction.clone().unwrap_or_default();
+        let edge_types = options
+            .edge_types
+            .as_ref()
+            .map(|t| self.schema.edge_subtypes(t));
+        let edge_types = edge_types.as_deref();
+
+        let mut visited: HashSet<Uuid> = HashSet::new();
+        let mut current_level: Vec<(Uuid, Uuid)> = Vec::new();
+        let mut reached = Vec::new();
+        for anchor in anchors {
+            if self.store.get_node(anchor).is_some() && visited.insert(*anchor) {
+                current_level.push((*anchor, *anchor));
+                reached.push((*anchor, 0, *anchor));
+            }
+        }
+
+        for level in 1..=depth {
+            if current_level.is_empty() {
+                break;
+            }
+            let mut next_level = Vec::new();
+            for (current_id, anchor_id) in &current_level {
+                for edge in self
+                    .store
+                    .edges_for_node(current_id, &direction, edge_types)
+                {
+                    let neighbor_id = if edge.source_id == *current_id {
+                        edge.target_id
+                    } else {
+                        edge.source_id
+                    };
+                    if visited.insert(neighbor_id) {
+                        next_level.push((neighbor_id, *anchor_id));
+                        reached.push((neighbor_id, level, *anchor_id));
+                    }
+                }
+            }
+            current_level = next_level;
+        }
+
+        reached.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Edge, EdgeType, Node, NodeType};
+
+    use super::*;
+
+    #[test]
+    fn test_hybrid_prefers_text_matches_near_the_anchor() {
+        let mut graph = KnowledgeGraph::new();
+        let file = graph
+            .add_node(Node::new("billing.py".to_string(), NodeType::File))
+            .unwrap();
+        let near = graph
+            .add_node(Node::new("payment_retry".to_string(), NodeType::Function))
+            .unwrap();
+        let far = graph
+            .add_node(Node::new("retry_payment".to_string(), NodeType::Function))
+            .unwrap();
+        let unrelated = graph
+            .add_node(Node::new("render".to_string(), NodeType::Function))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(file, near, EdgeType::Contains))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(file, unrelated, EdgeType::Contains))
+            .unwrap();
+
+        let mut query = SearchQuery::new("payment retry".to_string());
+        query.hybrid = Some(HybridOptions {
+            anchors: Some(vec![file]),
+            ..Default::default()
+        });
+        let results = graph.search_nodes(&query).results;
+        let order: Vec<Uuid> = results.iter().map(|hit| hit.node.id).collect();
+        assert_eq!(order[0], near);
+        assert!(order.contains(&far));
+        // Near the anchor, but not about payments.
+        assert!(!order.contains(&unrelated));
+
+        let breakdown = results[0].breakdown.as_ref().unwrap();
+        let graph_score = breakdown.graph.as_ref().unwrap();
+        assert_eq!(graph_score.distance, 1);
+        assert_eq!(graph_score.anchor_id, file);
+        let text_score = breakdown.text.as_ref().unwrap();
+        assert!(
+            (text_score.contribution + graph_score.contribution - results[0].score).abs() < 1e-12
+        );
+
+        let far_hit = results.iter().find(|hit| hit.node.id == far).unwrap();
+        assert!(far_hit.breakdown.as_ref().unwrap().graph.is_none());
+
+        let hybrid = query.hybrid.as_mut().unwrap();
+        hybrid.require_text = Some(false);
+        hybrid.anchor_depth = Some(usize::MAX);
+        let results = graph.search_nodes(&query).results;
+        assert!(results.iter().any(|hit| hit.node.id == unrelated));
+    }
+
+    #[test]
+    fn test_hybrid_vector_signal_and_weights() {
+        let mut graph = KnowledgeGraph::new();
+        let lexical = graph
+            .add_node(
+                Node::new("charge".to_string(), NodeType::Function).with_embedding(vec![0.0, 1.0]),
+            )
+            .unwrap();
+        let semantic = graph
+            .add_node(
+                Node::new("bill".to_string(), NodeType::Function).with_embedding(vec![1.0, 0.0]),
+            )
+            .unwrap();
+
+        let mut query = SearchQuery::new("charge".to_string());
+        query.hybrid = Some(HybridOptions {
+            vector: Some(vec![1.0, 0.0]),
+            weights: Some(crate::models::HybridWeights {
+                text: 0.0,
+                vector: 1.0,
+                graph: 0.0,
+            }),
+            require_text: Some(false),
+            ..Default::default()
+        });
+        let results = graph.search_nodes(&query).results;
+        assert_eq!(results[0].node.id, semantic);
+        assert_eq!(results[1].node.id, lexical);
+        let vector = results[0]
+            .breakdown
+            .as_ref()
+            .unwrap()
+            .vector
+            .as_ref()
+            .unwrap();
+        assert_eq!(vector.rank, 1);
+        assert!((vector.score - 1.0).abs() < 1e-6);
+    }
+}
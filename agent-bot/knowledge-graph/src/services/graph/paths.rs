@@ -0,0 +1,639 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use uuid::Uuid;
+
+use crate::models::{Direction, Edge, EdgeType, PathMode, PathQuery, PathResult};
+
+use super::KnowledgeGraph;
+
+const DEFAULT_SIMPLE_PATH_DEPTH: usize = 6;
+const DEFAULT_K_SHORTEST: usize = 3;
+const DEFAULT_SIMPLE_PATH_LIMIT: usize = 10;
+const MAX_PATHS: usize = 100;
+/// Edges an all-simple-paths search may follow.
+const SIMPLE_PATH_BUDGET: usize = 100_000;
+
+/// A path as graph indices and edge ids, before it is resolved to names.
+#[derive(Debug, Clone)]
+struct RawPath {
+    nodes: Vec<NodeIndex>,
+    edges: Vec<Uuid>,
+    cost: f64,
+}
+
+/// One way of reaching a node in the label-setting search.
+struct Label {
+    node: NodeIndex,
+    hops: usize,
+    cost: f64,
+    parent: Option<(usize, Uuid)>,
+}
+
+struct Candidate {
+    cost: f64,
+    hops: usize,
+    label: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed so `BinaryHeap` pops the cheapest, then shortest, candidate.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.hops.cmp(&self.hops))
+    }
+}
+
+/// The path an all-simple-paths search is extending.
+struct SimpleWalk {
+    nodes: Vec<NodeIndex>,
+    edges: Vec<Uuid>,
+    on_path: HashSet<NodeIndex>,
+    /// Edges left to follow.
+    budget: usize,
+}
+
+struct PathSearch<'a> {
+    graph: &'a KnowledgeGraph,
+    edge_types: Option<&'a [EdgeType]>,
+    edge_costs: Option<&'a HashMap<EdgeType, f64>>,
+    direction: Direction,
+    max_depth: usize,
+}
+
+impl<'a> PathSearch<'a> {
+    fn steps(&self, from: NodeIndex) -> impl Iterator<Item = (NodeIndex, &'a Edge)> + '_ {
+        let topology = &self.graph.graph;
+        let forward = matches!(self.direction, Direction::Outgoing | Direction::Both).then(|| {
+            topology
+                .edges_directed(from, petgraph::Outgoing)
+                .map(|e| (e.target(), e.weight()))
+        });
+        let backward = matches!(self.direction, Direction::Incoming | Direction::Both).then(|| {
+            topology
+                .edges_directed(from, petgraph::Incoming)
+                .map(|e| (e.source(), e.weight()))
+        });
+
+        forward
+            .into_iter()
+            .flatten()
+            .chain(backward.into_iter().flatten())
+            .filter_map(move |(next, edge_id)| {
+                let edge = self.graph.store.get_edge(edge_id)?;
+                let allowed = self
+                    .edge_types
+                    .is_none_or(|types| types.contains(&edge.edge_type));
+                allowed.then_some((next, edge))
+            })
+    }
+
+    /// The edge weight scaled by the query's multiplier for its type.
+    fn cost(&self, edge: &Edge) -> f64 {
+        let multiplier = self
+            .edge_costs
+            .and_then(|costs| costs.get(&edge.edge_type))
+            .copied()
+            .unwrap_or(1.0);
+        edge.weight * multiplier
+    }
+
+    /// Cheapest path of at most `max_hops` edges avoiding the banned ones.
+    fn shortest(
+        &self,
+        source: NodeIndex,
+        target: NodeIndex,
+        max_hops: usize,
+        banned_nodes: &HashSet<NodeIndex>,
+        banned_edges: &HashSet<Uuid>,
+    ) -> Option<RawPath> {
+        let mut labels = vec![Label {
+            node: source,
+            hops: 0,
+            cost: 0.0,
+            parent: None,
+        }];
+        let mut settled_hops: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut heap = BinaryHeap::from([Candidate {
+            cost: 0.0,
+            hops: 0,
+            label: 0,
+        }]);
+
+        while let Some(Candidate { label, .. }) = heap.pop() {
+            let (node, hops, cost) = {
+                let l = &labels[label];
+                (l.node, l.hops, l.cost)
+            };
+            if settled_hops.get(&node).is_some_and(|&h| h <= hops) {
+                continue;
+            }
+            settled_hops.insert(node, hops);
+
+            if node == target {
+                return Some(self.rebuild(&labels, label));
+            }
+            if hops >= max_hops {
+                continue;
+            }
+
+            for (next, edge) in self.steps(node) {
+                if banned_nodes.contains(&next) || banned_edges.contains(&edge.id) {
+                    continue;
+                }
+                if settled_hops.get(&next).is_some_and(|&h| h <= hops + 1) {
+                    continue;
+                }
+                let next_cost = cost + self.cost(edge);
+                labels.push(Label {
+                    node: next,
+                    hops: hops + 1,
+                    cost: next_cost,
+                    parent: Some((label, edge.id)),
+                });
+                heap.push(Candidate {
+                    cost: next_cost,
+                    hops: hops + 1,
+                    label: labels.len() - 1,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn rebuild(&self, labels: &[Label], mut label: usize) -> RawPath {
+        let cost = labels[label].cost;
+        let mut nodes = vec![labels[label].node];
+        let mut edges = Vec::new();
+        while let Some((parent, edge_id)) = labels[label].parent {
+            edges.push(edge_id);
+            nodes.push(labels[parent].node);
+            label = parent;
+        }
+        nodes.reverse();
+        edges.reverse();
+        RawPath { nodes, edges, cost }
+    }
+
+    fn edges_cost(&self, edge_ids: &[Uuid]) -> f64 {
+        edge_ids
+            .iter()
+            .filter_map(|id| self.graph.store.get_edge(id))
+            .map(|e| self.cost(e))
+            .sum()
+    }
+
+    /// Yen's algorithm: the `k` cheapest loopless paths, cheapest first.
+    fn k_shortest(&self, source: NodeIndex, target: NodeIndex, k: usize) -> Vec<RawPath> {
+        let Some(first) = self.shortest(
+            source,
+            target,
+            self.max_depth,
+            &HashSet::new(),
+            &HashSet::new(),
+        ) else {
+            return Vec::new();
+        };
+        let mut accepted = vec![first];
+        let mut candidates: Vec<RawPath> = Vec::new();
+
+        while accepted.len() < k {
+            let previous = accepted.last().unwrap().clone();
+
+            for spur_at in 0..previous.edges.len() {
+                let spur_node = previous.nodes[spur_at];
+                let root_edges = &previous.edges[..spur_at];
+
+                let banned_edges: HashSet<Uuid> = accepted
+                    .iter()
+                    .filter(|p| p.edges.len() > spur_at && p.edges[..spur_at] == *root_edges)
+                    .map(|p| p.edges[spur_at])
+                    .collect();
+                let banned_nodes: HashSet<NodeIndex> =
+                    previous.nodes[..spur_at].iter().copied().collect();
+
+                let remaining_hops = self.max_depth.saturating_sub(spur_at);
+                let Some(spur) = self.shortest(
+                    spur_node,
+                    target,
+                    remaining_hops,
+                    &banned_nodes,
+                    &banned_edges,
+                ) else {
+                    continue;
+                };
+
+                let mut nodes = previous.nodes[..spur_at].to_vec();
+                nodes.extend(spur.nodes);
+                let mut edges = root_edges.to_vec();
+                edges.extend(spur.edges);
+                let candidate = RawPath {
+                    cost: self.edges_cost(&edges),
+                    nodes,
+                    edges,
+                };
+
+                let known = accepted
+                    .iter()
+                    .chain(candidates.iter())
+                    .any(|p| p.edges == candidate.edges);
+                if !known {
+                    candidates.push(candidate);
+                }
+            }
+
+            let best = candidates
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    a.cost
+                        .total_cmp(&b.cost)
+                        .then_with(|| a.edges.len().cmp(&b.edges.len()))
+                })
+                .map(|(i, _)| i);
+            match best {
+                Some(i) => accepted.push(candidates.swap_remove(i)),
+                None => break,
+            }
+        }
+
+        accepted
+    }
+
+    /// The first `limit` loopless paths found, sorted by cost.
+    fn all_simple(&self, source: NodeIndex, target: NodeIndex, limit: usize) -> Vec<RawPath> {
+        let mut found = Vec::new();
+        let mut walk = SimpleWalk {
+            nodes: vec![source],
+            edges: Vec::new(),
+            on_path: HashSet::from([source]),
+            budget: SIMPLE_PATH_BUDGET,
+        };
+        self.extend_simple(target, limit, &mut walk, &mut found);
+
+        found.sort_by(|a, b| {
+            a.cost
+                .total_cmp(&b.cost)
+                .then_with(|| a.edges.len().cmp(&b.edges.len()))
+        });
+        found
+    }
+
+    fn extend_simple(
+        &self,
+        target: NodeIndex,
+        limit: usize,
+        walk: &mut SimpleWalk,
+        found: &mut Vec<RawPath>,
+    ) {
+        let current = *walk.nodes.last().unwrap();
+        if current == target {
+            found.push(RawPath {
+                nodes: walk.nodes.clone(),
+                edges: walk.edges.clone(),
+                cost: self.edges_cost(&walk.edges),
+            });
+            return;
+        }
+        if walk.edges.len() >= self.max_depth {
+            return;
+        }
+
+        for (next, edge) in self.steps(current) {
+            if found.len() >= limit || walk.budget == 0 {
+                return;
+            }
+            walk.budget -= 1;
+            if !walk.on_path.insert(next) {
+                continue;
+            }
+            walk.nodes.push(next);
+            walk.edges.push(edge.id);
+            self.extend_simple(target, limit, walk, found);
+            walk.edges.pop();
+            walk.nodes.pop();
+            walk.on_path.remove(&next);
+        }
+    }
+
+    fn resolve(&self, raw: RawPath) -> PathResult {
+        let path: Vec<Uuid> = raw.nodes.iter().map(|idx| self.graph.graph[*idx]).collect();
+        let node_names = path
+            .iter()
+            .filter_map(|id| self.graph.store.get_node(id).map(|n| n.name.clone()))
+            .collect();
+        let edges: Vec<&Edge> = raw
+            .edges
+            .iter()
+            .filter_map(|id| self.graph.store.get_edge(id))
+            .collect();
+        let directions = edges
+            .iter()
+            .zip(&path)
+            .map(|(edge, from)| {
+                if edge.source_id == *from {
+                    Direction::Outgoing
+                } else {
+                    Direction::Incoming
+                }
+            })
+            .collect();
+
+        PathResult {
+            path,
+            node_names,
+            edge_ids: raw.edges,
+            edge_types: edges.iter().map(|e| e.edge_type.clone()).collect(),
+            directions,
+            total_weight: raw.cost,
+        }
+    }
+}
+
+impl KnowledgeGraph {
+    /// Runs a path query. `shortest` yields at most one path; `k_shortest`
+    /// and `all_simple` yield up to `k` paths, cheapest first.
+    pub fn find_paths(&self, query: &PathQuery) -> Vec<PathResult> {
+        let (Some(source), Some(target)) = (
+            self.node_indices.get(&query.source_id),
+            self.node_indices.get(&query.target_id),
+        ) else {
+            return Vec::new();
+        };
+
+        let mode = query.mode.clone().unwrap_or_default();
+        let default_depth = match mode {
+            PathMode::AllSimple => DEFAULT_SIMPLE_PATH_DEPTH,
+            _ => usize::MAX,
+        };
+        let edge_types = query
+            .edge_types
+            .as_deref()
+            .map(|types| self.schema.edge_subtypes(types));
+        let search = PathSearch {
+            graph: self,
+            edge_types: edge_types.as_deref(),
+            edge_costs: query.edge_costs.as_ref(),
+            direction: query.direction.clone().unwrap_or(Direction::Outgoing),
+            max_depth: query.max_depth.unwrap_or(default_depth),
+        };
+
+        let raw = match mode {
+            PathMode::Shortest => search
+                .shortest(
+                    *source,
+                    *target,
+                    search.max_depth,
+                    &HashSet::new(),
+                    &HashSet::new(),
+                )
+                .into_iter()
+                .collect(),
+            PathMode::KShortest => {
+                let k = query.k.unwrap_or(DEFAULT_K_SHORTEST).clamp(1, MAX_PATHS);
+                search.k_shortest(*source, *target, k)
+            }
+            PathMode::AllSimple => {
+                let limit = query
+                    .k
+                    .unwrap_or(DEFAULT_SIMPLE_PATH_LIMIT)
+                    .clamp(1, MAX_PATHS);
+                search.all_simple(*source, *target, limit)
+            }
+        };
+
+        raw.into_iter().map(|p| search.resolve(p)).collect()
+    }
+
+    pub fn find_path(&self, source_id: &Uuid, target_id: &Uuid) -> Option<PathResult> {
+        self.find_paths(&PathQuery::new(*source_id, *target_id))
+            .into_iter()
+            .next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Node, NodeType};
+
+    fn function(graph: &mut KnowledgeGraph, name: &str) -> Uuid {
+        graph
+            .add_node(Node::new(name.to_string(), NodeType::Function))
+            .unwrap()
+    }
+
+    fn call(graph: &mut KnowledgeGraph, from: Uuid, to: Uuid, weight: f64) -> Uuid {
+        graph
+            .add_edge(Edge::new(from, to, EdgeType::Calls).with_weight(weight))
+            .unwrap()
+            .unwrap()
+    }
+
+    /// a -> b -> c -> d (cost 3) and a -> d (cost 10), plus a -> e -> d (cost 4).
+    fn diamond() -> (KnowledgeGraph, [Uuid; 5]) {
+        let mut graph = KnowledgeGraph::new();
+        let a = function(&mut graph, "a");
+        let b = function(&mut graph, "b");
+        let c = function(&mut graph, "c");
+        let d = function(&mut graph, "d");
+        let e = function(&mut graph, "e");
+        call(&mut graph, a, b, 1.0);
+        call(&mut graph, b, c, 1.0);
+        call(&mut graph, c, d, 1.0);
+        call(&mut graph, a, d, 10.0);
+        call(&mut graph, a, e, 2.0);
+        call(&mut graph, e, d, 2.0);
+        (graph, [a, b, c, d, e])
+    }
+
+    #[test]
+    fn test_max_depth_is_enforced() {
+        let (graph, [a, b, c, d, e]) = diamond();
+
+        let unbounded = graph.find_path(&a, &d).unwrap();
+        assert_eq!(unbounded.path, vec![a, b, c, d]);
+        assert_eq!(unbounded.total_weight, 3.0);
+
+        let mut query = PathQuery::new(a, d);
+        query.max_depth = Some(2);
+        let bounded = graph.find_paths(&query);
+        assert_eq!(bounded[0].path, vec![a, e, d]);
+
+        query.max_depth = Some(1);
+        assert_eq!(graph.find_paths(&query)[0].path, vec![a, d]);
+
+        query.target_id = c;
+        query.max_depth = Some(1);
+        assert!(graph.find_paths(&query).is_empty());
+
+        query.target_id = d;
+        query.max_depth = Some(3);
+        assert_eq!(graph.find_paths(&query)[0].path, vec![a, b, c, d]);
+    }
+
+    #[test]
+    fn test_edge_type_filter() {
+        let (mut graph, [a, _, _, d, e]) = diamond();
+        let imports = graph
+            .add_edge(Edge::new(a, d, EdgeType::Imports).with_weight(0.1))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(graph.find_path(&a, &d).unwrap().edge_ids, vec![imports]);
+
+        let mut query = PathQuery::new(a, d);
+        query.edge_types = Some(vec![EdgeType::Calls]);
+        query.max_depth = Some(2);
+        let path = &graph.find_paths(&query)[0];
+        assert_eq!(path.path, vec![a, e, d]);
+        assert!(path.edge_types.iter().all(|t| *t == EdgeType::Calls));
+    }
+
+    #[test]
+    fn test_k_shortest_paths_in_cost_order() {
+        let (graph, [a, b, c, d, e]) = diamond();
+
+        let mut query = PathQuery::new(a, d);
+        query.mode = Some(PathMode::KShortest);
+        query.k = Some(5);
+        let paths = graph.find_paths(&query);
+
+        let routes: Vec<Vec<Uuid>> = paths.iter().map(|p| p.path.clone()).collect();
+        assert_eq!(routes, vec![vec![a, b, c, d], vec![a, e, d], vec![a, d]]);
+        let costs: Vec<f64> = paths.iter().map(|p| p.total_weight).collect();
+        assert_eq!(costs, vec![3.0, 4.0, 10.0]);
+    }
+
+    #[test]
+    fn test_k_shortest_distinguishes_parallel_edges() {
+        let mut graph = KnowledgeGraph::new();
+        let a = function(&mut graph, "a");
+        let b = function(&mut graph, "b");
+        let cheap = call(&mut graph, a, b, 1.0);
+        let dear = call(&mut graph, a, b, 2.0);
+
+        let mut query = PathQuery::new(a, b);
+        query.mode = Some(PathMode::KShortest);
+        query.k = Some(3);
+        let edge_ids: Vec<Vec<Uuid>> = graph
+            .find_paths(&query)
+            .into_iter()
+            .map(|p| p.edge_ids)
+            .collect();
+        assert_eq!(edge_ids, vec![vec![cheap], vec![dear]]);
+    }
+
+    #[test]
+    fn test_all_simple_paths_respects_depth_and_limit() {
+        let (mut graph, [a, b, _, d, _]) = diamond();
+        // A cycle must not produce repeated nodes.
+        call(&mut graph, b, a, 1.0);
+
+        let mut query = PathQuery::new(a, d);
+        query.mode = Some(PathMode::AllSimple);
+        let paths = graph.find_paths(&query);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0].total_weight, 3.0);
+        for path in &paths {
+            let unique: HashSet<&Uuid> = path.path.iter().collect();
+            assert_eq!(unique.len(), path.path.len());
+        }
+
+        query.max_depth = Some(2);
+        assert_eq!(graph.find_paths(&query).len(), 2);
+
+        query.k = Some(1);
+        assert_eq!(graph.find_paths(&query).len(), 1);
+    }
+
+    #[test]
+    fn test_all_simple_paths_gives_up_on_dense_graphs() {
+        // Every node calls every later one, and the target is unreachable,
+        // so an exhaustive search would visit 2^30 paths.
+        let mut graph = KnowledgeGraph::new();
+        let nodes: Vec<Uuid> = (0..30)
+            .map(|i| function(&mut graph, &format!("f{}", i)))
+            .collect();
+        for (i, from) in nodes.iter().enumerate() {
+            for to in &nodes[i + 1..] {
+                call(&mut graph, *from, *to, 1.0);
+            }
+        }
+        let target = function(&mut graph, "unreachable");
+
+        let mut query = PathQuery::new(nodes[0], target);
+        query.mode = Some(PathMode::AllSimple);
+        query.max_depth = Some(32);
+        assert!(graph.find_paths(&query).is_empty());
+    }
+
+    #[test]
+    fn test_undirected_path_through_common_caller() {
+        let mut graph = KnowledgeGraph::new();
+        let controller = function(&mut graph, "controller");
+        let auth = function(&mut graph, "handleAuth");
+        let payment = function(&mut graph, "processPayment");
+        let to_auth = call(&mut graph, controller, auth, 1.0);
+        let to_payment = call(&mut graph, controller, payment, 1.0);
+
+        assert!(graph.find_path(&auth, &payment).is_none());
+
+        let mut query = PathQuery::new(auth, payment);
+        query.direction = Some(Direction::Both);
+        let path = &graph.find_paths(&query)[0];
+        assert_eq!(path.path, vec![auth, controller, payment]);
+        assert_eq!(path.edge_ids, vec![to_auth, to_payment]);
+        assert_eq!(
+            path.directions,
+            vec![Direction::Incoming, Direction::Outgoing]
+        );
+
+        let mut reverse = PathQuery::new(payment, controller);
+        reverse.direction = Some(Direction::Incoming);
+        let path = &graph.find_paths(&reverse)[0];
+        assert_eq!(path.path, vec![payment, controller]);
+        assert_eq!(path.directions, vec![Direction::Incoming]);
+    }
+
+    #[test]
+    fn test_edge_type_costs_change_route() {
+        let mut graph = KnowledgeGraph::new();
+        let a = function(&mut graph, "a");
+        let b = function(&mut graph, "b");
+        let c = function(&mut graph, "c");
+        graph.add_edge(Edge::new(a, c, EdgeType::Contains)).unwrap();
+        call(&mut graph, a, b, 1.0);
+        call(&mut graph, b, c, 1.0);
+
+        assert_eq!(graph.find_path(&a, &c).unwrap().path, vec![a, c]);
+
+        let mut query = PathQuery::new(a, c);
+        query.edge_costs = Some(HashMap::from([
+            (EdgeType::Contains, 10.0),
+            (EdgeType::Calls, 0.5),
+        ]));
+        let path = &graph.find_paths(&query)[0];
+        assert_eq!(path.path, vec![a, b, c]);
+        assert_eq!(path.total_weight, 1.0);
+    }
+}
@@ -0,0 +1,401 @@
+use uuid::Uuid;
+
+use crate::models::{
+    natural_edge_id, Edge, FailureKind, Node, Operation, TransactionError, TransactionResult,
+};
+use crate::services::store::StoreError;
+use crate::services::vector::embedding_error;
+
+use super::{KnowledgeGraph, WriteError};
+
+/// How to revert one applied operation.
+enum Undo {
+    RemoveNode(Uuid),
+    RestoreNode(Node),
+    /// A deleted node together with the edges its deletion removed. Either
+    /// may still be present if the deletion failed part way.
+    RestoreNodeWithEdges(Node, Vec<Edge>),
+    RemoveEdge(Uuid),
+    RestoreEdge(Edge),
+}
+
+impl KnowledgeGraph {
+    /// Applies `operations` in order, all or nothing.
+    ///
+    /// Callers hold the graph's write lock for the whole call, so readers see
+    /// either none or all of the changes. Each applied operation records how
+    /// to revert itself; when one fails, the recorded undos run in reverse
+    /// and the failing operation is reported. Persistent stores see the
+    /// reverted writes too, so they end in the pre-transaction state.
+    pub fn apply_transaction(
+        &mut self,
+        operations: Vec<Operation>,
+    ) -> Result<TransactionResult, TransactionError> {
+        let mut undo_log = Vec::with_capacity(operations.len());
+        let mut ids = Vec::with_capacity(operations.len());
+
+        for (index, operation) in operations.into_iter().enumerate() {
+            let op = operation.name();
+            match self.apply_operation(operation, &mut undo_log) {
+                Ok(id) => ids.push(id),
+                Err(failure) => {
+                    self.rollback(undo_log);
+                    let error = failure.to_string();
+                    let (kind, field) = match failure {
+                        WriteError::Rejected { kind, field, .. } => (kind, field),
+                        WriteError::Store(_) => (FailureKind::Storage, None),
+                    };
+                    return Err(TransactionError {
+                        index,
+                        op: op.to_string(),
+                        error,
+                        kind,
+                        field,
+                    });
+                }
+            }
+        }
+
+        Ok(TransactionResult {
+            committed: true,
+            ids,
+        })
+    }
+
+    fn apply_operation(
+        &mut self,
+        operation: Operation,
+        undo_log: &mut Vec<Undo>,
+    ) -> Result<Uuid, WriteError> {
+        match operation {
+            Operation::CreateNode { id, node } => {
+                let mut node: Node = node.into();
+                if let Some(id) = id {
+                    if self.get_node(&id).is_some() {
+                        return Err(conflict(format!("Node {} already exists", id)));
+                    }
+                    node.id = id;
+                }
+                self.check_embedding(node.embedding.as_deref())?;
+                self.check_node(&node)?;
+                undo_log.push(Undo::RemoveNode(node.id));
+                Ok(self.add_node(node)?)
+            }
+            Operation::UpdateNode { id, update } => {
+                let previous = self
+                    .get_node(&id)
+                    .cloned()
+                    .ok_or_else(|| node_not_found(id))?;
+                self.check_embedding(update.embedding.as_deref())?;
+                self.check_node_update(&id, &update)?;
+                undo_log.push(Undo::RestoreNode(previous));
+                self.update_node(&id, update)?;
+                Ok(id)
+            }
+            Operation::DeleteNode { id } => {
+                let node = self
+                    .get_node(&id)
+                    .cloned()
+                    .ok_or_else(|| node_not_found(id))?;
+                let edges: Vec<Edge> = self.get_edges_for_node(&id).into_iter().cloned().collect();
+                // The edges go one write at a time, so a failure can leave
+                // some removed; the undo must already be logged by then.
+                undo_log.push(Undo::RestoreNodeWithEdges(node, edges));
+                self.remove_node(&id)?;
+                Ok(id)
+            }
+            Operation::UpsertNode { node } => {
+                let id = node.key.node_id();
+                let undo = match self.get_node(&id) {
+                    Some(previous) => Undo::RestoreNode(previous.clone()),
+                    None => Undo::RemoveNode(id),
+                };
+                self.upsert_node(node)?;
+                undo_log.push(undo);
+                Ok(id)
+            }
+            Operation::CreateEdge { id, edge } => {
+                let mut edge: Edge = edge.into();
+                if let Some(id) = id {
+                    if self.get_edge(&id).is_some() {
+                        return Err(conflict(format!("Edge {} already exists", id)));
+                    }
+                    edge.id = id;
+                }
+                for (field, id) in [("source_id", edge.source_id), ("target_id", edge.target_id)] {
+                    if self.get_node(&id).is_none() {
+                        let message = format!("Node {} in {} not found", id, field);
+                        let kind = FailureKind::ReferenceNotFound;
+                        return Err(WriteError::rejected(kind, Some(field), message));
+                    }
+                }
+                self.check_edge(&edge)?;
+                let id = edge.id;
+                self.add_edge(edge)?;
+                undo_log.push(Undo::RemoveEdge(id));
+                Ok(id)
+            }
+            Operation::UpdateEdge { id, update } => {
+                let previous = self
+                    .get_edge(&id)
+                    .cloned()
+                    .ok_or_else(|| edge_not_found(id))?;
+                self.check_edge_update(&id, &update)?;
+                undo_log.push(Undo::RestoreEdge(previous));
+                self.update_edge(&id, update)?;
+                Ok(id)
+            }
+            Operation::DeleteEdge { id } => {
+                let edge = self.remove_edge(&id)?.ok_or_else(|| edge_not_found(id))?;
+                undo_log.push(Undo::RestoreEdge(edge));
+                Ok(id)
+            }
+            Operation::UpsertEdge { edge } => {
+                let id = natural_edge_id(
+                    &edge.source.node_id(),
+                    &edge.target.node_id(),
+                    &edge.edge_type,
+                );
+                let undo = match self.get_edge(&id) {
+                    Some(previous) => Undo::RestoreEdge(previous.clone()),
+                    None => Undo::RemoveEdge(id),
+                };
+                self.upsert_edge(edge)?;
+                undo_log.push(undo);
+                Ok(id)
+            }
+        }
+    }
+
+    fn check_embedding(&self, embedding: Option<&[f32]>) -> Result<(), WriteError> {
+        match embedding_error(embedding, self.embedding_dimension()) {
+            Some(error) => Err(WriteError::invalid_field("embedding", error)),
+            None => Ok(()),
+        }
+    }
+
+    /// An undo the store fails to record is logged and the rest still run.
+    fn rollback(&mut self, undo_log: Vec<Undo>) {
+        for undo in undo_log.into_iter().rev() {
+            if let Err(err) = self.undo(undo) {
+                tracing::error!("Failed to roll back transaction: {}", err);
+            }
+        }
+    }
+
+    fn undo(&mut self, undo: Undo) -> Result<(), StoreError> {
+        match undo {
+            Undo::RemoveNode(id) => {
+                self.remove_node(&id)?;
+            }
+            Undo::RestoreNode(node) => {
+                self.add_node(node)?;
+            }
+            Undo::RestoreNodeWithEdges(node, edges) => {
+                self.add_node(node)?;
+                for edge in edges {
+                    self.add_edge(edge)?;
+                }
+            }
+            Undo::RemoveEdge(id) => {
+                self.remove_edge(&id)?;
+            }
+            Undo::RestoreEdge(edge) => {
+                self.add_edge(edge)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn node_not_found(id: Uuid) -> WriteError {
+    WriteError::rejected(
+        FailureKind::NodeNotFound,
+        None,
+        format!("Node {} not found", id),
+    )
+}
+
+fn edge_not_found(id: Uuid) -> WriteError {
+    WriteError::rejected(
+        FailureKind::EdgeNotFound,
+        None,
+        format!("Edge {} not found", id),
+    )
+}
+
+fn conflict(message: String) -> WriteError {
+    WriteError::rejected(FailureKind::Conflict, None, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{CreateEdgeRequest, CreateNodeRequest, EdgeType, NodeType};
+    use crate::services::store::{FailingStore, MemoryStore};
+
+    use super::*;
+
+    fn create_node(id: Uuid, name: &str) -> Operation {
+        Operation::CreateNode {
+            id: Some(id),
+            node: CreateNodeRequest {
+                name: name.to_string(),
+                node_type: NodeType::Function,
+                path: Some("src/payments.py".to_string()),
+                language: None,
+                description: None,
+                metadata: None,
+                embedding: None,
+            },
+        }
+    }
+
+    fn create_edge(source_id: Uuid, target_id: Uuid) -> Operation {
+        Operation::CreateEdge {
+            id: None,
+            edge: CreateEdgeRequest {
+                source_id,
+                target_id,
+                edge_type: EdgeType::Calls,
+                weight: None,
+                metadata: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_reindex_file_commits_atomically() {
+        let mut graph = KnowledgeGraph::new();
+        let file = graph
+            .add_node(Node::new("payments.py".to_string(), NodeType::File))
+            .unwrap();
+        let old = graph
+            .add_node(Node::new("old_retry".to_string(), NodeType::Function))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(file, old, EdgeType::Contains))
+            .unwrap()
+            .unwrap();
+
+        let new = Uuid::new_v4();
+        let result = graph
+            .apply_transaction(vec![
+                Operation::DeleteNode { id: old },
+                create_node(new, "retry"),
+                create_edge(file, new),
+            ])
+            .unwrap();
+
+        assert!(result.committed);
+        assert_eq!(result.ids[0], old);
+        assert_eq!(result.ids[1], new);
+        assert!(graph.get_node(&old).is_none());
+        assert_eq!(graph.get_node(&new).unwrap().name, "retry");
+        assert_eq!(graph.get_stats().total_edges, 1);
+    }
+
+    #[test]
+    fn test_failure_rolls_back_every_operation() {
+        let mut graph = KnowledgeGraph::new();
+        let file = graph
+            .add_node(Node::new("payments.py".to_string(), NodeType::File))
+            .unwrap();
+        let old = graph
+            .add_node(Node::new("old_retry".to_string(), NodeType::Function))
+            .unwrap();
+        let contains = graph
+            .add_edge(Edge::new(file, old, EdgeType::Contains))
+            .unwrap()
+            .unwrap();
+        let calls = graph
+            .add_edge(Edge::new(old, old, EdgeType::Calls))
+            .unwrap()
+            .unwrap();
+
+        let new = Uuid::new_v4();
+        let error = graph
+            .apply_transaction(vec![
+                Operation::UpdateNode {
+                    id: file,
+                    update: crate::models::UpdateNodeRequest {
+                        name: Some("renamed.py".to_string()),
+                        ..Default::default()
+                    },
+                },
+                Operation::DeleteNode { id: old },
+                create_node(new, "retry"),
+                create_edge(file, new),
+                Operation::DeleteEdge { id: Uuid::new_v4() },
+            ])
+            .unwrap_err();
+
+        assert_eq!(error.index, 4);
+        assert_eq!(error.op, "delete_edge");
+        assert!(error.error.contains("not found"));
+        assert_eq!(error.kind, FailureKind::EdgeNotFound);
+
+        assert_eq!(graph.get_node(&file).unwrap().name, "payments.py");
+        assert_eq!(graph.get_node(&old).unwrap().name, "old_retry");
+        assert!(graph.get_node(&new).is_none());
+        assert!(graph.get_edge(&contains).is_some());
+        assert!(graph.get_edge(&calls).is_some());
+        assert_eq!(graph.get_stats().total_nodes, 2);
+        assert_eq!(graph.get_stats().total_edges, 2);
+        assert!(graph.find_path(&file, &old).is_some());
+        assert_eq!(
+            graph
+                .search_nodes(&crate::models::SearchQuery::new("retry".to_string()))
+                .total,
+            1
+        );
+    }
+
+    #[test]
+    fn test_operations_deserialize_from_flat_json() {
+        let request: crate::models::TransactionRequest =
+            serde_json::from_value(serde_json::json!({
+                "operations": [
+                    { "op": "create_node", "name": "retry", "node_type": "function" },
+                    { "op": "update_edge", "id": Uuid::nil(), "weight": 2.0 },
+                    { "op": "upsert_node", "repository": "acme/billing", "path": null,
+                      "qualified_name": "payments.retry", "node_type": "function" }
+                ]
+            }))
+            .unwrap();
+
+        assert!(
+            matches!(&request.operations[0], Operation::CreateNode { id: None, node } if node.name == "retry")
+        );
+        assert!(
+            matches!(&request.operations[1], Operation::UpdateEdge { update, .. } if update.weight == Some(2.0))
+        );
+        assert_eq!(request.operations[2].name(), "upsert_node");
+    }
+
+    #[test]
+    fn test_store_failure_mid_delete_restores_edges() {
+        let mut memory = MemoryStore::new();
+        let file = Node::new("payments.py".to_string(), NodeType::File);
+        let retry = Node::new("retry".to_string(), NodeType::Function);
+        let contains = Edge::new(file.id, retry.id, EdgeType::Contains);
+        let calls = Edge::new(retry.id, retry.id, EdgeType::Calls);
+        let (file_id, retry_id) = (file.id, retry.id);
+        memory.put_node(file);
+        memory.put_node(retry);
+        memory.put_edge(contains.clone());
+        memory.put_edge(calls.clone());
+
+        // The first edge removal succeeds and the second fails.
+        let store = FailingStore::new(memory, 2);
+        let mut graph = KnowledgeGraph::with_store(Box::new(store));
+        let error = graph
+            .apply_transaction(vec![Operation::DeleteNode { id: retry_id }])
+            .unwrap_err();
+
+        assert_eq!(error.kind, FailureKind::Storage);
+        assert!(graph.get_node(&retry_id).is_some());
+        assert!(graph.get_edge(&contains.id).is_some());
+        assert!(graph.get_edge(&calls.id).is_some());
+        assert!(graph.find_path(&file_id, &retry_id).is_some());
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::models::{
+    Direction, Edge, Node, UpdateEdgeRequest, UpdateNodeRequest, ValidationReport, Violation,
+};
+use crate::services::schema::GraphSchema;
+
+use super::KnowledgeGraph;
+
+/// Violations listed individually in a validation report.
+const MAX_REPORTED_VIOLATIONS: usize = 1000;
+
+impl KnowledgeGraph {
+    pub fn with_schema(mut self, schema: Arc<GraphSchema>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    pub fn schema(&self) -> &GraphSchema {
+        &self.schema
+    }
+
+    /// Checks a node about to be written.
+    pub fn check_node(&self, node: &Node) -> Result<(), Violation> {
+        self.schema.check_node(node)
+    }
+
+    /// Checks the node `id` would become after `update`; a missing node
+    /// passes, leaving the caller to report it.
+    pub fn check_node_update(
+        &self,
+        id: &Uuid,
+        update: &UpdateNodeRequest,
+    ) -> Result<(), Violation> {
+        let (Some(node), Some(name)) = (self.get_node(id), &update.name) else {
+            return Ok(());
+        };
+        let mut node = node.clone();
+        node.name = name.clone();
+        self.check_node(&node)
+    }
+
+    /// Checks an edge about to be written, counting it against cardinality
+    /// limits together with the stored edges it does not replace. Edges
+    /// with a missing endpoint pass, leaving `add_edge` to reject them.
+    pub fn check_edge(&self, edge: &Edge) -> Result<(), Violation> {
+        self.schema.check_edge_type(edge)?;
+        self.schema.check_weight(edge)?;
+        let (Some(source), Some(target)) = (
+            self.get_node(&edge.source_id),
+            self.get_node(&edge.target_id),
+        ) else {
+            return Ok(());
+        };
+        self.schema.check_endpoints(edge, source, target)?;
+
+        for (node, field, direction) in [
+            (source, "source_id", Direction::Outgoing),
+            (target, "target_id", Direction::Incoming),
+        ] {
+            for rule in self.schema.limits(&node.node_type, &edge.edge_type) {
+                let Some(max) = rule.max else {
+                    continue;
+                };
+                if rule.direction != direction && rule.direction != Direction::Both {
+                    continue;
+                }
+                let edge_types = self
+                    .schema
+                    .edge_subtypes(std::slice::from_ref(&rule.edge_type));
+                let others = self
+                    .store
+                    .edges_for_node(&node.id, &rule.direction, Some(&edge_types))
+                    .into_iter()
+                    .filter(|other| other.id != edge.id)
+                    .count();
+                if others < max {
+                    continue;
+                }
+                if let Err(mut violation) = rule.check(node, others + 1) {
+                    violation.field = field.to_string();
+                    return Err(violation);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the edge `id` would become after `update`; a missing edge
+    /// passes, leaving the caller to report it.
+    pub fn check_edge_update(
+        &self,
+        id: &Uuid,
+        update: &UpdateEdgeRequest,
+    ) -> Result<(), Violation> {
+        let Some(edge) = self.get_edge(id) else {
+            return Ok(());
+        };
+        let mut edge = edge.clone();
+        if let Some(edge_type) = &update.edge_type {
+            edge.edge_type = edge_type.clone();
+        }
+        if let Some(weight) = update.weight {
+            edge.weight = weight;
+        }
+        self.check_edge(&edge)
+    }
+
+    /// Audits every node and edge against the schema, including the
+    /// minimum edge counts that writes cannot enforce.
+    pub fn validate(&self) -> ValidationReport {
+        let mut violations = Vec::new();
+        for node in self.store.nodes() {
+            violations.extend(self.check_node(node).err());
+            let rules = self.schema.cardinality.iter();
+            for rule in rules.filter(|r| self.schema.node_is_a(&node.node_type, &r.node_type)) {
+                let edge_types = self
+                    .schema
+                    .edge_subtypes(std::slice::from_ref(&rule.edge_type));
+                let count = self
+                    .store
+                    .edges_for_node(&node.id, &rule.direction, Some(&edge_types))
+                    .len();
+                violations.extend(rule.check(node, count).err());
+            }
+        }
+        for edge in self.store.edges() {
+            violations.extend(self.schema.check_edge_type(edge).err());
+            violations.extend(self.schema.check_weight(edge).err());
+            if let (Some(source), Some(target)) = (
+                self.get_node(&edge.source_id),
+                self.get_node(&edge.target_id),
+            ) {
+                violations.extend(self.schema.check_endpoints(edge, source, target).err());
+            }
+        }
+
+        let total = violations.len();
+        violations.truncate(MAX_REPORTED_VIOLATIONS);
+        ValidationReport {
+            valid: total == 0,
+            nodes_checked: self.store.node_count(),
+            edges_checked: self.store.edge_count(),
+            total,
+            violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        BulkUpsertRequest, CreateEdgeRequest, EdgeType, NodeKey, NodeType, Operation, SchemaRule,
+        UpsertNode,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_writes_are_checked_and_audited() {
+        let mut graph = KnowledgeGraph::new();
+        let billing = graph
+            .add_node(Node::new("billing".to_string(), NodeType::Repository))
+            .unwrap();
+        let ledger = graph
+            .add_node(Node::new("ledger".to_string(), NodeType::Repository))
+            .unwrap();
+        let file = graph
+            .add_node(Node::new("payments.py".to_string(), NodeType::File))
+            .unwrap();
+        let retry = graph
+            .add_node(Node::new("retry".to_string(), NodeType::Function))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(billing, file, EdgeType::Contains))
+            .unwrap()
+            .unwrap();
+
+        // A file has one container; re-writing the same edge is not a second.
+        let second = Edge::new(ledger, file, EdgeType::Contains);
+        let violation = graph.check_edge(&second).unwrap_err();
+        assert_eq!(violation.rule, SchemaRule::Cardinality);
+        assert_eq!(
+            (violation.field.as_str(), violation.node_id),
+            ("target_id", Some(file))
+        );
+        let existing = graph.list_edges()[0].clone();
+        assert!(graph.check_edge(&existing).is_ok());
+
+        let error = graph
+            .apply_transaction(vec![Operation::CreateEdge {
+                id: None,
+                edge: CreateEdgeRequest {
+                    source_id: retry,
+                    target_id: billing,
+                    edge_type: EdgeType::Contains,
+                    weight: None,
+                    metadata: None,
+                },
+            }])
+            .unwrap_err();
+        assert!(error.error.contains("from a function to a repository"));
+
+        let report = graph
+            .bulk_upsert(BulkUpsertRequest {
+                nodes: vec![UpsertNode {
+                    key: NodeKey {
+                        repository: "billing".to_string(),
+                        path: None,
+                        qualified_name: "payments.retry".to_string(),
+                        node_type: NodeType::Function,
+                    },
+                    name: Some(" ".to_string()),
+                    language: None,
+                    description: None,
+                    metadata: None,
+                    embedding: None,
+                }],
+                edges: Vec::new(),
+            })
+            .unwrap();
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.nodes[0].error.as_deref(), Some("Node name is empty"));
+
+        // Written before the rules applied: a negative weight, and a file
+        // outside any repository.
+        graph
+            .add_edge(Edge::new(retry, retry, EdgeType::Calls).with_weight(-1.0))
+            .unwrap();
+        graph
+            .add_node(Node::new("orphan.py".to_string(), NodeType::File))
+            .unwrap();
+        let audit = graph.validate();
+        assert!(!audit.valid);
+        assert_eq!((audit.nodes_checked, audit.edges_checked), (5, 2));
+        let rules: Vec<SchemaRule> = audit.violations.iter().map(|v| v.rule).collect();
+        assert_eq!(audit.total, 2);
+        assert!(rules.contains(&SchemaRule::Weight) && rules.contains(&SchemaRule::Cardinality));
+    }
+}
@@ -0,0 +1,1082 @@
+//! Built-in source indexer for local repository checkouts.
+//!
+//! Walks a checkout under `REPOS_DIR` and extracts files, definitions and
+//! imports from Python, TypeScript/JavaScript and Rust sources, with line
+//! spans and best-effort call and import edges. Each language is parsed with
+//! its tree-sitter grammar, and one walk over the syntax tree collects the
+//! items, calls and imports. Results become natural-key upserts, like gkg
+//! ingestion.
+
+mod python;
+mod rust;
+mod tree;
+mod typescript;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::models::{
+    natural_edge_id, BulkUpsertRequest, Edge, EdgeType, IndexReport, Node, NodeKey, NodeRef,
+    NodeType, UpsertEdge, UpsertNode,
+};
+use crate::services::graph::KnowledgeGraph;
+use crate::services::registry;
+use crate::services::store::StoreError;
+
+/// Directories never descended into.
+const SKIPPED_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "venv",
+    ".venv",
+    "__pycache__",
+];
+/// Larger files are usually generated or vendored and are skipped.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    /// One checkout per repository, named after it.
+    pub repos_dir: PathBuf,
+}
+
+impl IndexerConfig {
+    /// Reads `REPOS_DIR`.
+    pub fn from_env() -> Self {
+        Self {
+            repos_dir: std::env::var("REPOS_DIR")
+                .unwrap_or_else(|_| "/data/repos".to_string())
+                .into(),
+        }
+    }
+
+    /// The checkout for `repository`, if it exists. Names that could escape
+    /// the repos dir are rejected.
+    pub fn repository_dir(&self, repository: &str) -> Option<PathBuf> {
+        if !registry::is_valid_name(repository) {
+            return None;
+        }
+        let dir = self.repos_dir.join(repository);
+        dir.is_dir().then_some(dir)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    TypeScript,
+    JavaScript,
+    Rust,
+}
+
+impl Language {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "py" => Some(Self::Python),
+            "ts" | "tsx" | "mts" | "cts" => Some(Self::TypeScript),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "rs" => Some(Self::Rust),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Python => "python",
+            Self::TypeScript => "typescript",
+            Self::JavaScript => "javascript",
+            Self::Rust => "rust",
+        }
+    }
+}
+
+/// A function, class or other item found in a file.
+#[derive(Debug, Clone)]
+struct Definition {
+    name: String,
+    /// Name within the file, e.g. `PaymentService.retry`.
+    qualified_name: String,
+    node_type: NodeType,
+    /// 1-based, inclusive.
+    start_line: usize,
+    end_line: usize,
+    /// Index of the enclosing definition.
+    parent: Option<usize>,
+    /// Names called from the body, in order of first use.
+    calls: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct ParsedFile {
+    definitions: Vec<Definition>,
+    /// Module specifiers as written, e.g. `./audit` or `crate::graph`.
+    imports: Vec<String>,
+}
+
+/// One parsed source file.
+#[derive(Debug)]
+pub struct SourceFile {
+    /// Relative to the repository root, with `/` separators.
+    pub path: String,
+    pub language: Language,
+    pub lines: usize,
+    /// Hex-encoded SHA-256 of the contents.
+    pub content_hash: String,
+    parsed: ParsedFile,
+}
+
+impl SourceFile {
+    pub fn parse(path: String, language: Language, source: &str) -> Self {
+        let parsed = match language {
+            Language::Python => python::parse(source),
+            Language::TypeScript => typescript::parse(source, path.ends_with(".tsx")),
+            Language::JavaScript => typescript::parse(source, true),
+            Language::Rust => rust::parse(source),
+        };
+        Self {
+            path,
+            language,
+            lines: source.lines().count(),
+            content_hash: hex::encode(Sha256::digest(source.as_bytes())),
+            parsed,
+        }
+    }
+}
+
+/// Parses every supported source file under `root`, sorted by path.
+pub fn scan_repository(root: &Path) -> Vec<SourceFile> {
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        entry.depth() == 0
+            || !entry.file_type().is_dir()
+            || !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+    });
+
+    let mut files = Vec::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.extend(read_source(path, entry.path()));
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// Parses the repository-relative `paths` that exist under `root` and would
+/// be picked up by a full scan, sorted by path.
+pub fn scan_paths(root: &Path, paths: &[String]) -> Vec<SourceFile> {
+    let mut files: Vec<SourceFile> = paths
+        .iter()
+        .filter(|path| {
+            !path
+                .split('/')
+                .any(|segment| SKIPPED_DIRS.contains(&segment))
+        })
+        .filter_map(|path| read_source(path.clone(), &root.join(path)))
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+fn read_source(path: String, full_path: &Path) -> Option<SourceFile> {
+    let language = Language::from_path(full_path)?;
+    let metadata = std::fs::metadata(full_path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let source = std::fs::read_to_string(full_path).ok()?;
+    Some(SourceFile::parse(path, language, &source))
+}
+
+/// What the graph holds from the last time a repository was indexed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexedState {
+    /// Commit recorded on the repository node.
+    pub commit: Option<String>,
+    /// Content hash recorded on each file node, by path.
+    pub hashes: HashMap<String, String>,
+}
+
+/// Reads the indexed commit and file hashes of `repository` from `graph`.
+pub fn indexed_state(graph: &KnowledgeGraph, repository: &str) -> IndexedState {
+    let commit = graph
+        .get_node(&registry::repository_key(repository).node_id())
+        .and_then(|node| node.metadata.get("commit")?.as_str().map(str::to_string));
+    let hashes = graph
+        .list_nodes()
+        .into_iter()
+        .filter(|node| node.node_type == NodeType::File && in_repository(node, repository))
+        .filter_map(|node| {
+            let hash = node.metadata.get("content_hash")?.as_str()?;
+            Some((node.path.clone()?, hash.to_string()))
+        })
+        .collect();
+    IndexedState { commit, hashes }
+}
+
+/// Files to re-index and paths to drop, from [`detect_changes`].
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// `HEAD` of the checkout, when it is a git repository.
+    pub commit: Option<String>,
+    pub previous_commit: Option<String>,
+    /// Whether the candidate files came from `git diff` rather than a scan.
+    pub incremental: bool,
+    /// New or modified files.
+    pub files: Vec<SourceFile>,
+    /// Indexed paths that no longer exist or are no longer indexable.
+    pub removed: Vec<String>,
+}
+
+/// Works out what changed in the checkout at `root` since `previous`. When
+/// both the recorded commit and `HEAD` are known, only the files in `git
+/// diff` between them are read; otherwise the whole checkout is scanned.
+/// Files whose content hash matches the recorded one are dropped unless
+/// `full` is set, which also forces a scan.
+pub async fn detect_changes(
+    root: PathBuf,
+    previous: IndexedState,
+    full: bool,
+) -> anyhow::Result<Changes> {
+    let commit = git_head(&root).await;
+    let diff = match (&previous.commit, &commit) {
+        (Some(old), Some(new)) if !full => git_diff(&root, old, new).await,
+        _ => None,
+    };
+
+    let mut changes = Changes {
+        commit,
+        previous_commit: previous.commit.clone(),
+        incremental: diff.is_some(),
+        ..Default::default()
+    };
+    let indexed: Vec<String> = previous.hashes.keys().cloned().collect();
+    let (mut files, mut removed) = tokio::task::spawn_blocking(move || match diff {
+        Some(diff) => {
+            let files = scan_paths(&root, &diff.changed);
+            let mut removed = diff.deleted;
+            removed.extend(
+                diff.changed
+                    .into_iter()
+                    .filter(|path| !files.iter().any(|f| &f.path == path)),
+            );
+            (files, removed)
+        }
+        None => {
+            let files = scan_repository(&root);
+            let present: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+            let removed = indexed
+                .into_iter()
+                .filter(|path| !present.contains(path.as_str()))
+                .collect();
+            (files, removed)
+        }
+    })
+    .await?;
+
+    removed.retain(|path| previous.hashes.contains_key(path));
+    removed.sort();
+    if !full {
+        files.retain(|f| previous.hashes.get(&f.path) != Some(&f.content_hash));
+    }
+    changes.files = files;
+    changes.removed = removed;
+    Ok(changes)
+}
+
+/// Paths changed between two commits, from `git diff --name-status`.
+#[derive(Debug, Default, PartialEq)]
+struct GitDiff {
+    /// Added or modified.
+    changed: Vec<String>,
+    deleted: Vec<String>,
+}
+
+async fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = async_process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// `HEAD` of the checkout at `root`, or `None` outside a git repository.
+async fn git_head(root: &Path) -> Option<String> {
+    let head = git(root, &["rev-parse", "--verify", "HEAD"]).await?;
+    Some(head.trim().to_string())
+}
+
+/// `None` when either commit is not available locally.
+async fn git_diff(root: &Path, old: &str, new: &str) -> Option<GitDiff> {
+    let is_commit = |sha: &str| !sha.is_empty() && sha.chars().all(|c| c.is_ascii_hexdigit());
+    if !is_commit(old) || !is_commit(new) {
+        return None;
+    }
+    let output = git(
+        root,
+        &["diff", "--name-status", "--no-renames", "-z", old, new],
+    )
+    .await?;
+    Some(parse_name_status(&output))
+}
+
+/// Parses `git diff --name-status -z` output: a status and a path per entry.
+fn parse_name_status(output: &str) -> GitDiff {
+    let mut diff = GitDiff::default();
+    let mut fields = output.split('\0');
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        if status.starts_with('D') {
+            diff.deleted.push(path.to_string());
+        } else {
+            diff.changed.push(path.to_string());
+        }
+    }
+    diff
+}
+
+fn file_key(repository: &str, path: &str) -> NodeKey {
+    NodeKey {
+        repository: repository.to_string(),
+        path: Some(path.to_string()),
+        qualified_name: path.to_string(),
+        node_type: NodeType::File,
+    }
+}
+
+fn in_repository(node: &Node, repository: &str) -> bool {
+    node.metadata.get("repository").and_then(|r| r.as_str()) == Some(repository)
+}
+
+/// Upserts for one file; calls and file-to-file imports are left to [`link`].
+fn file_request(repository: &str, file: &SourceFile) -> BulkUpsertRequest {
+    let mut request = BulkUpsertRequest::default();
+    let key = |qualified_name: &str, node_type| NodeKey {
+        repository: repository.to_string(),
+        path: Some(file.path.clone()),
+        qualified_name: qualified_name.to_string(),
+        node_type,
+    };
+    let contains = |source: &NodeKey, target: &NodeKey| edge(source, target, EdgeType::Contains);
+
+    let file_key = file_key(repository, &file.path);
+    let name = file
+        .path
+        .rsplit('/')
+        .next()
+        .unwrap_or(&file.path)
+        .to_string();
+    let metadata = serde_json::json!({
+        "lines": file.lines,
+        "content_hash": file.content_hash,
+    });
+    request.nodes.push(node(
+        file_key.clone(),
+        Some(name),
+        Some(file.language),
+        Some(metadata),
+    ));
+    request
+        .edges
+        .push(contains(&registry::repository_key(repository), &file_key));
+
+    let mut keys: Vec<NodeKey> = Vec::with_capacity(file.parsed.definitions.len());
+    for definition in &file.parsed.definitions {
+        let definition_key = key(&definition.qualified_name, definition.node_type.clone());
+        let metadata = serde_json::json!({
+            "start_line": definition.start_line,
+            "end_line": definition.end_line,
+            "calls": definition.calls,
+        });
+        request.nodes.push(node(
+            definition_key.clone(),
+            Some(definition.name.clone()),
+            Some(file.language),
+            Some(metadata),
+        ));
+        let parent = definition.parent.map_or(&file_key, |parent| &keys[parent]);
+        request.edges.push(contains(parent, &definition_key));
+        keys.push(definition_key);
+    }
+
+    let mut imported = HashSet::new();
+    for import in &file.parsed.imports {
+        if !imported.insert(import) {
+            continue;
+        }
+        let import_key = key(import, NodeType::Import);
+        request.nodes.push(node(
+            import_key.clone(),
+            Some(import.clone()),
+            Some(file.language),
+            None,
+        ));
+        request
+            .edges
+            .push(edge(&file_key, &import_key, EdgeType::Imports));
+    }
+
+    request
+}
+
+fn node(
+    key: NodeKey,
+    name: Option<String>,
+    language: Option<Language>,
+    metadata: Option<serde_json::Value>,
+) -> UpsertNode {
+    UpsertNode {
+        key,
+        name,
+        language: language.map(|l| l.name().to_string()),
+        description: None,
+        metadata,
+        embedding: None,
+    }
+}
+
+fn edge(source: &NodeKey, target: &NodeKey, edge_type: EdgeType) -> UpsertEdge {
+    UpsertEdge {
+        source: NodeRef::Key(source.clone()),
+        target: NodeRef::Key(target.clone()),
+        edge_type,
+        weight: None,
+        metadata: None,
+    }
+}
+
+/// The file node, its definitions and its import nodes.
+fn file_subgraph(graph: &KnowledgeGraph, file_id: Uuid) -> Vec<Uuid> {
+    let mut owned = vec![file_id];
+    let mut seen = HashSet::from([file_id]);
+    let mut index = 0;
+    while index < owned.len() {
+        let source = owned[index];
+        for edge in graph.get_edges_for_node(&source) {
+            if edge.source_id != source {
+                continue;
+            }
+            let owns = match edge.edge_type {
+                EdgeType::Contains => true,
+                EdgeType::Imports => graph
+                    .get_node(&edge.target_id)
+                    .is_some_and(|target| target.node_type == NodeType::Import),
+                _ => false,
+            };
+            if owns && seen.insert(edge.target_id) {
+                owned.push(edge.target_id);
+            }
+        }
+        index += 1;
+    }
+    owned
+}
+
+/// Edges that [`link`] derives rather than [`file_request`] upserting them.
+fn is_link(graph: &KnowledgeGraph, edge: &Edge) -> bool {
+    match edge.edge_type {
+        EdgeType::Calls => true,
+        EdgeType::Imports => graph
+            .get_node(&edge.target_id)
+            .is_some_and(|target| target.node_type == NodeType::File),
+        _ => false,
+    }
+}
+
+/// What an index run changed, which is all [`link`] has to revisit.
+#[derive(Debug, Default)]
+struct Touched {
+    /// Changed files, by path.
+    paths: HashSet<String>,
+    /// Names of items that were in, or are now in, changed or removed
+    /// files; calls to them may resolve differently.
+    names: HashSet<String>,
+    /// Files were added or removed, so any import may resolve differently.
+    files_moved: bool,
+}
+
+/// Re-resolves the calls and imports `touched` can affect; returns the
+/// number of edges added and removed.
+fn link(
+    graph: &mut KnowledgeGraph,
+    repository: &str,
+    touched: &Touched,
+) -> Result<(usize, usize), StoreError> {
+    let mut file_ids: HashMap<String, Uuid> = HashMap::new();
+    // (id, path, name, calls) of each definition, and (path, specifier) of
+    // each import to resolve.
+    let mut definitions: Vec<(Uuid, String, String, Vec<String>)> = Vec::new();
+    let mut imports: Vec<(String, String)> = Vec::new();
+    for node in graph.list_nodes() {
+        let Some(path) = node
+            .path
+            .as_ref()
+            .filter(|_| in_repository(node, repository))
+        else {
+            continue;
+        };
+        match node.node_type {
+            NodeType::File if node.metadata.get("content_hash").is_some() => {
+                file_ids.insert(path.clone(), node.id);
+            }
+            NodeType::Import if touched.files_moved || touched.paths.contains(path) => {
+                imports.push((path.clone(), node.name.clone()));
+            }
+            NodeType::Class | NodeType::Function | NodeType::Module | NodeType::Constant => {
+                let calls = node
+                    .metadata
+                    .get("calls")
+                    .and_then(|calls| serde_json::from_value(calls.clone()).ok())
+                    .unwrap_or_default();
+                definitions.push((node.id, path.clone(), node.name.clone(), calls));
+            }
+            _ => {}
+        }
+    }
+    definitions.retain(|(_, path, _, _)| file_ids.contains_key(path));
+    let paths: HashSet<&str> = file_ids.keys().map(String::as_str).collect();
+    let mut by_name: HashMap<&str, Vec<(Uuid, &str)>> = HashMap::new();
+    for (id, path, name, _) in &definitions {
+        by_name.entry(name).or_default().push((*id, path));
+    }
+
+    let mut wanted: HashMap<Uuid, (Uuid, Uuid, EdgeType)> = HashMap::new();
+    let mut want = |source: Uuid, target: Uuid, edge_type: EdgeType| {
+        let id = natural_edge_id(&source, &target, &edge_type);
+        wanted.insert(id, (source, target, edge_type));
+    };
+    let mut sources: Vec<Uuid> = Vec::new();
+    for (source, path, _, calls) in &definitions {
+        let relink =
+            touched.paths.contains(path) || calls.iter().any(|call| touched.names.contains(call));
+        if !relink {
+            continue;
+        }
+        sources.push(*source);
+        for call in calls {
+            let Some(candidates) = by_name.get(call.as_str()) else {
+                continue;
+            };
+            let local = candidates.iter().find(|(_, p)| p == path);
+            let target = local.or(match candidates.as_slice() {
+                [only] => Some(only),
+                _ => None,
+            });
+            if let Some((target, _)) = target {
+                if target != source {
+                    want(*source, *target, EdgeType::Calls);
+                }
+            }
+        }
+    }
+    for (path, file_id) in &file_ids {
+        if touched.files_moved || touched.paths.contains(path) {
+            sources.push(*file_id);
+        }
+    }
+    for (path, import) in &imports {
+        let (Some(file_id), Some(language)) =
+            (file_ids.get(path), Language::from_path(Path::new(path)))
+        else {
+            continue;
+        };
+        if let Some(target) = resolve_import(language, path, import, &paths) {
+            if target != *path {
+                want(*file_id, file_ids[&target], EdgeType::Imports);
+            }
+        }
+    }
+
+    let mut stale = Vec::new();
+    for source in &sources {
+        for edge in graph.get_edges_for_node(source) {
+            let natural = natural_edge_id(&edge.source_id, &edge.target_id, &edge.edge_type);
+            if edge.source_id == *source
+                && edge.id == natural
+                && is_link(graph, edge)
+                && !wanted.contains_key(&edge.id)
+            {
+                stale.push(edge.id);
+            }
+        }
+    }
+    for id in &stale {
+        graph.remove_edge(id)?;
+    }
+
+    let mut added = 0;
+    for (id, (source, target, edge_type)) in wanted {
+        if graph.get_edge(&id).is_none() {
+            let mut edge = Edge::new(source, target, edge_type);
+            edge.id = id;
+            graph.add_edge(edge)?;
+            added += 1;
+        }
+    }
+    Ok((added, stale.len()))
+}
+
+fn resolve_import(
+    language: Language,
+    from: &str,
+    import: &str,
+    paths: &HashSet<&str>,
+) -> Option<String> {
+    let dir = from.rsplit_once('/').map_or("", |(dir, _)| dir);
+    match language {
+        Language::Python => {
+            let dots = import.chars().take_while(|&c| c == '.').count();
+            let module = import[dots..].replace('.', "/");
+            let candidates = |base: &str| {
+                let stem = join(base, &module);
+                [format!("{}.py", stem), format!("{}/__init__.py", stem)]
+            };
+            if dots > 0 {
+                let base = ancestor(dir, dots - 1)?;
+                candidates(&base)
+                    .into_iter()
+                    .find(|c| paths.contains(c.as_str()))
+            } else {
+                // Absolute imports may be rooted at any source directory.
+                candidates("").into_iter().find_map(|candidate| {
+                    paths
+                        .iter()
+                        .find(|p| **p == candidate || p.ends_with(&format!("/{}", candidate)))
+                        .map(|p| p.to_string())
+                })
+            }
+        }
+        Language::TypeScript | Language::JavaScript => {
+            if !import.starts_with('.') {
+                return None;
+            }
+            let stem = normalize(&join(dir, import))?;
+            [
+                "",
+                ".ts",
+                ".tsx",
+                ".js",
+                ".jsx",
+                "/index.ts",
+                "/index.tsx",
+                "/index.js",
+            ]
+            .iter()
+            .map(|suffix| format!("{}{}", stem, suffix))
+            .find(|c| paths.contains(c.as_str()))
+        }
+        Language::Rust => {
+            let path = import
+                .split('{')
+                .next()
+                .unwrap_or(import)
+                .trim_end_matches("::");
+            let mut segments: Vec<&str> = path.split("::").collect();
+            let base = match segments.first().copied() {
+                Some("crate") => {
+                    segments.remove(0);
+                    match from.rfind("src/") {
+                        Some(index) => from[..index + 4].trim_end_matches('/').to_string(),
+                        None => return None,
+                    }
+                }
+                Some("self") | Some("super") => {
+                    let module_dir = match from.rsplit('/').next() {
+                        Some("mod.rs" | "lib.rs" | "main.rs") => dir.to_string(),
+                        _ => from.trim_end_matches(".rs").to_string(),
+                    };
+                    let ups = segments.iter().take_while(|s| **s == "super").count();
+                    segments.drain(..ups.max(1));
+                    ancestor(&module_dir, ups)?
+                }
+                _ => return None,
+            };
+            // Trailing segments may name items rather than modules.
+            loop {
+                let stem = join(&base, &segments.join("/"));
+                let found = [format!("{}.rs", stem), format!("{}/mod.rs", stem)]
+                    .into_iter()
+                    .find(|c| paths.contains(c.as_str()));
+                if found.is_some() || segments.pop().is_none() {
+                    return found;
+                }
+            }
+        }
+    }
+}
+
+fn join(base: &str, path: &str) -> String {
+    match (base.is_empty(), path.is_empty()) {
+        (true, _) => path.to_string(),
+        (_, true) => base.to_string(),
+        _ => format!("{}/{}", base, path),
+    }
+}
+
+/// `dir` with `levels` trailing segments removed.
+fn ancestor(dir: &str, levels: usize) -> Option<String> {
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    if levels > segments.len() {
+        return None;
+    }
+    segments.truncate(segments.len() - levels);
+    Some(segments.join("/"))
+}
+
+/// Resolves `.` and `..` segments; `None` when the path leaves the root.
+fn normalize(path: &str) -> Option<String> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Applies `changes` to the indexed graph of `repository`. Each changed
+/// file's subgraph is replaced: definitions that are still there keep their
+/// ids, and with them edges from other files, while the rest are removed.
+/// Removed files are dropped, the commit and time are recorded on the
+/// repository node, and the calls and imports this can affect are re-linked.
+/// A store failure stops indexing part way; running it again completes it.
+pub fn index_repository(
+    graph: &mut KnowledgeGraph,
+    repository: &str,
+    changes: &Changes,
+) -> Result<IndexReport, StoreError> {
+    let mut touched = Touched {
+        paths: changes.files.iter().map(|file| file.path.clone()).collect(),
+        files_moved: !changes.removed.is_empty(),
+        ..Default::default()
+    };
+    let mut removed_nodes = 0;
+    for path in &changes.removed {
+        for id in file_subgraph(graph, file_key(repository, path).node_id()) {
+            if let Some(node) = graph.remove_node(&id)? {
+                touched.names.insert(node.name);
+                removed_nodes += 1;
+            }
+        }
+    }
+
+    let mut request = BulkUpsertRequest::default();
+    let status = serde_json::json!({
+        "commit": changes.commit,
+        "indexed_at": chrono::Utc::now(),
+        "last_error": null,
+        "last_error_at": null,
+    });
+    request
+        .nodes
+        .push(registry::repository_node(graph, repository, status));
+    for file in &changes.files {
+        let file_request = file_request(repository, file);
+        let node_ids: HashSet<Uuid> = file_request.nodes.iter().map(|n| n.key.node_id()).collect();
+        let edge_ids: HashSet<Uuid> = file_request
+            .edges
+            .iter()
+            .map(|e| natural_edge_id(&e.source.node_id(), &e.target.node_id(), &e.edge_type))
+            .collect();
+
+        let file_id = file_key(repository, &file.path).node_id();
+        touched.files_moved |= graph.get_node(&file_id).is_none();
+        let names = file.parsed.definitions.iter().map(|d| d.name.clone());
+        touched.names.extend(names);
+        for id in file_subgraph(graph, file_id) {
+            if let Some(node) = graph.get_node(&id) {
+                touched.names.insert(node.name.clone());
+            }
+            if !node_ids.contains(&id) {
+                removed_nodes += usize::from(graph.remove_node(&id)?.is_some());
+                continue;
+            }
+            let outdated: Vec<Uuid> = graph
+                .get_edges_for_node(&id)
+                .into_iter()
+                .filter(|e| e.source_id == id && !edge_ids.contains(&e.id) && !is_link(graph, e))
+                .map(|e| e.id)
+                .collect();
+            for edge_id in outdated {
+                graph.remove_edge(&edge_id)?;
+            }
+        }
+        request.nodes.extend(file_request.nodes);
+        request.edges.extend(file_request.edges);
+    }
+
+    let upserted = graph.bulk_upsert(request)?;
+    let (linked, unlinked) = link(graph, repository, &touched)?;
+    Ok(IndexReport {
+        repository: repository.to_string(),
+        commit: changes.commit.clone(),
+        previous_commit: changes.previous_commit.clone(),
+        incremental: changes.incremental,
+        files: changes.files.len(),
+        removed_files: changes.removed.len(),
+        nodes: upserted.nodes.len(),
+        edges: upserted.edges.len(),
+        created: upserted.created,
+        updated: upserted.updated,
+        failed: upserted.failed,
+        removed_nodes,
+        linked,
+        unlinked,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (IndexerConfig, PathBuf) {
+        let config = IndexerConfig {
+            repos_dir: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/repos"),
+        };
+        let root = config.repository_dir("acme-shop").unwrap();
+        (config, root)
+    }
+
+    fn key(path: &str, qualified_name: &str, node_type: NodeType) -> NodeKey {
+        NodeKey {
+            repository: "acme-shop".to_string(),
+            path: Some(path.to_string()),
+            qualified_name: qualified_name.to_string(),
+            node_type,
+        }
+    }
+
+    #[test]
+    fn test_scan_skips_vendored_dirs() {
+        let (config, root) = fixture();
+        assert!(config.repository_dir("../repos").is_none());
+        let paths: Vec<String> = scan_repository(&root).into_iter().map(|f| f.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "billing/__init__.py",
+                "billing/audit.py",
+                "billing/payments.py",
+                "engine/src/lib.rs",
+                "engine/src/store.rs",
+                "web/src/api.ts",
+                "web/src/checkout.ts",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_fixture_links_imports_and_calls() {
+        let (_, root) = fixture();
+        let changes = Changes {
+            files: scan_repository(&root),
+            ..Default::default()
+        };
+        let mut graph = KnowledgeGraph::new();
+        let report = index_repository(&mut graph, "acme-shop", &changes).unwrap();
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.created, report.nodes + report.edges);
+        assert!(report.linked > 0);
+
+        let retry = key(
+            "billing/payments.py",
+            "PaymentService.retry",
+            NodeType::Function,
+        );
+        let record = key("billing/audit.py", "record", NodeType::Function);
+        let node = graph.get_node(&retry.node_id()).unwrap();
+        assert_eq!(node.metadata["start_line"], 8);
+        assert_eq!(node.metadata["end_line"], 10);
+        let has_edge = |source: &NodeKey, target: &NodeKey, edge_type: EdgeType| {
+            graph
+                .get_edges_for_node(&source.node_id())
+                .iter()
+                .any(|e| e.target_id == target.node_id() && e.edge_type == edge_type)
+        };
+        assert!(has_edge(&retry, &record, EdgeType::Calls));
+
+        let payments = key("billing/payments.py", "billing/payments.py", NodeType::File);
+        let audit = key("billing/audit.py", "billing/audit.py", NodeType::File);
+        assert!(has_edge(&payments, &audit, EdgeType::Imports));
+
+        let checkout = key("web/src/checkout.ts", "web/src/checkout.ts", NodeType::File);
+        let api = key("web/src/api.ts", "web/src/api.ts", NodeType::File);
+        assert!(has_edge(&checkout, &api, EdgeType::Imports));
+        let submit = key("web/src/checkout.ts", "submit", NodeType::Function);
+        let post = key("web/src/api.ts", "ApiClient.post", NodeType::Function);
+        assert!(has_edge(&submit, &post, EdgeType::Calls));
+
+        let lib = key("engine/src/lib.rs", "engine/src/lib.rs", NodeType::File);
+        let store = key("engine/src/store.rs", "engine/src/store.rs", NodeType::File);
+        assert!(has_edge(&lib, &store, EdgeType::Imports));
+        let save = key("engine/src/store.rs", "Store::save", NodeType::Function);
+        let class = key("engine/src/store.rs", "Store", NodeType::Class);
+        assert!(has_edge(&class, &save, EdgeType::Contains));
+
+        let again = index_repository(&mut graph, "acme-shop", &changes).unwrap();
+        assert_eq!(again.created, 0);
+        assert_eq!(
+            (again.linked, again.unlinked, again.removed_nodes),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_name_status() {
+        let diff = parse_name_status("M\0a.py\0A\0web/b.ts\0D\0old.rs\0");
+        assert_eq!(
+            diff,
+            GitDiff {
+                changed: vec!["a.py".to_string(), "web/b.ts".to_string()],
+                deleted: vec!["old.rs".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reindex_replaces_changed_files_and_relinks() {
+        let root = std::env::temp_dir().join(format!("kg-indexer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let write = |path: &str, source: &str| std::fs::write(root.join(path), source).unwrap();
+        let commit = || {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(["-c", "user.name=kg", "-c", "user.email=kg@example.com"])
+                .args(["commit", "-qam", "update"])
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+
+        git(&["init", "-q"]);
+        write(
+            "app.py",
+            "from lib import helper\n\ndef run():\n    return helper()\n",
+        );
+        write("lib.py", "def helper():\n    return 1\n");
+        write("util.py", "def other():\n    return 2\n");
+        write("old.py", "def legacy():\n    return 3\n");
+        git(&["add", "."]);
+        commit();
+
+        let mut graph = KnowledgeGraph::new();
+        let changes = detect_changes(root.clone(), indexed_state(&graph, "demo"), false)
+            .await
+            .unwrap();
+        assert!(!changes.incremental);
+        assert_eq!(changes.files.len(), 4);
+        let first = index_repository(&mut graph, "demo", &changes).unwrap();
+        assert_eq!(first.failed, 0);
+
+        let key = |path: &str, qualified_name: &str, node_type| NodeKey {
+            repository: "demo".to_string(),
+            path: Some(path.to_string()),
+            qualified_name: qualified_name.to_string(),
+            node_type,
+        };
+        let run = key("app.py", "run", NodeType::Function).node_id();
+        let calls = |graph: &KnowledgeGraph| -> Vec<Uuid> {
+            graph
+                .get_edges_for_node(&run)
+                .iter()
+                .filter(|e| e.source_id == run && e.edge_type == EdgeType::Calls)
+                .map(|e| e.target_id)
+                .collect()
+        };
+        let lib_helper = key("lib.py", "helper", NodeType::Function).node_id();
+        assert_eq!(calls(&graph), vec![lib_helper]);
+        let state = indexed_state(&graph, "demo");
+        assert_eq!(state.commit, changes.commit);
+        assert_eq!(state.hashes.len(), 4);
+
+        // Move `helper` to util.py and delete old.py; app.py is untouched.
+        write("lib.py", "def unused():\n    return 1\n");
+        write(
+            "util.py",
+            "def other():\n    return 2\n\ndef helper():\n    return 3\n",
+        );
+        git(&["rm", "-q", "old.py"]);
+        commit();
+
+        let changes = detect_changes(root.clone(), state, false).await.unwrap();
+        assert!(changes.incremental);
+        let paths: Vec<&str> = changes.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["lib.py", "util.py"]);
+        assert_eq!(changes.removed, vec!["old.py"]);
+
+        let second = index_repository(&mut graph, "demo", &changes).unwrap();
+        assert_eq!(second.removed_nodes, 3);
+        assert!(graph.get_node(&lib_helper).is_none());
+        assert!(graph
+            .get_node(&key("old.py", "old.py", NodeType::File).node_id())
+            .is_none());
+        let util_helper = key("util.py", "helper", NodeType::Function).node_id();
+        assert_eq!(calls(&graph), vec![util_helper]);
+        assert_eq!(second.linked, 1);
+
+        let state = indexed_state(&graph, "demo");
+        let unchanged = detect_changes(root.clone(), state, false).await.unwrap();
+        assert!(unchanged.files.is_empty() && unchanged.removed.is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_relative_imports() {
+        let paths: HashSet<&str> = ["a/b/c.py", "a/d.py", "src/x/mod.rs", "src/x/y.rs"].into();
+        let python = |from, import| resolve_import(Language::Python, from, import, &paths);
+        assert_eq!(python("a/b/e.py", ".c"), Some("a/b/c.py".to_string()));
+        assert_eq!(python("a/b/e.py", "..d"), Some("a/d.py".to_string()));
+        assert_eq!(python("a/b/e.py", "b.c"), Some("a/b/c.py".to_string()));
+        assert_eq!(python("a/b/e.py", "os"), None);
+
+        let rust = |from, import| resolve_import(Language::Rust, from, import, &paths);
+        assert_eq!(
+            rust("src/x/mod.rs", "self::y::Item"),
+            Some("src/x/y.rs".to_string())
+        );
+        assert_eq!(
+            rust("src/x/y.rs", "super::Thing"),
+            Some("src/x/mod.rs".to_string())
+        );
+        assert_eq!(
+            rust("src/main.rs", "crate::x::{y, z}"),
+            Some("src/x/mod.rs".to_string())
+        );
+        assert_eq!(rust("src/main.rs", "serde::Serialize"), None);
+    }
+}
@@ -0,0 +1,147 @@
+use tree_sitter::Node;
+
+use crate::models::NodeType;
+
+use super::tree::{self, text, Grammar};
+use super::ParsedFile;
+
+struct Python;
+
+impl Grammar for Python {
+    const SEPARATOR: &'static str = ".";
+
+    fn definition(&self, node: Node, source: &str) -> Option<(String, NodeType)> {
+        let node_type = match node.kind() {
+            "function_definition" => NodeType::Function,
+            "class_definition" => NodeType::Class,
+            _ => return None,
+        };
+        let name = node.child_by_field_name("name")?;
+        Some((text(name, source).to_string(), node_type))
+    }
+
+    fn imports(&self, node: Node, source: &str, imports: &mut Vec<String>) {
+        let mut cursor = node.walk();
+        match node.kind() {
+            "import_statement" => {
+                for name in node.children_by_field_name("name", &mut cursor) {
+                    imports.push(module_name(name, source).to_string());
+                }
+            }
+            "import_from_statement" => {
+                let Some(module) = node.child_by_field_name("module_name") else {
+                    return;
+                };
+                let module = text(module, source);
+                if module.chars().all(|c| c == '.') {
+                    for name in node.children_by_field_name("name", &mut cursor) {
+                        imports.push(format!("{}{}", module, module_name(name, source)));
+                    }
+                } else {
+                    imports.push(module.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn callee<'tree>(&self, node: Node<'tree>) -> Option<Node<'tree>> {
+        if node.kind() != "call" {
+            return None;
+        }
+        let function = node.child_by_field_name("function")?;
+        match function.kind() {
+            "identifier" => Some(function),
+            "attribute" => function.child_by_field_name("attribute"),
+            _ => None,
+        }
+    }
+}
+
+/// The imported name without its alias.
+fn module_name<'source>(name: Node, source: &'source str) -> &'source str {
+    match name.child_by_field_name("name") {
+        Some(aliased) if name.kind() == "aliased_import" => text(aliased, source),
+        _ => text(name, source),
+    }
+}
+
+pub(super) fn parse(source: &str) -> ParsedFile {
+    tree::parse(&Python, tree_sitter_python::LANGUAGE.into(), source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_python() {
+        let source = r#"from .audit import record
+import os, json as j
+
+class PaymentService:
+    """Charges cards. def not_a_function():"""
+
+    def retry(
+        self,
+        amount,
+    ) -> bool:
+        record("retry")  # audit(
+        return self.charge(amount)
+
+    async def charge(self, amount):
+        def inner():
+            return os.getenv("X")
+        return inner()
+
+def main():
+    PaymentService().retry(1)
+"#;
+        let parsed = parse(source);
+        assert_eq!(parsed.imports, vec![".audit", "os", "json"]);
+
+        let names: Vec<(&str, usize, usize)> = parsed
+            .definitions
+            .iter()
+            .map(|d| (d.qualified_name.as_str(), d.start_line, d.end_line))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("PaymentService", 4, 17),
+                ("PaymentService.retry", 7, 12),
+                ("PaymentService.charge", 14, 17),
+                ("PaymentService.charge.inner", 15, 16),
+                ("main", 19, 20),
+            ]
+        );
+        assert_eq!(parsed.definitions[1].parent, Some(0));
+        assert_eq!(parsed.definitions[1].calls, vec!["record", "charge"]);
+        assert_eq!(parsed.definitions[2].calls, vec!["inner"]);
+        assert_eq!(parsed.definitions[3].calls, vec!["getenv"]);
+        assert_eq!(parsed.definitions[4].calls, vec!["PaymentService", "retry"]);
+    }
+
+    #[test]
+    fn test_parse_python_decorators_and_continuations() {
+        let source = r#"@retrying(
+    attempts=3,
+)
+def fetch(url,
+          timeout=default_timeout()):
+    return \
+        download(url)
+"#;
+        let parsed = parse(source);
+        let fetch = &parsed.definitions[0];
+        assert_eq!(
+            (
+                fetch.qualified_name.as_str(),
+                fetch.start_line,
+                fetch.end_line
+            ),
+            ("fetch", 4, 7)
+        );
+        assert_eq!(fetch.calls, vec!["default_timeout", "download"]);
+    }
+}
@@ -0,0 +1,172 @@
+use tree_sitter::Node;
+
+use crate::models::NodeType;
+
+use super::tree::{self, text, Grammar};
+use super::ParsedFile;
+
+struct Rust;
+
+impl Grammar for Rust {
+    const SEPARATOR: &'static str = "::";
+
+    fn definition(&self, node: Node, source: &str) -> Option<(String, NodeType)> {
+        let node_type = match node.kind() {
+            "function_item" | "function_signature_item" => NodeType::Function,
+            "struct_item" | "enum_item" | "union_item" | "trait_item" => NodeType::Class,
+            "mod_item" => NodeType::Module,
+            "const_item" | "static_item" => NodeType::Constant,
+            _ => return None,
+        };
+        let name = node.child_by_field_name("name")?;
+        Some((text(name, source).to_string(), node_type))
+    }
+
+    /// Methods in `impl Type` are qualified by `Type`.
+    fn scope(&self, node: Node, source: &str) -> Option<String> {
+        if node.kind() != "impl_item" {
+            return None;
+        }
+        let mut implemented = node.child_by_field_name("type")?;
+        loop {
+            let inner = match implemented.kind() {
+                "generic_type" | "reference_type" => implemented.child_by_field_name("type"),
+                "scoped_type_identifier" => implemented.child_by_field_name("name"),
+                _ => return Some(text(implemented, source).to_string()),
+            };
+            implemented = inner?;
+        }
+    }
+
+    fn imports(&self, node: Node, source: &str, imports: &mut Vec<String>) {
+        if node.kind() != "use_declaration" {
+            return;
+        }
+        if let Some(argument) = node.child_by_field_name("argument") {
+            imports.push(text(argument, source).split_whitespace().collect());
+        }
+    }
+
+    fn callee<'tree>(&self, node: Node<'tree>) -> Option<Node<'tree>> {
+        if node.kind() != "call_expression" {
+            return None;
+        }
+        let mut called = node.child_by_field_name("function")?;
+        loop {
+            match called.kind() {
+                "identifier" => return Some(called),
+                "field_expression" => return called.child_by_field_name("field"),
+                "scoped_identifier" => return called.child_by_field_name("name"),
+                "generic_function" => called = called.child_by_field_name("function")?,
+                _ => return None,
+            }
+        }
+    }
+}
+
+pub(super) fn parse(source: &str) -> ParsedFile {
+    tree::parse(&Rust, tree_sitter_rust::LANGUAGE.into(), source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rust() {
+        let source = r#"use crate::services::audit::record;
+use std::{
+    collections::HashMap,
+    fmt,
+};
+
+pub const MAX_RETRIES: u32 = 3;
+
+pub struct PaymentService<'a> {
+    gateway: &'a str,
+}
+
+impl<'a> fmt::Display for PaymentService<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fn fake() {{")
+    }
+}
+
+impl PaymentService<'_> {
+    pub async fn retry(&self, amount: u32) -> bool {
+        record(amount);
+        self.charge(amount)
+    }
+
+    fn charge(&self, _amount: u32) -> bool {
+        let c = '}';
+        c == '}'
+    }
+}
+
+pub trait Gateway {
+    fn send(&self);
+}
+"#;
+        let parsed = parse(source);
+        assert_eq!(
+            parsed.imports,
+            vec![
+                "crate::services::audit::record",
+                "std::{collections::HashMap,fmt,}"
+            ]
+        );
+
+        let names: Vec<(&str, usize, usize)> = parsed
+            .definitions
+            .iter()
+            .map(|d| (d.qualified_name.as_str(), d.start_line, d.end_line))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("MAX_RETRIES", 7, 7),
+                ("PaymentService", 9, 11),
+                ("PaymentService::fmt", 14, 16),
+                ("PaymentService::retry", 20, 23),
+                ("PaymentService::charge", 25, 28),
+                ("Gateway", 31, 33),
+                ("Gateway::send", 32, 32),
+            ]
+        );
+        assert_eq!(parsed.definitions[3].parent, Some(1));
+        assert_eq!(parsed.definitions[3].calls, vec!["record", "charge"]);
+        assert!(parsed.definitions[4].calls.is_empty());
+        assert_eq!(parsed.definitions[6].parent, Some(5));
+    }
+
+    #[test]
+    fn test_parse_rust_generic_impl() {
+        let source = r#"pub struct Cache<K, V> {
+    entries: HashMap<K, Vec<V>>,
+}
+
+impl<K: Hash + Eq, V: Into<Vec<u8>>> crate::store::Store
+    for Cache<K, V>
+where
+    V: Clone,
+{
+    fn put(&mut self, key: K, value: V) {
+        self.entries.entry(key).or_default().push(value);
+        Vec::<u8>::with_capacity(1);
+    }
+}
+"#;
+        let parsed = parse(source);
+        let put = &parsed.definitions[1];
+        assert_eq!(
+            (put.qualified_name.as_str(), put.start_line, put.end_line),
+            ("Cache::put", 10, 13)
+        );
+        assert_eq!(put.parent, Some(0));
+        assert_eq!(
+            put.calls,
+            vec!["entry", "or_default", "push", "with_capacity"]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Syntax-tree walk shared by the language parsers.
+
+use std::collections::HashSet;
+
+use tree_sitter::{Language, Node, Parser};
+
+use crate::models::NodeType;
+
+use super::{Definition, ParsedFile};
+
+/// Called names that are never project definitions.
+const NOT_CALLS: &[&str] = &["super", "self", "Some", "Ok", "Err", "print"];
+
+/// The parts of a language the walk needs to know about.
+pub(super) trait Grammar {
+    /// Joins a definition's name to its enclosing one.
+    const SEPARATOR: &'static str;
+
+    /// Name and type of the definition `node` declares, if any.
+    fn definition(&self, node: Node, source: &str) -> Option<(String, NodeType)>;
+
+    /// Name that qualifies the items inside `node` when it is a scope but
+    /// not a definition, like a Rust `impl` block.
+    fn scope(&self, _node: Node, _source: &str) -> Option<String> {
+        None
+    }
+
+    /// Module specifiers imported by `node`.
+    fn imports(&self, node: Node, source: &str, imports: &mut Vec<String>);
+
+    /// The name node a call `node` calls, e.g. `charge` in `self.charge()`.
+    fn callee<'tree>(&self, node: Node<'tree>) -> Option<Node<'tree>>;
+}
+
+enum Scope {
+    Definition(usize),
+    Named(String),
+}
+
+pub(super) fn parse<G: Grammar>(grammar: &G, language: Language, source: &str) -> ParsedFile {
+    let mut parser = Parser::new();
+    let mut parsed = ParsedFile::default();
+    if parser.set_language(&language).is_err() {
+        return parsed;
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return parsed;
+    };
+
+    // (opening node id, scope) of the enclosing scopes.
+    let mut stack: Vec<(usize, Scope)> = Vec::new();
+    // (byte offset, name) of the calls made by each definition.
+    let mut calls: Vec<Vec<(usize, String)>> = Vec::new();
+    let mut cursor = tree.walk();
+    'walk: loop {
+        let node = cursor.node();
+        if let Some(name) = grammar.scope(node, source) {
+            stack.push((node.id(), Scope::Named(name)));
+        } else if let Some((name, node_type)) = grammar.definition(node, source) {
+            if node_type != NodeType::Constant || owner(&stack, &parsed.definitions).is_none() {
+                let (prefix, parent) = enclosing::<G>(&stack, &parsed.definitions);
+                let qualified_name = match prefix {
+                    Some(prefix) => format!("{}{}{}", prefix, G::SEPARATOR, name),
+                    None => name.clone(),
+                };
+                parsed.definitions.push(Definition {
+                    name,
+                    qualified_name,
+                    node_type,
+                    start_line: node.start_position().row + 1,
+                    end_line: end_line(node),
+                    parent,
+                    calls: Vec::new(),
+                });
+                calls.push(Vec::new());
+                stack.push((node.id(), Scope::Definition(parsed.definitions.len() - 1)));
+            }
+        } else if let Some(callee) = grammar.callee(node) {
+            let name = text(callee, source);
+            if let (Some(owner), false) = (
+                owner(&stack, &parsed.definitions),
+                NOT_CALLS.contains(&name),
+            ) {
+                calls[owner].push((callee.start_byte(), name.to_string()));
+            }
+        }
+        grammar.imports(node, source, &mut parsed.imports);
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            let id = cursor.node().id();
+            if stack.last().is_some_and(|(opened, _)| *opened == id) {
+                stack.pop();
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
+    }
+
+    for (definition, mut calls) in parsed.definitions.iter_mut().zip(calls) {
+        calls.sort_by_key(|(offset, _)| *offset);
+        let mut seen = HashSet::new();
+        definition.calls = calls
+            .into_iter()
+            .map(|(_, name)| name)
+            .filter(|name| seen.insert(name.clone()))
+            .collect();
+    }
+    parsed
+}
+
+pub(super) fn text<'source>(node: Node, source: &'source str) -> &'source str {
+    &source[node.byte_range()]
+}
+
+/// 1-based last line of `node`, not counting a trailing newline.
+fn end_line(node: Node) -> usize {
+    let end = node.end_position();
+    if end.column == 0 && end.row > node.start_position().row {
+        end.row
+    } else {
+        end.row + 1
+    }
+}
+
+/// The innermost enclosing function, which owns the calls made at this point.
+fn owner(stack: &[(usize, Scope)], definitions: &[Definition]) -> Option<usize> {
+    stack.iter().rev().find_map(|(_, scope)| match scope {
+        Scope::Definition(i) if definitions[*i].node_type == NodeType::Function => Some(*i),
+        _ => None,
+    })
+}
+
+/// Qualified-name prefix and parent definition for an item inside `stack`.
+fn enclosing<G: Grammar>(
+    stack: &[(usize, Scope)],
+    definitions: &[Definition],
+) -> (Option<String>, Option<usize>) {
+    match stack.last() {
+        None => (None, None),
+        Some((_, Scope::Definition(parent))) => (
+            Some(definitions[*parent].qualified_name.clone()),
+            Some(*parent),
+        ),
+        Some((_, Scope::Named(name))) => {
+            let (outer, _) = enclosing::<G>(&stack[..stack.len() - 1], definitions);
+            let qualified = match outer {
+                Some(outer) => format!("{}{}{}", outer, G::SEPARATOR, name),
+                None => name.clone(),
+            };
+            let parent = definitions
+                .iter()
+                .position(|d| d.node_type == NodeType::Class && d.qualified_name == qualified);
+            (Some(qualified), parent)
+        }
+    }
+}
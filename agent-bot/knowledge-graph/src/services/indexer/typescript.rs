@@ -0,0 +1,172 @@
+use tree_sitter::Node;
+
+use crate::models::NodeType;
+
+use super::tree::{self, text, Grammar};
+use super::ParsedFile;
+
+struct TypeScript;
+
+impl Grammar for TypeScript {
+    const SEPARATOR: &'static str = ".";
+
+    fn definition(&self, node: Node, source: &str) -> Option<(String, NodeType)> {
+        let node_type = match node.kind() {
+            "class_declaration"
+            | "abstract_class_declaration"
+            | "interface_declaration"
+            | "enum_declaration" => NodeType::Class,
+            "function_declaration"
+            | "generator_function_declaration"
+            | "method_definition"
+            | "method_signature"
+            | "abstract_method_signature" => NodeType::Function,
+            "variable_declarator" | "public_field_definition" => {
+                let value = node.child_by_field_name("value")?;
+                if !matches!(
+                    value.kind(),
+                    "arrow_function" | "function_expression" | "generator_function"
+                ) {
+                    return None;
+                }
+                NodeType::Function
+            }
+            _ => return None,
+        };
+        let name = node.child_by_field_name("name")?;
+        Some((text(name, source).to_string(), node_type))
+    }
+
+    fn imports(&self, node: Node, source: &str, imports: &mut Vec<String>) {
+        let specifier = match node.kind() {
+            "import_statement" | "export_statement" => node.child_by_field_name("source"),
+            "call_expression" => node
+                .child_by_field_name("function")
+                .filter(|function| text(*function, source) == "require")
+                .and_then(|_| node.child_by_field_name("arguments"))
+                .and_then(|arguments| arguments.named_child(0))
+                .filter(|argument| argument.kind() == "string"),
+            _ => None,
+        };
+        if let Some(specifier) = specifier {
+            imports.push(
+                text(specifier, source)
+                    .trim_matches(['"', '\''])
+                    .to_string(),
+            );
+        }
+    }
+
+    fn callee<'tree>(&self, node: Node<'tree>) -> Option<Node<'tree>> {
+        let called = match node.kind() {
+            "call_expression" => node.child_by_field_name("function")?,
+            "new_expression" => node.child_by_field_name("constructor")?,
+            _ => return None,
+        };
+        match called.kind() {
+            "identifier" => Some(called),
+            "member_expression" => called.child_by_field_name("property"),
+            _ => None,
+        }
+    }
+}
+
+/// Parses TypeScript or JavaScript; `tsx` selects the grammar that accepts
+/// JSX, which JavaScript files may also contain.
+pub(super) fn parse(source: &str, tsx: bool) -> ParsedFile {
+    let language = if tsx {
+        tree_sitter_typescript::LANGUAGE_TSX
+    } else {
+        tree_sitter_typescript::LANGUAGE_TYPESCRIPT
+    };
+    tree::parse(&TypeScript, language.into(), source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_typescript() {
+        let source = r#"import { record } from "./audit";
+import type {
+  Card,
+} from '../types';
+const fs = require("fs");
+// import { hidden } from "./nope";
+
+export class PaymentService {
+  private retries = 0;
+
+  constructor(private readonly gateway: Gateway) {}
+
+  async retry(amount: number): Promise<boolean> {
+    if (amount > 0) {
+      record(`retry(${amount})`);
+    }
+    return this.charge(amount);
+  }
+
+  handle = async (event: Event) => {
+    this.retry(1);
+  };
+}
+
+export const charge = async (amount: number) => {
+  return fs.existsSync("x");
+};
+
+export function main() {
+  new PaymentService(gateway).retry(1);
+}
+"#;
+        let parsed = parse(source, false);
+        assert_eq!(parsed.imports, vec!["./audit", "../types", "fs"]);
+
+        let names: Vec<(&str, usize, usize)> = parsed
+            .definitions
+            .iter()
+            .map(|d| (d.qualified_name.as_str(), d.start_line, d.end_line))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("PaymentService", 8, 23),
+                ("PaymentService.constructor", 11, 11),
+                ("PaymentService.retry", 13, 18),
+                ("PaymentService.handle", 20, 22),
+                ("charge", 25, 27),
+                ("main", 29, 31),
+            ]
+        );
+        assert_eq!(parsed.definitions[2].calls, vec!["record", "charge"]);
+        assert_eq!(parsed.definitions[3].calls, vec!["retry"]);
+        assert_eq!(parsed.definitions[5].calls, vec!["PaymentService", "retry"]);
+    }
+
+    #[test]
+    fn test_parse_tsx() {
+        let source = r#"import { Button } from "./button";
+
+export function Checkout({ total }: Props) {
+  const pay = () => charge(total);
+  return (
+    <Button onClick={pay}>
+      Pay {format(total)}
+    </Button>
+  );
+}
+"#;
+        let parsed = parse(source, true);
+        assert_eq!(parsed.imports, vec!["./button"]);
+
+        let names: Vec<(&str, usize, usize)> = parsed
+            .definitions
+            .iter()
+            .map(|d| (d.qualified_name.as_str(), d.start_line, d.end_line))
+            .collect();
+        assert_eq!(names, vec![("Checkout", 3, 10), ("Checkout.pay", 4, 4)]);
+        assert_eq!(parsed.definitions[0].calls, vec!["format"]);
+        assert_eq!(parsed.definitions[1].calls, vec!["charge"]);
+    }
+}
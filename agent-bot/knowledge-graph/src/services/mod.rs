@@ -1 +1,12 @@
+pub mod auth;
+pub mod gkg;
 pub mod graph;
+pub mod indexer;
+pub mod registry;
+pub mod render;
+pub mod schema;
+pub mod search;
+pub mod store;
+pub mod tenants;
+pub mod transfer;
+pub mod vector;
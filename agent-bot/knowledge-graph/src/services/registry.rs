@@ -0,0 +1,331 @@
+//! Registry of repositories in `config/repos.json`.
+//!
+//! The file is shared with `scripts/sync-repos.sh`, which clones and fetches
+//! every entry. Each registered repository has a `Repository` node whose
+//! metadata records when it was last indexed, at which commit, and the last
+//! error, so status is read from the graph rather than kept separately.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    BulkUpsertRequest, NodeKey, NodeType, RepositoryEntry, RepositoryStatus, UpsertNode,
+};
+use crate::services::graph::KnowledgeGraph;
+use crate::services::indexer::IndexerConfig;
+use crate::services::store::StoreError;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    repositories: Vec<RepositoryEntry>,
+}
+
+#[derive(Debug)]
+pub struct Registry {
+    path: PathBuf,
+    repositories: Vec<RepositoryEntry>,
+}
+
+impl Registry {
+    /// Reads `REPOS_CONFIG`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let path =
+            std::env::var("REPOS_CONFIG").unwrap_or_else(|_| "/app/config/repos.json".to_string());
+        Self::load(path)
+    }
+
+    /// Loads the registry at `path`; a missing file is an empty registry.
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let file: RegistryFile = config::Config::builder()
+            .add_source(
+                config::File::from(path.as_path())
+                    .format(config::FileFormat::Json)
+                    .required(false),
+            )
+            .build()?
+            .try_deserialize()?;
+        Ok(Self {
+            path,
+            repositories: file.repositories,
+        })
+    }
+
+    pub fn repositories(&self) -> &[RepositoryEntry] {
+        &self.repositories
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RepositoryEntry> {
+        self.repositories.iter().find(|entry| entry.name == name)
+    }
+
+    /// Org that owns repository `name`. Unregistered repositories and
+    /// entries without an org belong to `default_org`.
+    pub fn owner<'a>(&'a self, name: &str, default_org: &'a str) -> &'a str {
+        self.get(name)
+            .and_then(|entry| entry.org_id.as_deref())
+            .unwrap_or(default_org)
+    }
+
+    /// Adds `entry` and saves the file. The caller checks the name is free.
+    pub fn register(&mut self, entry: RepositoryEntry) -> anyhow::Result<()> {
+        self.repositories.push(entry);
+        let saved = self.save();
+        if saved.is_err() {
+            self.repositories.pop();
+        }
+        saved
+    }
+
+    /// Removes `name` and saves the file; `false` if it was not registered.
+    pub fn remove(&mut self, name: &str) -> anyhow::Result<bool> {
+        let Some(index) = self
+            .repositories
+            .iter()
+            .position(|entry| entry.name == name)
+        else {
+            return Ok(false);
+        };
+        let entry = self.repositories.remove(index);
+        match self.save() {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                self.repositories.insert(index, entry);
+                Err(err)
+            }
+        }
+    }
+
+    /// Writes through a temporary file, so the file is never left truncated.
+    fn save(&self) -> anyhow::Result<()> {
+        let file = RegistryFile {
+            repositories: self.repositories.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)?;
+        let temporary = self.path.with_extension("json.tmp");
+        std::fs::write(&temporary, json + "\n")?;
+        std::fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Whether `name` can be used as a checkout and output directory name.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.')
+}
+
+pub fn repository_key(name: &str) -> NodeKey {
+    NodeKey {
+        repository: name.to_string(),
+        path: None,
+        qualified_name: name.to_string(),
+        node_type: NodeType::Repository,
+    }
+}
+
+/// The `Repository` node of `name` for an upsert, keeping its current
+/// metadata with `patch` applied on top. `null` values in `patch` remove
+/// keys.
+pub fn repository_node(graph: &KnowledgeGraph, name: &str, patch: serde_json::Value) -> UpsertNode {
+    let key = repository_key(name);
+    let mut metadata = graph
+        .get_node(&key.node_id())
+        .and_then(|node| node.metadata.as_object().cloned())
+        .unwrap_or_default();
+    if let serde_json::Value::Object(patch) = patch {
+        for (field, value) in patch {
+            if value.is_null() {
+                metadata.remove(&field);
+            } else {
+                metadata.insert(field, value);
+            }
+        }
+    }
+    UpsertNode {
+        key,
+        name: Some(name.to_string()),
+        language: None,
+        description: None,
+        metadata: Some(serde_json::Value::Object(metadata)),
+        embedding: None,
+    }
+}
+
+/// Creates the `Repository` node of `name` if it is missing.
+pub fn ensure_node(graph: &mut KnowledgeGraph, name: &str) -> Result<(), StoreError> {
+    if graph.get_node(&repository_key(name).node_id()).is_none() {
+        graph.bulk_upsert(BulkUpsertRequest {
+            nodes: vec![repository_node(graph, name, serde_json::json!({}))],
+            edges: Vec::new(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Records a failed indexing run on the `Repository` node of `name`, if
+/// there is one.
+pub fn record_error(graph: &mut KnowledgeGraph, name: &str, error: &str) -> Result<(), StoreError> {
+    if graph.get_node(&repository_key(name).node_id()).is_none() {
+        return Ok(());
+    }
+    let patch = serde_json::json!({ "last_error": error, "last_error_at": Utc::now() });
+    graph.bulk_upsert(BulkUpsertRequest {
+        nodes: vec![repository_node(graph, name, patch)],
+        edges: Vec::new(),
+    })?;
+    Ok(())
+}
+
+/// Removes every node of repository `name`; returns how many there were.
+pub fn remove_nodes(graph: &mut KnowledgeGraph, name: &str) -> Result<usize, StoreError> {
+    let ids: Vec<_> = graph
+        .list_nodes()
+        .into_iter()
+        .filter(|node| node.metadata.get("repository").and_then(|r| r.as_str()) == Some(name))
+        .map(|node| node.id)
+        .collect();
+    let mut removed = 0;
+    for id in &ids {
+        removed += usize::from(graph.remove_node(id)?.is_some());
+    }
+    Ok(removed)
+}
+
+/// Status of each of `entries`, counting nodes by their `repository`
+/// metadata and edges by their source node.
+pub fn statuses(
+    graph: &KnowledgeGraph,
+    indexer: &IndexerConfig,
+    entries: &[RepositoryEntry],
+) -> Vec<RepositoryStatus> {
+    let names: HashSet<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    let mut nodes: HashMap<&str, usize> = HashMap::new();
+    let mut edges: HashMap<&str, usize> = HashMap::new();
+    let mut owners = HashMap::new();
+    for node in graph.list_nodes() {
+        let repository = node.metadata.get("repository").and_then(|r| r.as_str());
+        if let Some(repository) = repository.filter(|r| names.contains(r)) {
+            *nodes.entry(repository).or_default() += 1;
+            owners.insert(node.id, repository);
+        }
+    }
+    for edge in graph.list_edges() {
+        if let Some(repository) = owners.get(&edge.source_id) {
+            *edges.entry(repository).or_default() += 1;
+        }
+    }
+
+    entries
+        .iter()
+        .map(|entry| {
+            let node_id = repository_key(&entry.name).node_id();
+            let metadata = graph.get_node(&node_id).map(|node| &node.metadata);
+            let field = |name: &str| metadata.and_then(|m| m.get(name)).cloned();
+            let text = |name: &str| field(name).and_then(|v| v.as_str().map(str::to_string));
+            RepositoryStatus {
+                entry: entry.clone(),
+                node_id,
+                checked_out: indexer.repository_dir(&entry.name).is_some(),
+                last_indexed: field("indexed_at")
+                    .and_then(|v| serde_json::from_value::<DateTime<Utc>>(v).ok()),
+                commit: text("commit"),
+                nodes: nodes.get(entry.name.as_str()).copied().unwrap_or_default(),
+                edges: edges.get(entry.name.as_str()).copied().unwrap_or_default(),
+                last_error: text("last_error"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::services::indexer::{self, Changes};
+
+    fn entry(name: &str) -> RepositoryEntry {
+        RepositoryEntry {
+            name: name.to_string(),
+            url: Some(format!("https://github.com/acme/{}.git", name)),
+            branch: None,
+            org_id: None,
+        }
+    }
+
+    #[test]
+    fn test_register_and_remove_persist() {
+        let dir = std::env::temp_dir().join(format!("kg-registry-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("repos.json");
+
+        let mut registry = Registry::load(&path).unwrap();
+        assert!(registry.repositories().is_empty());
+        registry.register(entry("acme-shop")).unwrap();
+        registry.register(entry("acme-billing")).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["repositories"][0]["name"], "acme-shop");
+        assert_eq!(
+            written["repositories"][0]["url"],
+            "https://github.com/acme/acme-shop.git"
+        );
+
+        let mut reloaded = Registry::load(&path).unwrap();
+        assert_eq!(reloaded.repositories(), registry.repositories());
+        assert!(reloaded.remove("acme-shop").unwrap());
+        assert!(!reloaded.remove("acme-shop").unwrap());
+        let names: Vec<String> = Registry::load(&path)
+            .unwrap()
+            .repositories()
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect();
+        assert_eq!(names, vec!["acme-billing"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_status_reads_repository_node() {
+        let indexer_config = IndexerConfig {
+            repos_dir: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/repos"),
+        };
+        let entries = vec![entry("acme-shop"), entry("acme-missing")];
+        let mut graph = KnowledgeGraph::new();
+        for entry in &entries {
+            ensure_node(&mut graph, &entry.name).unwrap();
+        }
+        record_error(&mut graph, "acme-shop", "checkout was locked").unwrap();
+
+        let changes = Changes {
+            files: indexer::scan_repository(&indexer_config.repository_dir("acme-shop").unwrap()),
+            ..Default::default()
+        };
+        let report = indexer::index_repository(&mut graph, "acme-shop", &changes).unwrap();
+        record_error(&mut graph, "acme-missing", "Repository checkout not found").unwrap();
+
+        let statuses = statuses(&graph, &indexer_config, &entries);
+        let shop = &statuses[0];
+        assert!(shop.checked_out);
+        assert!(shop.last_indexed.is_some());
+        assert_eq!(shop.last_error, None);
+        assert_eq!(shop.nodes, report.nodes);
+        assert_eq!(shop.edges, report.edges + report.linked);
+        let root = graph.get_edges_for_node(&shop.node_id);
+        assert_eq!(root.len(), changes.files.len());
+
+        let missing = &statuses[1];
+        assert!(!missing.checked_out);
+        assert_eq!((missing.nodes, missing.edges), (1, 0));
+        assert_eq!(
+            missing.last_error.as_deref(),
+            Some("Repository checkout not found")
+        );
+    }
+}
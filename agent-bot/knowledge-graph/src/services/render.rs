@@ -0,0 +1,449 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use uuid::Uuid;
+
+use crate::models::{Edge, EdgeType, Node, NodeType, RenderFormat, RenderQuery, Subgraph};
+use crate::services::graph::KnowledgeGraph;
+use crate::services::schema::GraphSchema;
+
+/// How edges of one type are drawn, in every format.
+struct EdgeStyle {
+    color: &'static str,
+    line: Line,
+}
+
+enum Line {
+    Solid,
+    Dashed,
+    Dotted,
+    Bold,
+}
+
+/// Custom types are drawn like the built-in type they specialize.
+fn edge_style(schema: &GraphSchema, edge_type: &EdgeType) -> EdgeStyle {
+    let (color, line) = match schema.builtin_edge_type(edge_type).unwrap_or(edge_type) {
+        EdgeType::Contains => ("#7f8c8d", Line::Solid),
+        EdgeType::Imports => ("#2980b9", Line::Dashed),
+        EdgeType::Calls => ("#2c3e50", Line::Bold),
+        EdgeType::Inherits => ("#27ae60", Line::Solid),
+        EdgeType::Implements => ("#27ae60", Line::Dashed),
+        EdgeType::Uses => ("#8e44ad", Line::Dotted),
+        EdgeType::DependsOn => ("#e67e22", Line::Dashed),
+        EdgeType::DefinedIn => ("#95a5a6", Line::Dotted),
+        EdgeType::References => ("#7f8c8d", Line::Dashed),
+        EdgeType::Handles => ("#c0392b", Line::Solid),
+        EdgeType::Delegates => ("#d35400", Line::Bold),
+        EdgeType::Custom(_) => ("#34495e", Line::Solid),
+    };
+    EdgeStyle { color, line }
+}
+
+impl Line {
+    fn name(&self) -> &'static str {
+        match self {
+            Line::Solid => "solid",
+            Line::Dashed => "dashed",
+            Line::Dotted => "dotted",
+            Line::Bold => "bold",
+        }
+    }
+}
+
+/// The nodes and edges `query` selects. `None` when `root_id` is unknown.
+pub fn select(graph: &KnowledgeGraph, query: &RenderQuery) -> Option<Subgraph> {
+    let edge_types = query.edge_types.as_deref();
+    if query.root_id.is_none() && query.node_ids.is_none() {
+        let ids: Vec<Uuid> = graph.list_nodes().iter().map(|n| n.id).collect();
+        return Some(graph.subgraph(&ids, edge_types));
+    }
+
+    let mut ids = query.node_ids.clone().unwrap_or_default();
+    if let Some(root_id) = query.root_id {
+        graph.get_node(&root_id)?;
+        let direction = query.direction.clone().unwrap_or_default();
+        let depth = query.depth.unwrap_or(1);
+        ids.push(root_id);
+        ids.extend(
+            graph
+                .find_neighbors(&root_id, edge_types, &direction, depth)
+                .into_iter()
+                .map(|n| n.node.id),
+        );
+    }
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    Some(graph.subgraph(&ids, edge_types))
+}
+
+pub fn content_type(format: RenderFormat) -> &'static str {
+    match format {
+        RenderFormat::Graphml => "application/graphml+xml",
+        RenderFormat::Dot => "text/vnd.graphviz",
+        RenderFormat::Mermaid => "text/plain; charset=utf-8",
+    }
+}
+
+/// Renders `subgraph` as text. Nodes and edges are sorted by id so the
+/// same subgraph always renders identically.
+pub fn render(subgraph: &Subgraph, format: RenderFormat, schema: &GraphSchema) -> String {
+    let mut nodes: Vec<&Node> = subgraph.nodes.iter().collect();
+    let mut edges: Vec<&Edge> = subgraph.edges.iter().collect();
+    nodes.sort_by_key(|n| n.id);
+    edges.sort_by_key(|e| e.id);
+    match format {
+        RenderFormat::Graphml => graphml(&nodes, &edges, schema),
+        RenderFormat::Dot => dot(&nodes, &edges, schema),
+        RenderFormat::Mermaid => mermaid(&nodes, &edges, schema),
+    }
+}
+
+fn graphml(nodes: &[&Node], edges: &[&Edge], schema: &GraphSchema) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+    );
+    let keys = [
+        ("label", "node", "string"),
+        ("node_type", "node", "string"),
+        ("path", "node", "string"),
+        ("language", "node", "string"),
+        ("edge_type", "edge", "string"),
+        ("weight", "edge", "double"),
+        ("color", "edge", "string"),
+        ("style", "edge", "string"),
+    ];
+    for (id, target, kind) in keys {
+        let _ = writeln!(
+            out,
+            "  <key id=\"{id}\" for=\"{target}\" attr.name=\"{id}\" attr.type=\"{kind}\"/>"
+        );
+    }
+    out.push_str("  <graph id=\"knowledge_graph\" edgedefault=\"directed\">\n");
+
+    for node in nodes {
+        let _ = writeln!(out, "    <node id=\"{}\">", node.id);
+        let mut data = vec![
+            ("label", label(node, "\n")),
+            ("node_type", node.node_type.as_str().to_string()),
+        ];
+        data.extend(node.path.clone().map(|path| ("path", path)));
+        data.extend(node.language.clone().map(|language| ("language", language)));
+        for (key, value) in data {
+            let _ = writeln!(
+                out,
+                "      <data key=\"{}\">{}</data>",
+                key,
+                escape_xml(&value)
+            );
+        }
+        out.push_str("    </node>\n");
+    }
+
+    for edge in edges {
+        let style = edge_style(schema, &edge.edge_type);
+        let _ = writeln!(
+            out,
+            "    <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+            edge.id, edge.source_id, edge.target_id
+        );
+        let data = [
+            ("edge_type", edge.edge_type.as_str().to_string()),
+            ("weight", edge.weight.to_string()),
+            ("color", style.color.to_string()),
+            ("style", style.line.name().to_string()),
+        ];
+        for (key, value) in data {
+            let _ = writeln!(
+                out,
+                "      <data key=\"{}\">{}</data>",
+                key,
+                escape_xml(&value)
+            );
+        }
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn dot(nodes: &[&Node], edges: &[&Edge], schema: &GraphSchema) -> String {
+    let mut out = String::from(
+        "digraph knowledge_graph {\n  \
+         rankdir=LR;\n  \
+         node [fontname=\"Helvetica\", fontsize=10];\n  \
+         edge [fontname=\"Helvetica\", fontsize=9];\n",
+    );
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "  \"{}\" [label=\"{}\", shape={}];",
+            node.id,
+            escape_dot(&label(node, "\n")),
+            dot_shape(schema, &node.node_type)
+        );
+    }
+    for edge in edges {
+        let style = edge_style(schema, &edge.edge_type);
+        let _ = writeln!(
+            out,
+            "  \"{}\" -> \"{}\" [label=\"{}\", color=\"{}\", fontcolor=\"{}\", style={}];",
+            edge.source_id,
+            edge.target_id,
+            edge.edge_type.as_str(),
+            style.color,
+            style.color,
+            style.line.name()
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn dot_shape(schema: &GraphSchema, node_type: &NodeType) -> &'static str {
+    match schema.builtin_node_type(node_type).unwrap_or(node_type) {
+        NodeType::Repository => "folder",
+        NodeType::File => "note",
+        NodeType::Module => "tab",
+        NodeType::Class => "box",
+        NodeType::Function => "ellipse",
+        NodeType::Variable | NodeType::Constant => "plaintext",
+        NodeType::Import => "cds",
+        NodeType::Agent => "hexagon",
+        NodeType::Skill => "component",
+        NodeType::Task => "parallelogram",
+        NodeType::Custom(_) => "box",
+    }
+}
+
+/// Mermaid ids must be plain identifiers, so nodes are numbered in order.
+fn mermaid(nodes: &[&Node], edges: &[&Edge], schema: &GraphSchema) -> String {
+    let mut out = String::from("flowchart LR\n");
+    let mut ids = HashMap::new();
+    for (index, node) in nodes.iter().enumerate() {
+        ids.insert(node.id, index);
+        let (open, close) = mermaid_shape(schema, &node.node_type);
+        let _ = writeln!(
+            out,
+            "    n{}{}\"{}\"{}",
+            index,
+            open,
+            escape_mermaid(&label(node, "\n")),
+            close
+        );
+    }
+
+    let mut link_styles = Vec::new();
+    for edge in edges {
+        let (Some(source), Some(target)) = (ids.get(&edge.source_id), ids.get(&edge.target_id))
+        else {
+            continue;
+        };
+        let style = edge_style(schema, &edge.edge_type);
+        let arrow = match style.line {
+            Line::Solid => "-->",
+            Line::Dashed | Line::Dotted => "-.->",
+            Line::Bold => "==>",
+        };
+        let stroke = match style.line {
+            Line::Solid => String::new(),
+            Line::Dashed => ",stroke-dasharray:6 4".to_string(),
+            Line::Dotted => ",stroke-dasharray:2 2".to_string(),
+            Line::Bold => ",stroke-width:2px".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "    n{} {}|{}| n{}",
+            source,
+            arrow,
+            edge.edge_type.as_str(),
+            target
+        );
+        link_styles.push(format!(
+            "    linkStyle {} stroke:{}{}",
+            link_styles.len(),
+            style.color,
+            stroke
+        ));
+    }
+    for line in link_styles {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn mermaid_shape(schema: &GraphSchema, node_type: &NodeType) -> (&'static str, &'static str) {
+    match schema.builtin_node_type(node_type).unwrap_or(node_type) {
+        NodeType::Repository => ("[(", ")]"),
+        NodeType::File => ("[/", "/]"),
+        NodeType::Module => ("[[", "]]"),
+        NodeType::Function => ("(", ")"),
+        NodeType::Agent => ("{{", "}}"),
+        NodeType::Task => (">", "]"),
+        _ => ("[", "]"),
+    }
+}
+
+/// `name`, with the path on a second line when there is one.
+fn label(node: &Node, line_break: &str) -> String {
+    match &node.path {
+        Some(path) => format!("{}{}{}", node.name, line_break, path),
+        None => node.name.clone(),
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => out.push(char::REPLACEMENT_CHARACTER),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_mermaid(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+        .replace('\n', "<br/>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Direction;
+
+    fn sample() -> (KnowledgeGraph, Uuid, Uuid, Uuid) {
+        let mut graph = KnowledgeGraph::new();
+        let file = graph
+            .add_node(
+                Node::new("payments.py".to_string(), NodeType::File)
+                    .with_path("src/payments.py".to_string()),
+            )
+            .unwrap();
+        let charge = graph
+            .add_node(Node::new("charge<T>".to_string(), NodeType::Function))
+            .unwrap();
+        let audit = graph
+            .add_node(Node::new("audit \"log\"".to_string(), NodeType::Function))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(file, charge, EdgeType::Contains))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(charge, audit, EdgeType::Calls))
+            .unwrap();
+        (graph, file, charge, audit)
+    }
+
+    fn query(format: RenderFormat) -> RenderQuery {
+        RenderQuery {
+            format,
+            root_id: None,
+            depth: None,
+            direction: None,
+            node_ids: None,
+            edge_types: None,
+        }
+    }
+
+    #[test]
+    fn test_select_neighborhood_of_root() {
+        let (graph, file, charge, audit) = sample();
+        let mut around = query(RenderFormat::Dot);
+        around.root_id = Some(file);
+        around.direction = Some(Direction::Outgoing);
+
+        let subgraph = select(&graph, &around).unwrap();
+        let ids: HashSet<Uuid> = subgraph.nodes.iter().map(|n| n.id).collect();
+        assert_eq!(ids, HashSet::from([file, charge]));
+        assert_eq!(subgraph.edges.len(), 1);
+        assert!(!ids.contains(&audit));
+
+        assert_eq!(
+            select(&graph, &query(RenderFormat::Dot))
+                .unwrap()
+                .nodes
+                .len(),
+            3
+        );
+        around.root_id = Some(Uuid::new_v4());
+        assert!(select(&graph, &around).is_none());
+    }
+
+    #[test]
+    fn test_dot_escapes_labels_and_styles_edges() {
+        let (mut graph, file, charge, _) = sample();
+        let subgraph = select(&graph, &query(RenderFormat::Dot)).unwrap();
+        let dot = render(&subgraph, RenderFormat::Dot, graph.schema());
+
+        assert!(dot.starts_with("digraph knowledge_graph {"));
+        assert!(dot.contains(&format!(
+            "\"{}\" [label=\"payments.py\\nsrc/payments.py\", shape=note];",
+            file
+        )));
+        assert!(dot.contains("label=\"audit \\\"log\\\"\""));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [label=\"contains\", color=\"#7f8c8d\"",
+            file, charge
+        )));
+        assert!(dot.contains("style=bold];"));
+
+        // A method is drawn as the function it specializes.
+        let method = NodeType::Custom("method".to_string());
+        let refund = graph
+            .add_node(Node::new("refund".to_string(), method))
+            .unwrap();
+        let subgraph = graph.subgraph(&[refund], None);
+        let dot = render(&subgraph, RenderFormat::Dot, graph.schema());
+        assert!(dot.contains("[label=\"refund\", shape=ellipse];"));
+    }
+
+    #[test]
+    fn test_graphml_and_mermaid_escape_text() {
+        let (graph, _, _, _) = sample();
+        let subgraph = select(&graph, &query(RenderFormat::Graphml)).unwrap();
+
+        let graphml = render(&subgraph, RenderFormat::Graphml, graph.schema());
+        assert!(graphml.contains("<data key=\"label\">charge&lt;T&gt;</data>"));
+        assert!(graphml.contains("<data key=\"edge_type\">calls</data>"));
+        assert_eq!(graphml.matches("<node id=").count(), 3);
+        assert_eq!(graphml.matches("<edge id=").count(), 2);
+
+        let mermaid = render(&subgraph, RenderFormat::Mermaid, graph.schema());
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("\"charge#lt;T#gt;\""));
+        assert!(mermaid.contains("\"payments.py<br/>src/payments.py\""));
+        assert!(mermaid.contains("==>|calls|"));
+        assert!(mermaid.contains("linkStyle 1 stroke:"));
+        assert_eq!(
+            render(&subgraph, RenderFormat::Mermaid, graph.schema()),
+            mermaid
+        );
+    }
+
+    #[test]
+    fn test_xml_drops_invalid_characters() {
+        assert_eq!(
+            escape_xml("a\u{0}b\u{1b}[0m\tc\r\n"),
+            "a\u{fffd}b\u{fffd}[0m\tc\r\n"
+        );
+        assert_eq!(escape_xml("\u{ffff}\u{e9}&"), "\u{fffd}\u{e9}&amp;");
+    }
+}
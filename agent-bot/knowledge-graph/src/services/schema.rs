@@ -0,0 +1,613 @@
+//! Schema constraints on graph contents, from `config/schema.json`.
+//!
+//! The schema declares which node types each edge type may connect, how
+//! many edges of a type a node may have, and bounds on names and weights.
+//! It also registers node and edge types beyond the built-in ones; a
+//! custom type may name a parent, e.g. `method` is a `function`, and then
+//! follows its parent's rules and matches queries for it.
+//! Writes through the API are checked against it; data written before a
+//! rule existed is found by auditing the graph.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Direction, Edge, EdgeType, Node, NodeType, SchemaRule, Violation};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphSchema {
+    /// Custom node types. Nodes of an unregistered custom type are rejected.
+    #[serde(default)]
+    pub node_types: Vec<TypeDefinition<NodeType>>,
+    #[serde(default)]
+    pub edge_types: Vec<TypeDefinition<EdgeType>>,
+    /// Allowed endpoints per edge type. Edge types without a rule may
+    /// connect any two nodes.
+    #[serde(default)]
+    pub edges: Vec<EdgeRule>,
+    #[serde(default)]
+    pub cardinality: Vec<CardinalityRule>,
+    #[serde(default)]
+    pub values: ValueConstraints,
+}
+
+/// A custom type and the type it specializes, if any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeDefinition<T> {
+    pub name: T,
+    pub parent: Option<T>,
+}
+
+/// Edges of `edge_type` may run from any of `source` to any of `target`.
+/// An edge type may have several rules; an edge needs to match one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeRule {
+    pub edge_type: EdgeType,
+    pub source: Vec<NodeType>,
+    pub target: Vec<NodeType>,
+    /// Whether a node may have such an edge to itself, e.g. a recursive call.
+    #[serde(default)]
+    pub self_loops: bool,
+}
+
+/// Bounds on the number of `edge_type` edges at each `node_type` node.
+/// Writes only enforce `max`, since a node is created before its edges;
+/// audits check both.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardinalityRule {
+    pub node_type: NodeType,
+    pub edge_type: EdgeType,
+    pub direction: Direction,
+    #[serde(default)]
+    pub min: usize,
+    #[serde(default)]
+    pub max: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueConstraints {
+    #[serde(default = "default_max_name_length")]
+    pub max_name_length: usize,
+    /// Path queries run Dijkstra over weights, which needs them non-negative.
+    #[serde(default)]
+    pub min_weight: f64,
+    #[serde(default)]
+    pub max_weight: Option<f64>,
+}
+
+fn default_max_name_length() -> usize {
+    512
+}
+
+impl Default for ValueConstraints {
+    fn default() -> Self {
+        Self {
+            max_name_length: default_max_name_length(),
+            min_weight: 0.0,
+            max_weight: None,
+        }
+    }
+}
+
+impl GraphSchema {
+    /// Reads `GRAPH_SCHEMA_FILE`; without the file the built-in schema
+    /// applies.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let path = std::env::var("GRAPH_SCHEMA_FILE")
+            .unwrap_or_else(|_| "/app/config/schema.json".to_string());
+        if !Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+        Self::load(path)
+    }
+
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let schema: Self = config::Config::builder()
+            .add_source(config::File::from(path.as_path()).format(config::FileFormat::Json))
+            .build()?
+            .try_deserialize()?;
+        check_definitions("node", &schema.node_types)?;
+        check_definitions("edge", &schema.edge_types)?;
+        Ok(schema)
+    }
+
+    /// Whether `node_type` is `ancestor` or one of its subtypes.
+    pub fn node_is_a(&self, node_type: &NodeType, ancestor: &NodeType) -> bool {
+        lineage(&self.node_types, node_type).any(|t| t == ancestor)
+    }
+
+    pub fn edge_is_a(&self, edge_type: &EdgeType, ancestor: &EdgeType) -> bool {
+        lineage(&self.edge_types, edge_type).any(|t| t == ancestor)
+    }
+
+    /// `types` and all their registered subtypes, for filtering queries.
+    pub fn node_subtypes(&self, types: &[NodeType]) -> Vec<NodeType> {
+        with_subtypes(&self.node_types, types)
+    }
+
+    pub fn edge_subtypes(&self, types: &[EdgeType]) -> Vec<EdgeType> {
+        with_subtypes(&self.edge_types, types)
+    }
+
+    /// The nearest built-in type `node_type` specializes, if any.
+    pub fn builtin_node_type<'a>(&'a self, node_type: &'a NodeType) -> Option<&'a NodeType> {
+        lineage(&self.node_types, node_type).find(|t| !t.is_custom())
+    }
+
+    pub fn builtin_edge_type<'a>(&'a self, edge_type: &'a EdgeType) -> Option<&'a EdgeType> {
+        lineage(&self.edge_types, edge_type).find(|t| !t.is_custom())
+    }
+
+    /// Why a query filter on `types` is rejected, if it is: an unregistered
+    /// type would silently match nothing.
+    pub fn node_filter_error<'a>(
+        &self,
+        types: impl IntoIterator<Item = &'a NodeType>,
+    ) -> Option<String> {
+        let ty = types
+            .into_iter()
+            .find(|ty| !is_registered(&self.node_types, *ty))?;
+        Some(unknown("Node", ty))
+    }
+
+    pub fn edge_filter_error<'a>(
+        &self,
+        types: impl IntoIterator<Item = &'a EdgeType>,
+    ) -> Option<String> {
+        let ty = types
+            .into_iter()
+            .find(|ty| !is_registered(&self.edge_types, *ty))?;
+        Some(unknown("Edge", ty))
+    }
+
+    /// Checks a node's type is known and its name is valid.
+    pub fn check_node(&self, node: &Node) -> Result<(), Violation> {
+        let violation = |rule, message: String| Violation {
+            rule,
+            field: "name".to_string(),
+            node_id: Some(node.id),
+            edge_id: None,
+            message,
+        };
+        if !is_registered(&self.node_types, &node.node_type) {
+            return Err(Violation {
+                field: "node_type".to_string(),
+                ..violation(SchemaRule::UnknownType, unknown("Node", &node.node_type))
+            });
+        }
+        if node.name.trim().is_empty() {
+            return Err(violation(
+                SchemaRule::EmptyName,
+                "Node name is empty".to_string(),
+            ));
+        }
+        let max = self.values.max_name_length;
+        if node.name.chars().count() > max {
+            let message = format!("Node name is longer than {} characters", max);
+            return Err(violation(SchemaRule::NameTooLong, message));
+        }
+        Ok(())
+    }
+
+    pub fn check_edge_type(&self, edge: &Edge) -> Result<(), Violation> {
+        if is_registered(&self.edge_types, &edge.edge_type) {
+            return Ok(());
+        }
+        Err(Violation {
+            rule: SchemaRule::UnknownType,
+            field: "edge_type".to_string(),
+            node_id: None,
+            edge_id: Some(edge.id),
+            message: unknown("Edge", &edge.edge_type),
+        })
+    }
+
+    pub fn check_weight(&self, edge: &Edge) -> Result<(), Violation> {
+        let ValueConstraints {
+            min_weight,
+            max_weight,
+            ..
+        } = self.values;
+        let in_range = edge.weight.is_finite()
+            && edge.weight >= min_weight
+            && max_weight.is_none_or(|max| edge.weight <= max);
+        if in_range {
+            return Ok(());
+        }
+        let range = match max_weight {
+            Some(max) => format!("between {} and {}", min_weight, max),
+            None => format!("at least {}", min_weight),
+        };
+        Err(Violation {
+            rule: SchemaRule::Weight,
+            field: "weight".to_string(),
+            node_id: None,
+            edge_id: Some(edge.id),
+            message: format!("Edge weight {} must be {}", edge.weight, range),
+        })
+    }
+
+    /// Checks `edge` may connect a `source` node to a `target` node. Rules
+    /// on a parent type cover its subtypes.
+    pub fn check_endpoints(
+        &self,
+        edge: &Edge,
+        source: &Node,
+        target: &Node,
+    ) -> Result<(), Violation> {
+        let rules: Vec<&EdgeRule> = self
+            .edges
+            .iter()
+            .filter(|rule| self.edge_is_a(&edge.edge_type, &rule.edge_type))
+            .collect();
+        if rules.is_empty() {
+            return Ok(());
+        }
+        let admits = |types: &[NodeType], node: &Node| {
+            types.iter().any(|t| self.node_is_a(&node.node_type, t))
+        };
+        let matching: Vec<&EdgeRule> = rules
+            .into_iter()
+            .filter(|rule| admits(&rule.source, source))
+            .filter(|rule| admits(&rule.target, target))
+            .collect();
+        let self_loop = edge.source_id == edge.target_id;
+        if matching.iter().any(|rule| !self_loop || rule.self_loops) {
+            return Ok(());
+        }
+
+        let edge_type = edge.edge_type.as_str();
+        let (rule, field, message) = if matching.is_empty() {
+            let message = format!(
+                "A {} edge cannot run from a {} to a {}",
+                edge_type,
+                source.node_type.as_str(),
+                target.node_type.as_str()
+            );
+            (SchemaRule::EdgeEndpoints, "edge_type", message)
+        } else {
+            let message = format!("A {} edge cannot connect a node to itself", edge_type);
+            (SchemaRule::SelfLoop, "target_id", message)
+        };
+        Err(Violation {
+            rule,
+            field: field.to_string(),
+            node_id: None,
+            edge_id: Some(edge.id),
+            message,
+        })
+    }
+
+    /// Cardinality rules covering `edge_type` edges at `node_type` nodes.
+    pub fn limits<'a>(
+        &'a self,
+        node_type: &'a NodeType,
+        edge_type: &'a EdgeType,
+    ) -> impl Iterator<Item = &'a CardinalityRule> {
+        self.cardinality.iter().filter(move |rule| {
+            self.node_is_a(node_type, &rule.node_type) && self.edge_is_a(edge_type, &rule.edge_type)
+        })
+    }
+}
+
+/// What the type registry needs from `NodeType` and `EdgeType`.
+trait SchemaType: PartialEq + Clone {
+    fn name(&self) -> &str;
+    fn is_custom(&self) -> bool;
+}
+
+impl SchemaType for NodeType {
+    fn name(&self) -> &str {
+        self.as_str()
+    }
+
+    fn is_custom(&self) -> bool {
+        matches!(self, NodeType::Custom(_))
+    }
+}
+
+impl SchemaType for EdgeType {
+    fn name(&self) -> &str {
+        self.as_str()
+    }
+
+    fn is_custom(&self) -> bool {
+        matches!(self, EdgeType::Custom(_))
+    }
+}
+
+/// `ty` followed by its ancestors, nearest first.
+fn lineage<'a, T: SchemaType>(
+    definitions: &'a [TypeDefinition<T>],
+    ty: &'a T,
+) -> impl Iterator<Item = &'a T> {
+    std::iter::successors(Some(ty), move |ty| {
+        definitions.iter().find(|d| d.name == **ty)?.parent.as_ref()
+    })
+    .take(definitions.len() + 1)
+}
+
+fn with_subtypes<T: SchemaType>(definitions: &[TypeDefinition<T>], types: &[T]) -> Vec<T> {
+    let mut expanded = types.to_vec();
+    for definition in definitions {
+        let inherits = lineage(definitions, &definition.name).any(|t| types.contains(t));
+        if inherits && !expanded.contains(&definition.name) {
+            expanded.push(definition.name.clone());
+        }
+    }
+    expanded
+}
+
+fn is_registered<T: SchemaType>(definitions: &[TypeDefinition<T>], ty: &T) -> bool {
+    !ty.is_custom() || definitions.iter().any(|d| d.name == *ty)
+}
+
+fn unknown<T: SchemaType>(kind: &str, ty: &T) -> String {
+    format!(
+        "{} type {} is not registered in the schema",
+        kind,
+        ty.name()
+    )
+}
+
+fn check_definitions<T: SchemaType>(
+    kind: &str,
+    definitions: &[TypeDefinition<T>],
+) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+    for definition in definitions {
+        let name = definition.name.name();
+        if !definition.name.is_custom() {
+            bail!("{} type {} is built in and cannot be redefined", kind, name);
+        }
+        let snake_case = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !snake_case {
+            bail!("{} type {:?} must be snake_case", kind, name);
+        }
+        if !seen.insert(name) {
+            bail!("{} type {} is defined twice", kind, name);
+        }
+        if let Some(parent) = &definition.parent {
+            if !is_registered(definitions, parent) {
+                bail!(
+                    "Parent {} of {} type {} is not registered",
+                    parent.name(),
+                    kind,
+                    name
+                );
+            }
+        }
+        if lineage(definitions, &definition.name)
+            .skip(1)
+            .any(|t| *t == definition.name)
+        {
+            bail!("{} type {} is its own ancestor", kind, name);
+        }
+    }
+    Ok(())
+}
+
+impl CardinalityRule {
+    /// The violation for a node with `count` such edges, if any.
+    pub fn check(&self, node: &Node, count: usize) -> Result<(), Violation> {
+        if count >= self.min && self.max.is_none_or(|max| count <= max) {
+            return Ok(());
+        }
+        let bound = match self.max {
+            Some(max) if max == self.min => format!("exactly {}", max),
+            Some(max) if count > max => format!("at most {}", max),
+            _ => format!("at least {}", self.min),
+        };
+        let direction = match self.direction {
+            Direction::Incoming => " incoming",
+            Direction::Outgoing => " outgoing",
+            Direction::Both => "",
+        };
+        Err(Violation {
+            rule: SchemaRule::Cardinality,
+            field: "edge_type".to_string(),
+            node_id: Some(node.id),
+            edge_id: None,
+            message: format!(
+                "A {} must have {}{} {} edge(s); {} has {}",
+                self.node_type.as_str(),
+                bound,
+                direction,
+                self.edge_type.as_str(),
+                node.name,
+                count
+            ),
+        })
+    }
+}
+
+impl Default for GraphSchema {
+    /// Code structure: repositories hold files and directories, files hold
+    /// definitions, and only code calls, imports or inherits. Edges between
+    /// agents, skills and tasks are unconstrained. Every file sits in
+    /// exactly one repository or directory. Methods are functions and
+    /// directories are modules.
+    fn default() -> Self {
+        use NodeType::*;
+
+        let code = [File, Module, Class, Function, Variable, Constant];
+        let definitions = &code[1..];
+        let members = &code[2..];
+        let rule = |edge_type, source: &[NodeType], target: &[NodeType], self_loops| EdgeRule {
+            edge_type,
+            source: source.to_vec(),
+            target: target.to_vec(),
+            self_loops,
+        };
+
+        let custom = |name: &str, parent| TypeDefinition {
+            name: Custom(name.to_string()),
+            parent: Some(parent),
+        };
+
+        Self {
+            node_types: vec![custom("method", Function), custom("directory", Module)],
+            edge_types: Vec::new(),
+            edges: vec![
+                rule(EdgeType::Contains, &[Repository], &[File, Module], false),
+                rule(EdgeType::Contains, &[Module], &code, false),
+                rule(EdgeType::Contains, &[File], definitions, false),
+                rule(EdgeType::Contains, &[Class, Function], members, false),
+                rule(
+                    EdgeType::Imports,
+                    &code[..4],
+                    &[File, Module, Import],
+                    false,
+                ),
+                rule(EdgeType::Calls, &code, definitions, true),
+                rule(EdgeType::Inherits, &[Class], &[Class], false),
+                rule(EdgeType::Implements, &[Class], &[Class], false),
+                rule(
+                    EdgeType::DefinedIn,
+                    definitions,
+                    &[File, Module, Class],
+                    false,
+                ),
+            ],
+            cardinality: vec![CardinalityRule {
+                node_type: File,
+                edge_type: EdgeType::Contains,
+                direction: Direction::Incoming,
+                min: 1,
+                max: Some(1),
+            }],
+            values: ValueConstraints::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_schema_rules() {
+        let schema = GraphSchema::default();
+        let repository = Node::new("billing".to_string(), NodeType::Repository);
+        let file = Node::new("payments.py".to_string(), NodeType::File);
+        let class = Node::new("Payment".to_string(), NodeType::Class);
+        let function = Node::new("retry".to_string(), NodeType::Function);
+
+        let contains = Edge::new(repository.id, file.id, EdgeType::Contains);
+        assert!(schema
+            .check_endpoints(&contains, &repository, &file)
+            .is_ok());
+        let backwards = Edge::new(function.id, repository.id, EdgeType::Contains);
+        let violation = schema
+            .check_endpoints(&backwards, &function, &repository)
+            .unwrap_err();
+        assert_eq!(violation.rule, SchemaRule::EdgeEndpoints);
+        assert!(violation
+            .message
+            .contains("from a function to a repository"));
+
+        let inherits = Edge::new(class.id, class.id, EdgeType::Inherits);
+        let violation = schema
+            .check_endpoints(&inherits, &class, &class)
+            .unwrap_err();
+        assert_eq!(
+            (violation.rule, violation.field.as_str()),
+            (SchemaRule::SelfLoop, "target_id")
+        );
+        let recursion = Edge::new(function.id, function.id, EdgeType::Calls);
+        assert!(schema
+            .check_endpoints(&recursion, &function, &function)
+            .is_ok());
+        let uses = Edge::new(function.id, repository.id, EdgeType::Uses);
+        assert!(schema
+            .check_endpoints(&uses, &function, &repository)
+            .is_ok());
+
+        let negative = Edge::new(function.id, class.id, EdgeType::Calls).with_weight(-1.0);
+        assert_eq!(
+            schema.check_weight(&negative).unwrap_err().rule,
+            SchemaRule::Weight
+        );
+        let blank = Node::new("  ".to_string(), NodeType::Function);
+        assert_eq!(
+            schema.check_node(&blank).unwrap_err().rule,
+            SchemaRule::EmptyName
+        );
+
+        let container = schema
+            .limits(&NodeType::File, &EdgeType::Contains)
+            .next()
+            .unwrap();
+        assert!(container.check(&file, 1).is_ok());
+        let orphan = container.check(&file, 0).unwrap_err();
+        assert!(orphan.message.contains("exactly 1 incoming contains"));
+    }
+
+    #[test]
+    fn test_custom_types_follow_their_parents() {
+        let schema = GraphSchema::default();
+        let method: NodeType = serde_json::from_str("\"method\"").unwrap();
+        assert_eq!(method, NodeType::Custom("method".to_string()));
+        let function: NodeType = serde_json::from_str("\"function\"").unwrap();
+        assert_eq!(function, NodeType::Function);
+        assert!(schema.node_is_a(&method, &NodeType::Function));
+        assert!(schema
+            .node_subtypes(&[NodeType::Function])
+            .contains(&method));
+        assert_eq!(schema.builtin_node_type(&method), Some(&NodeType::Function));
+
+        let class = Node::new("Payment".to_string(), NodeType::Class);
+        let refund = Node::new("refund".to_string(), method);
+        let contains = Edge::new(class.id, refund.id, EdgeType::Contains);
+        assert!(schema.check_node(&refund).is_ok());
+        assert!(schema.check_endpoints(&contains, &class, &refund).is_ok());
+        let inherits = Edge::new(refund.id, class.id, EdgeType::Inherits);
+        assert!(schema.check_endpoints(&inherits, &refund, &class).is_err());
+
+        let lambda = Node::new("f".to_string(), NodeType::Custom("lambda".to_string()));
+        let violation = schema.check_node(&lambda).unwrap_err();
+        assert_eq!(violation.rule, SchemaRule::UnknownType);
+        assert_eq!(violation.field, "node_type");
+        let awaits = Edge::new(class.id, refund.id, EdgeType::Custom("awaits".to_string()));
+        assert_eq!(
+            schema.check_edge_type(&awaits).unwrap_err().rule,
+            SchemaRule::UnknownType
+        );
+        assert!(schema
+            .node_filter_error([&NodeType::File, &refund.node_type])
+            .is_none());
+        assert!(schema.node_filter_error([&lambda.node_type]).is_some());
+        let typo = EdgeType::Custom("cals".to_string());
+        assert!(schema
+            .edge_filter_error([&EdgeType::Calls, &typo])
+            .unwrap()
+            .contains("cals"));
+
+        let custom = |name: &str, parent: Option<&str>| TypeDefinition {
+            name: NodeType::Custom(name.to_string()),
+            parent: parent.map(|p| serde_json::from_value(p.into()).unwrap()),
+        };
+        assert!(check_definitions("node", &[custom("method", Some("function"))]).is_ok());
+        let redefined = [TypeDefinition {
+            name: NodeType::File,
+            parent: None,
+        }];
+        assert!(check_definitions("node", &redefined).is_err());
+        assert!(check_definitions("node", &[custom("Method", None)]).is_err());
+        assert!(check_definitions("node", &[custom("method", Some("lambda"))]).is_err());
+        let cycle = [custom("a", Some("b")), custom("b", Some("a"))];
+        assert!(check_definitions("node", &cycle).is_err());
+    }
+
+    #[test]
+    fn test_example_config_matches_builtin_schema() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config/schema.example.json");
+        assert_eq!(GraphSchema::load(path).unwrap(), GraphSchema::default());
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+use uuid::Uuid;
+
+use crate::models::{Node, SearchMode};
+
+/// Metadata keys whose string values are indexed alongside the node fields.
+pub const INDEXED_METADATA_FIELDS: &[&str] =
+    &["qualified_name", "signature", "docstring", "summary"];
+
+const NAME_BOOST: f64 = 3.0;
+const PATH_BOOST: f64 = 1.5;
+const DESCRIPTION_BOOST: f64 = 1.0;
+const METADATA_BOOST: f64 = 1.0;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// Inverted index over node text with BM25 ranking.
+///
+/// Field boosts are folded into the term frequencies and document lengths
+/// (a simplified BM25F), so a match in `name` outweighs one in `description`.
+#[derive(Default)]
+pub struct SearchIndex {
+    /// term -> document -> boosted term frequency
+    postings: BTreeMap<String, HashMap<Uuid, f64>>,
+    /// Indexed terms by length in characters, so fuzzy matching only
+    /// compares terms within the allowed number of edits.
+    terms_by_length: HashMap<usize, BTreeSet<String>>,
+    doc_terms: HashMap<Uuid, Vec<String>>,
+    doc_lengths: HashMap<Uuid, f64>,
+    /// Name tokens joined by spaces, for `SearchMode::Exact`.
+    name_keys: HashMap<Uuid, String>,
+    total_length: f64,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, node: &Node) {
+        self.remove(&node.id);
+
+        let mut frequencies: HashMap<String, f64> = HashMap::new();
+        let mut length = 0.0;
+        let mut add = |text: &str, boost: f64| {
+            for token in tokenize(text) {
+                *frequencies.entry(token).or_insert(0.0) += boost;
+                length += boost;
+            }
+        };
+
+        add(&node.name, NAME_BOOST);
+        if let Some(path) = &node.path {
+            add(path, PATH_BOOST);
+        }
+        if let Some(description) = &node.description {
+            add(description, DESCRIPTION_BOOST);
+        }
+        for field in INDEXED_METADATA_FIELDS {
+            if let Some(value) = node.metadata.get(*field).and_then(|v| v.as_str()) {
+                add(value, METADATA_BOOST);
+            }
+        }
+
+        for (term, frequency) in &frequencies {
+            if !self.postings.contains_key(term) {
+                self.terms_by_length
+                    .entry(term.chars().count())
+                    .or_default()
+                    .insert(term.clone());
+            }
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(node.id, *frequency);
+        }
+        self.doc_terms
+            .insert(node.id, frequencies.into_keys().collect());
+        self.doc_lengths.insert(node.id, length);
+        self.name_keys.insert(node.id, name_key(&node.name));
+        self.total_length += length;
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        let Some(terms) = self.doc_terms.remove(id) else {
+            return;
+        };
+        for term in terms {
+            if let Some(docs) = self.postings.get_mut(&term) {
+                docs.remove(id);
+                if docs.is_empty() {
+                    self.postings.remove(&term);
+                    self.remove_term_length(&term);
+                }
+            }
+        }
+        if let Some(length) = self.doc_lengths.remove(id) {
+            self.total_length -= length;
+        }
+        self.name_keys.remove(id);
+    }
+
+    /// Every document matching at least one query token, best first.
+    ///
+    /// Non-exact term matches (longer prefixes, misspellings) are discounted
+    /// by how similar they are to the query token. Ties are broken by node id
+    /// so the order is stable across calls.
+    pub fn search(&self, query: &str, mode: SearchMode) -> Vec<(Uuid, f64)> {
+        let doc_count = self.doc_lengths.len() as f64;
+        if doc_count == 0.0 {
+            return Vec::new();
+        }
+        let avg_length = (self.total_length / doc_count).max(f64::EPSILON);
+
+        let mut scores: HashMap<Uuid, f64> = HashMap::new();
+        for token in tokenize(query) {
+            for (term, similarity) in self.matching_terms(&token, mode) {
+                let docs = &self.postings[term];
+                let df = docs.len() as f64;
+                let idf = (1.0 + (doc_count - df + 0.5) / (df + 0.5)).ln();
+
+                for (doc, tf) in docs {
+                    let length = self.doc_lengths.get(doc).copied().unwrap_or(0.0);
+                    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length / avg_length);
+                    let score = idf * tf * (BM25_K1 + 1.0) / (tf + norm);
+                    *scores.entry(*doc).or_insert(0.0) += score * similarity;
+                }
+            }
+        }
+
+        if mode == SearchMode::Exact {
+            let key = name_key(query);
+            scores.retain(|doc, _| self.name_keys.get(doc) == Some(&key));
+        }
+
+        let mut ranked: Vec<(Uuid, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    /// Index terms matched by one query token, with a similarity in `(0, 1]`.
+    fn matching_terms(&self, token: &str, mode: SearchMode) -> Vec<(&String, f64)> {
+        match mode {
+            SearchMode::Exact | SearchMode::Token => self
+                .postings
+                .get_key_value(token)
+                .map(|(term, _)| (term, 1.0))
+                .into_iter()
+                .collect(),
+            SearchMode::Prefix => self
+                .postings
+                .range::<str, _>((Bound::Included(token), Bound::Unbounded))
+                .take_while(|(term, _)| term.starts_with(token))
+                .map(|(term, _)| (term, token.len() as f64 / term.len() as f64))
+                .collect(),
+            SearchMode::Fuzzy => {
+                let max_edits = max_edits(token);
+                let token: Vec<char> = token.chars().collect();
+                let lengths = token.len().saturating_sub(max_edits)..=token.len() + max_edits;
+                lengths
+                    .filter_map(|length| self.terms_by_length.get(&length))
+                    .flatten()
+                    .filter_map(|term| {
+                        let term_chars: Vec<char> = term.chars().collect();
+                        let distance = edit_distance(&token, &term_chars);
+                        (distance <= max_edits).then(|| {
+                            let longest = token.len().max(term_chars.len()) as f64;
+                            (term, 1.0 - distance as f64 / longest)
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn remove_term_length(&mut self, term: &str) {
+        let length = term.chars().count();
+        if let Some(terms) = self.terms_by_length.get_mut(&length) {
+            terms.remove(term);
+            if terms.is_empty() {
+                self.terms_by_length.remove(&length);
+            }
+        }
+    }
+}
+
+/// Typos tolerated in a fuzzy token; short tokens must be nearly exact.
+fn max_edits(token: &str) -> usize {
+    match token.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Splits text into lowercased identifier tokens.
+///
+/// Identifiers (runs of alphanumerics, `_` and `-`) are split on `_`, `-`
+/// and camelCase or acronym boundaries (`parseHTTP_request` -> `parse`,
+/// `http`, `request`); any other character, such as `/`, `.` or whitespace,
+/// separates identifiers. An identifier that splits is also emitted joined
+/// (`parsehttprequest`) so a run-together query still matches.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for run in runs(text) {
+        let parts = split_identifier(run);
+        if parts.len() > 1 {
+            tokens.push(parts.concat());
+        }
+        tokens.extend(parts);
+    }
+    tokens
+}
+
+/// `HandleAuth`, `handle_auth` and `handle auth` share a key.
+fn name_key(name: &str) -> String {
+    runs(name)
+        .flat_map(split_identifier)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn runs(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .filter(|run| !run.is_empty())
+}
+
+fn split_identifier(run: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for word in run.split(['_', '-']).filter(|word| !word.is_empty()) {
+        let chars: Vec<char> = word.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, cur) = (chars[i - 1], chars[i]);
+            let lower_to_upper = (prev.is_lowercase() || prev.is_numeric()) && cur.is_uppercase();
+            let acronym_end = prev.is_uppercase()
+                && cur.is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if lower_to_upper || acronym_end {
+                parts.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        parts.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+    parts
+}
+
+/// Opaque keyset cursor: the score and id of the last hit on a page.
+pub fn encode_cursor(score: f64, id: &Uuid) -> String {
+    format!("{:016x}.{}", score.to_bits(), id.simple())
+}
+
+pub fn decode_cursor(cursor: &str) -> Option<(f64, Uuid)> {
+    let (bits, id) = cursor.split_once('.')?;
+    let score = f64::from_bits(u64::from_str_radix(bits, 16).ok()?);
+    Some((score, Uuid::parse_str(id).ok()?))
+}
+
+/// Why `cursor` cannot resume a search, if it can't.
+pub fn cursor_error(cursor: Option<&str>) -> Option<String> {
+    match cursor.map(decode_cursor) {
+        Some(None) => Some("not a cursor returned by a previous page".to_string()),
+        Some(Some((score, _))) if score.is_nan() => Some("score is not a number".to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a ranked hit sorts after the hit a cursor points at.
+pub fn is_after_cursor(score: f64, id: &Uuid, cursor: &(f64, Uuid)) -> bool {
+    score < cursor.0 || (score == cursor.0 && *id > cursor.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NodeType;
+
+    #[test]
+    fn test_name_match_outranks_description_match() {
+        let mut index = SearchIndex::new();
+        let by_name = Node::new("retry".to_string(), NodeType::Function);
+        let by_description = Node::new("schedule".to_string(), NodeType::Function)
+            .with_description("Queues a payment retry for later".to_string());
+        index.insert(&by_name);
+        index.insert(&by_description);
+
+        let ranked = index.search("retry", SearchMode::Token);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].0, by_name.id);
+        assert!(ranked[0].1 > ranked[1].1);
+    }
+
+    #[test]
+    fn test_exact_term_outranks_partial_term() {
+        let mut index = SearchIndex::new();
+        let exact = Node::new("auth".to_string(), NodeType::Module);
+        let partial = Node::new("handleAuth".to_string(), NodeType::Function);
+        index.insert(&partial);
+        index.insert(&exact);
+
+        let ranked = index.search("auth", SearchMode::Token);
+        assert_eq!(ranked[0].0, exact.id);
+        assert_eq!(ranked[1].0, partial.id);
+    }
+
+    #[test]
+    fn test_metadata_fields_are_indexed_and_removal_cleans_up() {
+        let mut index = SearchIndex::new();
+        let node = Node::new("process".to_string(), NodeType::Function)
+            .with_path("src/billing/payments.py".to_string())
+            .with_metadata(serde_json::json!({ "docstring": "Charges the customer card" }));
+        index.insert(&node);
+
+        assert_eq!(index.search("payments", SearchMode::Token).len(), 1);
+        assert_eq!(index.search("customer", SearchMode::Token).len(), 1);
+
+        index.remove(&node.id);
+        assert!(index.search("customer", SearchMode::Token).is_empty());
+        assert!(index.postings.is_empty());
+        assert_eq!(index.total_length, 0.0);
+    }
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("handle_auth"),
+            vec!["handleauth", "handle", "auth"]
+        );
+        assert_eq!(tokenize("HandleAuth"), vec!["handleauth", "handle", "auth"]);
+        assert_eq!(
+            tokenize("handle-auth"),
+            vec!["handleauth", "handle", "auth"]
+        );
+        assert_eq!(
+            tokenize("parseHTTP_request"),
+            vec!["parsehttprequest", "parse", "http", "request"]
+        );
+        assert_eq!(
+            tokenize("src/auth/login.py"),
+            vec!["src", "auth", "login", "py"]
+        );
+        assert_eq!(name_key("handle auth"), name_key("HandleAuth"));
+    }
+
+    #[test]
+    fn test_identifier_spellings_find_the_same_node() {
+        let mut index = SearchIndex::new();
+        let node = Node::new("handleAuth".to_string(), NodeType::Function);
+        index.insert(&node);
+        index.insert(&Node::new(
+            "handleAuthCallback".to_string(),
+            NodeType::Function,
+        ));
+
+        for query in ["handle auth", "HandleAuth", "handle_auth", "handleauth"] {
+            let ranked = index.search(query, SearchMode::Token);
+            assert_eq!(ranked[0].0, node.id, "query {:?}", query);
+        }
+
+        let exact = index.search("handle_auth", SearchMode::Exact);
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].0, node.id);
+    }
+
+    #[test]
+    fn test_prefix_and_fuzzy_modes() {
+        let mut index = SearchIndex::new();
+        let node = Node::new("handleAuth".to_string(), NodeType::Function);
+        index.insert(&node);
+
+        assert_eq!(index.search("hndleAuth", SearchMode::Token).len(), 1);
+        assert!(index.search("hndle", SearchMode::Token).is_empty());
+        assert_eq!(index.search("hndle", SearchMode::Fuzzy).len(), 1);
+        assert_eq!(index.search("hadnle", SearchMode::Fuzzy).len(), 1);
+        assert!(index.search("hand", SearchMode::Token).is_empty());
+        assert_eq!(index.search("hand", SearchMode::Prefix).len(), 1);
+        // Three-letter tokens must match exactly even in fuzzy mode.
+        assert!(index.search("aut", SearchMode::Fuzzy).is_empty());
+        // Only terms within the edit budget in length are compared.
+        assert_eq!(index.search("handleauthx", SearchMode::Fuzzy).len(), 1);
+        assert!(index.search("handleauthxyz", SearchMode::Fuzzy).is_empty());
+
+        index.remove(&node.id);
+        assert!(index.terms_by_length.is_empty());
+        assert!(index.search("hndle", SearchMode::Fuzzy).is_empty());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let id = Uuid::new_v4();
+        let cursor = encode_cursor(1.25, &id);
+        assert_eq!(decode_cursor(&cursor), Some((1.25, id)));
+        assert!(decode_cursor("garbage").is_none());
+        assert!(cursor_error(Some(&cursor)).is_none() && cursor_error(None).is_none());
+        assert!(cursor_error(Some("garbage")).is_some());
+    }
+}
@@ -0,0 +1,252 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::models::{Direction, Edge, EdgeType, Node};
+
+use super::{GraphStore, MemoryStore, Mutation, StoreError};
+
+/// Embedded store backed by an append-only log of JSON mutations.
+///
+/// The log is replayed into memory on open and then compacted to one
+/// `put_*` record per live node and edge, so it only grows with churn
+/// since the last restart. A mutation reaches memory only once its record
+/// has been written.
+pub struct FileStore {
+    memory: MemoryStore,
+    path: PathBuf,
+    file: File,
+    /// A failed append may have left part of a record; the next one starts
+    /// on a fresh line so only the torn record is skipped on replay.
+    torn: bool,
+}
+
+impl FileStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut memory = MemoryStore::new();
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for (line_no, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Mutation>(&line) {
+                    Ok(mutation) => mutation.apply(&mut memory),
+                    // A crash mid-append leaves a torn final record; skip it.
+                    Err(err) => tracing::warn!(
+                        "Skipping unreadable record at {}:{}: {}",
+                        path.display(),
+                        line_no + 1,
+                        err
+                    ),
+                }
+            }
+        }
+
+        compact(&path, &memory)?;
+        let file = OpenOptions::new().append(true).open(&path)?;
+
+        Ok(Self {
+            memory,
+            path,
+            file,
+            torn: false,
+        })
+    }
+
+    fn append(&mut self, mutation: &Mutation) -> Result<(), StoreError> {
+        let mut record = if self.torn { vec![b'\n'] } else { Vec::new() };
+        let result = serde_json::to_writer(&mut record, mutation)
+            .map_err(io::Error::from)
+            .and_then(|_| {
+                record.push(b'\n');
+                self.file.write_all(&record)
+            });
+        self.torn = result.is_err();
+        result.map_err(|source| StoreError::Append {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+fn compact(path: &Path, memory: &MemoryStore) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("compact");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        for node in memory.nodes() {
+            serde_json::to_writer(&mut writer, &Mutation::PutNode { node: node.clone() })?;
+            writer.write_all(b"\n")?;
+        }
+        for edge in memory.edges() {
+            serde_json::to_writer(&mut writer, &Mutation::PutEdge { edge: edge.clone() })?;
+            writer.write_all(b"\n")?;
+        }
+        writer.into_inner()?.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+impl GraphStore for FileStore {
+    fn insert_node(&mut self, node: Node) -> Result<(), StoreError> {
+        self.append(&Mutation::PutNode { node: node.clone() })?;
+        self.memory.put_node(node);
+        Ok(())
+    }
+
+    fn get_node(&self, id: &Uuid) -> Option<&Node> {
+        self.memory.get_node(id)
+    }
+
+    fn remove_node(&mut self, id: &Uuid) -> Result<Option<Node>, StoreError> {
+        if self.memory.get_node(id).is_none() {
+            return Ok(None);
+        }
+        self.append(&Mutation::RemoveNode { id: *id })?;
+        Ok(self.memory.take_node(id))
+    }
+
+    fn nodes(&self) -> Box<dyn Iterator<Item = &Node> + '_> {
+        self.memory.nodes()
+    }
+
+    fn node_count(&self) -> usize {
+        self.memory.node_count()
+    }
+
+    fn insert_edge(&mut self, edge: Edge) -> Result<(), StoreError> {
+        self.append(&Mutation::PutEdge { edge: edge.clone() })?;
+        self.memory.put_edge(edge);
+        Ok(())
+    }
+
+    fn get_edge(&self, id: &Uuid) -> Option<&Edge> {
+        self.memory.get_edge(id)
+    }
+
+    fn remove_edge(&mut self, id: &Uuid) -> Result<Option<Edge>, StoreError> {
+        if self.memory.get_edge(id).is_none() {
+            return Ok(None);
+        }
+        self.append(&Mutation::RemoveEdge { id: *id })?;
+        Ok(self.memory.take_edge(id))
+    }
+
+    fn edges(&self) -> Box<dyn Iterator<Item = &Edge> + '_> {
+        self.memory.edges()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.memory.edge_count()
+    }
+
+    fn edges_for_node(
+        &self,
+        node_id: &Uuid,
+        direction: &Direction,
+        edge_types: Option<&[EdgeType]>,
+    ) -> Vec<&Edge> {
+        self.memory.edges_for_node(node_id, direction, edge_types)
+    }
+
+    fn degree(&self, node_id: &Uuid) -> usize {
+        self.memory.degree(node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EdgeType, NodeType};
+
+    fn temp_log(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("kg-{}-{}.log", name, Uuid::new_v4()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_reopen_restores_nodes_and_edges() {
+        let path = temp_log("reopen");
+        let node1 = Node::new("A".to_string(), NodeType::Function);
+        let node2 = Node::new("B".to_string(), NodeType::Function);
+        let node3 = Node::new("C".to_string(), NodeType::Function);
+        let edge = Edge::new(node1.id, node2.id, EdgeType::Calls);
+        let dropped = Edge::new(node2.id, node3.id, EdgeType::Calls);
+
+        {
+            let mut store = FileStore::open(&path).unwrap();
+            store.insert_node(node1.clone()).unwrap();
+            store.insert_node(node2.clone()).unwrap();
+            store.insert_node(node3.clone()).unwrap();
+            store.insert_edge(edge.clone()).unwrap();
+            store.insert_edge(dropped.clone()).unwrap();
+            store.remove_edge(&dropped.id).unwrap();
+            store.remove_node(&node3.id).unwrap();
+        }
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.node_count(), 2);
+        assert_eq!(store.edge_count(), 1);
+        assert_eq!(store.get_edge(&edge.id).unwrap().target_id, node2.id);
+        assert!(store.get_node(&node3.id).is_none());
+
+        // Compaction leaves one record per live entity.
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 3);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_torn_final_record_is_skipped() {
+        let path = temp_log("torn");
+        let node = Node::new("A".to_string(), NodeType::File);
+        {
+            let mut store = FileStore::open(&path).unwrap();
+            store.insert_node(node.clone()).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"op\":\"put_node\",\"node\":{\"id\"")
+            .unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.node_count(), 1);
+        assert!(store.get_node(&node.id).is_some());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_append_is_reported_and_not_applied() {
+        let path = temp_log("failed");
+        let kept = Node::new("A".to_string(), NodeType::File);
+        let lost = Node::new("B".to_string(), NodeType::File);
+        let mut store = FileStore::open(&path).unwrap();
+        store.insert_node(kept.clone()).unwrap();
+
+        // A read-only handle makes every write fail.
+        let append = std::mem::replace(&mut store.file, File::open(&path).unwrap());
+        assert!(matches!(
+            store.insert_node(lost.clone()),
+            Err(StoreError::Append { .. })
+        ));
+        assert!(store.remove_node(&kept.id).is_err());
+        assert!(store.get_node(&lost.id).is_none());
+        assert!(store.get_node(&kept.id).is_some());
+
+        store.file = append;
+        store.insert_node(lost.clone()).unwrap();
+        drop(store);
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.node_count(), 2);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::models::{Direction, Edge, EdgeType, Node};
+
+use super::{GraphStore, StoreError};
+
+/// Edge ids per node, partitioned by `EdgeType`.
+type AdjacencyIndex = HashMap<Uuid, HashMap<EdgeType, HashSet<Uuid>>>;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    nodes: HashMap<Uuid, Node>,
+    edges: HashMap<Uuid, Edge>,
+    outgoing: AdjacencyIndex,
+    incoming: AdjacencyIndex,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Infallible versions of the `GraphStore` mutations, for the backends
+    // that keep their working copy in a `MemoryStore`.
+    pub fn put_node(&mut self, node: Node) {
+        self.nodes.insert(node.id, node);
+    }
+
+    pub fn take_node(&mut self, id: &Uuid) -> Option<Node> {
+        self.nodes.remove(id)
+    }
+
+    pub fn put_edge(&mut self, edge: Edge) {
+        if let Some(old) = self.edges.remove(&edge.id) {
+            self.unindex_edge(&old);
+        }
+        self.index_edge(&edge);
+        self.edges.insert(edge.id, edge);
+    }
+
+    pub fn take_edge(&mut self, id: &Uuid) -> Option<Edge> {
+        let edge = self.edges.remove(id)?;
+        self.unindex_edge(&edge);
+        Some(edge)
+    }
+
+    fn index_edge(&mut self, edge: &Edge) {
+        for (index, node_id) in [
+            (&mut self.outgoing, edge.source_id),
+            (&mut self.incoming, edge.target_id),
+        ] {
+            index
+                .entry(node_id)
+                .or_default()
+                .entry(edge.edge_type.clone())
+                .or_default()
+                .insert(edge.id);
+        }
+    }
+
+    fn unindex_edge(&mut self, edge: &Edge) {
+        for (index, node_id) in [
+            (&mut self.outgoing, edge.source_id),
+            (&mut self.incoming, edge.target_id),
+        ] {
+            if let Some(by_type) = index.get_mut(&node_id) {
+                if let Some(ids) = by_type.get_mut(&edge.edge_type) {
+                    ids.remove(&edge.id);
+                    if ids.is_empty() {
+                        by_type.remove(&edge.edge_type);
+                    }
+                }
+                if by_type.is_empty() {
+                    index.remove(&node_id);
+                }
+            }
+        }
+    }
+
+    fn collect_from<'a>(
+        &'a self,
+        index: &AdjacencyIndex,
+        node_id: &Uuid,
+        edge_types: Option<&[EdgeType]>,
+        out: &mut Vec<&'a Edge>,
+    ) {
+        let Some(by_type) = index.get(node_id) else {
+            return;
+        };
+        let ids = by_type
+            .iter()
+            .filter(|(edge_type, _)| edge_types.is_none_or(|types| types.contains(edge_type)))
+            .flat_map(|(_, ids)| ids);
+        out.extend(ids.filter_map(|id| self.edges.get(id)));
+    }
+}
+
+impl GraphStore for MemoryStore {
+    fn insert_node(&mut self, node: Node) -> Result<(), StoreError> {
+        self.put_node(node);
+        Ok(())
+    }
+
+    fn get_node(&self, id: &Uuid) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    fn remove_node(&mut self, id: &Uuid) -> Result<Option<Node>, StoreError> {
+        Ok(self.take_node(id))
+    }
+
+    fn nodes(&self) -> Box<dyn Iterator<Item = &Node> + '_> {
+        Box::new(self.nodes.values())
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn insert_edge(&mut self, edge: Edge) -> Result<(), StoreError> {
+        self.put_edge(edge);
+        Ok(())
+    }
+
+    fn get_edge(&self, id: &Uuid) -> Option<&Edge> {
+        self.edges.get(id)
+    }
+
+    fn remove_edge(&mut self, id: &Uuid) -> Result<Option<Edge>, StoreError> {
+        Ok(self.take_edge(id))
+    }
+
+    fn edges(&self) -> Box<dyn Iterator<Item = &Edge> + '_> {
+        Box::new(self.edges.values())
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn edges_for_node(
+        &self,
+        node_id: &Uuid,
+        direction: &Direction,
+        edge_types: Option<&[EdgeType]>,
+    ) -> Vec<&Edge> {
+        let mut result = Vec::new();
+        if matches!(direction, Direction::Outgoing | Direction::Both) {
+            self.collect_from(&self.outgoing, node_id, edge_types, &mut result);
+        }
+        if matches!(direction, Direction::Incoming | Direction::Both) {
+            self.collect_from(&self.incoming, node_id, edge_types, &mut result);
+        }
+        if matches!(direction, Direction::Both) {
+            // Self-loops appear in both indexes.
+            let mut seen = HashSet::new();
+            result.retain(|e| seen.insert(e.id));
+        }
+        result
+    }
+
+    fn degree(&self, node_id: &Uuid) -> usize {
+        let count = |index: &AdjacencyIndex| {
+            index
+                .get(node_id)
+                .map_or(0, |by_type| by_type.values().map(HashSet::len).sum())
+        };
+        let self_loops = self.outgoing.get(node_id).map_or(0, |by_type| {
+            by_type
+                .values()
+                .flatten()
+                .filter(|id| self.edges.get(id).is_some_and(|e| e.target_id == *node_id))
+                .count()
+        });
+        count(&self.outgoing) + count(&self.incoming) - self_loops
+    }
+}
+
+/// Memory store whose `fail_at`-th write (counting from 1) fails, for
+/// testing writes that fail part way.
+#[cfg(test)]
+pub struct FailingStore {
+    memory: MemoryStore,
+    writes: usize,
+    fail_at: usize,
+}
+
+#[cfg(test)]
+impl FailingStore {
+    pub fn new(memory: MemoryStore, fail_at: usize) -> Self {
+        Self {
+            memory,
+            writes: 0,
+            fail_at,
+        }
+    }
+
+    fn write(&mut self) -> Result<(), StoreError> {
+        self.writes += 1;
+        if self.writes == self.fail_at {
+            return Err(StoreError::Persist("injected failure".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl GraphStore for FailingStore {
+    fn insert_node(&mut self, node: Node) -> Result<(), StoreError> {
+        self.write()?;
+        self.memory.insert_node(node)
+    }
+
+    fn get_node(&self, id: &Uuid) -> Option<&Node> {
+        self.memory.get_node(id)
+    }
+
+    fn remove_node(&mut self, id: &Uuid) -> Result<Option<Node>, StoreError> {
+        self.write()?;
+        self.memory.remove_node(id)
+    }
+
+    fn nodes(&self) -> Box<dyn Iterator<Item = &Node> + '_> {
+        self.memory.nodes()
+    }
+
+    fn node_count(&self) -> usize {
+        self.memory.node_count()
+    }
+
+    fn insert_edge(&mut self, edge: Edge) -> Result<(), StoreError> {
+        self.write()?;
+        self.memory.insert_edge(edge)
+    }
+
+    fn get_edge(&self, id: &Uuid) -> Option<&Edge> {
+        self.memory.get_edge(id)
+    }
+
+    fn remove_edge(&mut self, id: &Uuid) -> Result<Option<Edge>, StoreError> {
+        self.write()?;
+        self.memory.remove_edge(id)
+    }
+
+    fn edges(&self) -> Box<dyn Iterator<Item = &Edge> + '_> {
+        self.memory.edges()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.memory.edge_count()
+    }
+
+    fn edges_for_node(
+        &self,
+        node_id: &Uuid,
+        direction: &Direction,
+        edge_types: Option<&[EdgeType]>,
+    ) -> Vec<&Edge> {
+        self.memory.edges_for_node(node_id, direction, edge_types)
+    }
+
+    fn degree(&self, node_id: &Uuid) -> usize {
+        self.memory.degree(node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NodeType;
+
+    #[test]
+    fn test_adjacency_follows_edge_updates() {
+        let mut store = MemoryStore::new();
+        let a = Node::new("A".to_string(), NodeType::Function);
+        let b = Node::new("B".to_string(), NodeType::Function);
+        let (a_id, b_id) = (a.id, b.id);
+        store.put_node(a);
+        store.put_node(b);
+
+        let mut edge = Edge::new(a_id, b_id, EdgeType::Calls);
+        store.put_edge(edge.clone());
+        store.put_edge(Edge::new(a_id, a_id, EdgeType::References));
+
+        let calls = store.edges_for_node(&a_id, &Direction::Outgoing, Some(&[EdgeType::Calls]));
+        assert_eq!(calls.len(), 1);
+        assert_eq!(store.edges_for_node(&a_id, &Direction::Both, None).len(), 2);
+        assert_eq!(store.degree(&a_id), 2);
+        assert_eq!(store.degree(&b_id), 1);
+
+        // Changing the type must move the edge to the new partition.
+        edge.edge_type = EdgeType::Imports;
+        store.put_edge(edge.clone());
+        assert!(store
+            .edges_for_node(&b_id, &Direction::Incoming, Some(&[EdgeType::Calls]))
+            .is_empty());
+        assert_eq!(
+            store
+                .edges_for_node(&b_id, &Direction::Incoming, Some(&[EdgeType::Imports]))
+                .len(),
+            1
+        );
+
+        store.take_edge(&edge.id);
+        assert_eq!(store.degree(&b_id), 0);
+        assert!(!store.incoming.contains_key(&b_id));
+    }
+}
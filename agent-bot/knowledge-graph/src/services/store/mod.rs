@@ -0,0 +1,218 @@
+mod file;
+mod memory;
+mod postgres;
+
+pub use file::FileStore;
+#[cfg(test)]
+pub use memory::FailingStore;
+pub use memory::MemoryStore;
+pub use postgres::{PendingWrites, PostgresStore};
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::models::{Direction, Edge, EdgeType, Node};
+
+/// A mutation that could not be recorded. The in-memory copy is left as it
+/// was, except for `Persist`: it reports a background write that failed
+/// after its mutation had been accepted.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Failed to append to {}: {source}", path.display())]
+    Append {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to persist to PostgreSQL: {0}")]
+    Persist(String),
+    #[error("PostgreSQL writer has stopped")]
+    WriterStopped,
+}
+
+/// Record storage behind `KnowledgeGraph`.
+///
+/// Reads are served from memory by every backend; persistent backends
+/// additionally record each mutation so the graph can be rebuilt on startup.
+pub trait GraphStore: Send + Sync {
+    fn insert_node(&mut self, node: Node) -> Result<(), StoreError>;
+    fn get_node(&self, id: &Uuid) -> Option<&Node>;
+    fn remove_node(&mut self, id: &Uuid) -> Result<Option<Node>, StoreError>;
+    fn nodes(&self) -> Box<dyn Iterator<Item = &Node> + '_>;
+    fn node_count(&self) -> usize;
+
+    fn insert_edge(&mut self, edge: Edge) -> Result<(), StoreError>;
+    fn get_edge(&self, id: &Uuid) -> Option<&Edge>;
+    fn remove_edge(&mut self, id: &Uuid) -> Result<Option<Edge>, StoreError>;
+    fn edges(&self) -> Box<dyn Iterator<Item = &Edge> + '_>;
+    fn edge_count(&self) -> usize;
+
+    /// Edges touching `node_id` in the given direction, optionally restricted
+    /// to some edge types. Served from an adjacency index, not an edge scan.
+    fn edges_for_node(
+        &self,
+        node_id: &Uuid,
+        direction: &Direction,
+        edge_types: Option<&[EdgeType]>,
+    ) -> Vec<&Edge>;
+
+    /// Number of distinct edges touching `node_id`.
+    fn degree(&self, node_id: &Uuid) -> usize;
+
+    /// Writes accepted but not yet persisted, for backends that persist in
+    /// the background.
+    fn pending_writes(&self) -> Option<PendingWrites> {
+        None
+    }
+}
+
+/// A single change to the stored graph, as written to persistent backends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Mutation {
+    PutNode { node: Node },
+    RemoveNode { id: Uuid },
+    PutEdge { edge: Edge },
+    RemoveEdge { id: Uuid },
+}
+
+impl Mutation {
+    fn apply(self, store: &mut MemoryStore) {
+        match self {
+            Mutation::PutNode { node } => store.put_node(node),
+            Mutation::RemoveNode { id } => {
+                store.take_node(&id);
+            }
+            Mutation::PutEdge { edge } => store.put_edge(edge),
+            Mutation::RemoveEdge { id } => {
+                store.take_edge(&id);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    Memory,
+    /// One log per graph under `dir`, e.g. `acme.log` or `acme/billing.log`.
+    File {
+        dir: PathBuf,
+    },
+    Postgres {
+        database_url: String,
+    },
+}
+
+impl StorageConfig {
+    /// Reads `STORAGE_BACKEND` (`memory`, `file` or `postgres`).
+    ///
+    /// When unset, `postgres` is used if `DATABASE_URL` is present and
+    /// `memory` otherwise.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let database_url = std::env::var("DATABASE_URL").ok();
+        let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| {
+            if database_url.is_some() {
+                "postgres".to_string()
+            } else {
+                "memory".to_string()
+            }
+        });
+
+        match backend.as_str() {
+            "memory" => Ok(Self::Memory),
+            "file" => {
+                let path = std::env::var("STORAGE_PATH")
+                    .unwrap_or_else(|_| "/data/knowledge-graph".to_string());
+                // A single log path from before graphs were per tenant
+                // becomes the directory next to it.
+                let dir = PathBuf::from(path.strip_suffix(".log").unwrap_or(&path));
+                Ok(Self::File { dir })
+            }
+            "postgres" => Ok(Self::Postgres {
+                database_url: database_url.ok_or_else(|| {
+                    anyhow::anyhow!("STORAGE_BACKEND=postgres requires DATABASE_URL")
+                })?,
+            }),
+            other => anyhow::bail!("Unknown STORAGE_BACKEND: {}", other),
+        }
+    }
+
+    /// Connects to the backend; graphs are opened from the result by name.
+    pub async fn open(&self) -> anyhow::Result<Storage> {
+        Ok(match self {
+            Self::Memory => Storage::Memory,
+            Self::File { dir } => Storage::File { dir: dir.clone() },
+            Self::Postgres { database_url } => Storage::Postgres {
+                pool: PostgresStore::connect(database_url).await?,
+            },
+        })
+    }
+}
+
+/// A connected backend holding any number of named graphs.
+pub enum Storage {
+    Memory,
+    File { dir: PathBuf },
+    Postgres { pool: PgPool },
+}
+
+impl Storage {
+    /// Opens the graph called `name`, which may contain one `/`.
+    pub async fn open_graph(&self, name: &str) -> anyhow::Result<Box<dyn GraphStore>> {
+        Ok(match self {
+            Self::Memory => Box::new(MemoryStore::new()),
+            Self::File { dir } => Box::new(FileStore::open(dir.join(format!("{}.log", name)))?),
+            Self::Postgres { pool } => Box::new(PostgresStore::open(pool, name).await?),
+        })
+    }
+
+    /// Names of the graphs with stored records. Memory keeps none.
+    pub async fn graph_names(&self) -> anyhow::Result<Vec<String>> {
+        match self {
+            Self::Memory => Ok(Vec::new()),
+            Self::File { dir } => {
+                let mut names: Vec<String> = WalkDir::new(dir)
+                    .max_depth(2)
+                    .into_iter()
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_file())
+                    .filter_map(|entry| {
+                        let relative = entry.path().strip_prefix(dir).ok()?.to_str()?;
+                        relative.strip_suffix(".log").map(str::to_string)
+                    })
+                    .collect();
+                names.sort();
+                Ok(names)
+            }
+            Self::Postgres { pool, .. } => PostgresStore::graphs(pool).await,
+        }
+    }
+
+    /// Whether graph `name` has stored records.
+    pub async fn contains(&self, name: &str) -> anyhow::Result<bool> {
+        match self {
+            Self::Memory => Ok(false),
+            Self::File { dir } => Ok(dir.join(format!("{}.log", name)).is_file()),
+            Self::Postgres { pool, .. } => PostgresStore::contains(pool, name).await,
+        }
+    }
+
+    /// Deletes every stored record of graph `name`. Its store must no longer
+    /// be writing.
+    pub async fn remove_graph(&self, name: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Memory => Ok(()),
+            Self::File { dir } => {
+                let path = dir.join(format!("{}.log", name));
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(())
+            }
+            Self::Postgres { pool, .. } => PostgresStore::remove(pool, name).await,
+        }
+    }
+}
@@ -0,0 +1,561 @@
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::Row;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::models::{Direction, Edge, EdgeType, Node};
+
+use super::{GraphStore, MemoryStore, Mutation, StoreError};
+
+/// How far a graph's writer has got.
+#[derive(Debug, Default)]
+struct Progress {
+    /// Mutations written so far, in queue order.
+    written: u64,
+    /// The write that stopped the writer, once one has failed.
+    failed: Option<String>,
+}
+
+/// Store that serves reads from memory and mirrors every mutation to
+/// PostgreSQL.
+///
+/// Writes are queued and applied in order by a background task, so request
+/// handlers never wait on the database while holding the graph lock; they
+/// wait on `pending_writes` once the lock is released. The queue itself is
+/// unbounded, so a batch of any size is accepted; waiting for the writer
+/// between batches is what keeps it short. Every row carries the
+/// name of the graph it belongs to, so one database holds any number of
+/// graphs.
+///
+/// A failed write stops the writer, since later mutations may depend on it,
+/// and every write after it is refused until the graph is reloaded.
+pub struct PostgresStore {
+    memory: MemoryStore,
+    sender: mpsc::UnboundedSender<Mutation>,
+    /// Mutations handed to the writer so far.
+    queued: u64,
+    progress: watch::Receiver<Progress>,
+}
+
+/// The writes a store had queued when it was asked; awaited once the graph
+/// lock is released.
+pub struct PendingWrites {
+    queued: u64,
+    progress: watch::Receiver<Progress>,
+}
+
+impl PostgresStore {
+    /// Connects and runs migrations. The pool is shared by every graph.
+    pub async fn connect(database_url: &str) -> anyhow::Result<PgPool> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(pool)
+    }
+
+    /// Loads every stored node and edge of `graph`.
+    pub async fn open(pool: &PgPool, graph: &str) -> anyhow::Result<Self> {
+        let memory = load(pool, graph).await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (progress_tx, progress) = watch::channel(Progress::default());
+        tokio::spawn(write_loop(
+            pool.clone(),
+            graph.to_string(),
+            receiver,
+            progress_tx,
+        ));
+
+        Ok(Self {
+            memory,
+            sender,
+            queued: 0,
+            progress,
+        })
+    }
+
+    /// Names of the graphs with stored nodes.
+    pub async fn graphs(pool: &PgPool) -> anyhow::Result<Vec<String>> {
+        let rows = sqlx::query("SELECT DISTINCT graph FROM kg_nodes ORDER BY graph")
+            .fetch_all(pool)
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get("graph"))
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Deletes every row of `graph` in one transaction.
+    pub async fn remove(pool: &PgPool, graph: &str) -> anyhow::Result<()> {
+        let mut transaction = pool.begin().await?;
+        for table in ["kg_edges", "kg_nodes"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE graph = $1", table))
+                .bind(graph)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn contains(pool: &PgPool, graph: &str) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT EXISTS (SELECT 1 FROM kg_nodes WHERE graph = $1) AS found")
+            .bind(graph)
+            .fetch_one(pool)
+            .await?;
+        Ok(row.try_get("found")?)
+    }
+
+    fn enqueue(&mut self, mutation: Mutation) -> Result<(), StoreError> {
+        if let Some(error) = &self.progress.borrow().failed {
+            return Err(StoreError::Persist(error.clone()));
+        }
+        self.sender
+            .send(mutation)
+            .map_err(|_| StoreError::WriterStopped)?;
+        self.queued += 1;
+        Ok(())
+    }
+}
+
+async fn load(pool: &PgPool, graph: &str) -> Result<MemoryStore, sqlx::Error> {
+    let mut memory = MemoryStore::new();
+
+    let node_rows = sqlx::query(
+        "SELECT id, name, node_type, path, language, description, metadata, embedding, \
+         created_at, updated_at FROM kg_nodes WHERE graph = $1",
+    )
+    .bind(graph)
+    .fetch_all(pool)
+    .await?;
+    for row in node_rows {
+        memory.put_node(node_from_row(&row)?);
+    }
+
+    let edge_rows = sqlx::query(
+        "SELECT id, source_id, target_id, edge_type, weight, metadata, created_at FROM kg_edges \
+         WHERE graph = $1",
+    )
+    .bind(graph)
+    .fetch_all(pool)
+    .await?;
+    for row in edge_rows {
+        memory.put_edge(edge_from_row(&row)?);
+    }
+
+    Ok(memory)
+}
+
+async fn write_loop(
+    pool: PgPool,
+    graph: String,
+    mut receiver: mpsc::UnboundedReceiver<Mutation>,
+    progress: watch::Sender<Progress>,
+) {
+    while let Some(mutation) = receiver.recv().await {
+        if let Err(err) = write(&pool, &graph, &mutation).await {
+            tracing::error!(
+                "Failed to persist {:?} to graph {}: {}",
+                mutation,
+                graph,
+                err
+            );
+            progress.send_modify(|progress| progress.failed = Some(err.to_string()));
+            return;
+        }
+        progress.send_modify(|progress| progress.written += 1);
+    }
+}
+
+async fn write(pool: &PgPool, graph: &str, mutation: &Mutation) -> Result<(), sqlx::Error> {
+    match mutation {
+        Mutation::PutNode { node } => {
+            sqlx::query(
+                "INSERT INTO kg_nodes (graph, id, name, node_type, path, language, description, metadata, embedding, created_at, updated_at) \
+                 VALUES ($11, $1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+                 ON CONFLICT (graph, id) DO UPDATE SET \
+                    name = EXCLUDED.name, node_type = EXCLUDED.node_type, path = EXCLUDED.path, \
+                    language = EXCLUDED.language, description = EXCLUDED.description, \
+                    metadata = EXCLUDED.metadata, embedding = EXCLUDED.embedding, \
+                    updated_at = EXCLUDED.updated_at",
+            )
+            .bind(node.id)
+            .bind(&node.name)
+            .bind(enum_to_text(&node.node_type))
+            .bind(&node.path)
+            .bind(&node.language)
+            .bind(&node.description)
+            .bind(&node.metadata)
+            .bind(&node.embedding)
+            .bind(node.created_at)
+            .bind(node.updated_at)
+            .bind(graph)
+            .execute(pool)
+            .await?;
+        }
+        Mutation::RemoveNode { id } => {
+            sqlx::query("DELETE FROM kg_nodes WHERE graph = $2 AND id = $1")
+                .bind(id)
+                .bind(graph)
+                .execute(pool)
+                .await?;
+        }
+        Mutation::PutEdge { edge } => {
+            sqlx::query(
+                "INSERT INTO kg_edges (graph, id, source_id, target_id, edge_type, weight, metadata, created_at) \
+                 VALUES ($8, $1, $2, $3, $4, $5, $6, $7) \
+                 ON CONFLICT (graph, id) DO UPDATE SET \
+                    source_id = EXCLUDED.source_id, target_id = EXCLUDED.target_id, \
+                    edge_type = EXCLUDED.edge_type, weight = EXCLUDED.weight, metadata = EXCLUDED.metadata",
+            )
+            .bind(edge.id)
+            .bind(edge.source_id)
+            .bind(edge.target_id)
+            .bind(enum_to_text(&edge.edge_type))
+            .bind(edge.weight)
+            .bind(&edge.metadata)
+            .bind(edge.created_at)
+            .bind(graph)
+            .execute(pool)
+            .await?;
+        }
+        Mutation::RemoveEdge { id } => {
+            sqlx::query("DELETE FROM kg_edges WHERE graph = $2 AND id = $1")
+                .bind(id)
+                .bind(graph)
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+impl GraphStore for PostgresStore {
+    fn insert_node(&mut self, node: Node) -> Result<(), StoreError> {
+        self.enqueue(Mutation::PutNode { node: node.clone() })?;
+        self.memory.put_node(node);
+        Ok(())
+    }
+
+    fn get_node(&self, id: &Uuid) -> Option<&Node> {
+        self.memory.get_node(id)
+    }
+
+    fn remove_node(&mut self, id: &Uuid) -> Result<Option<Node>, StoreError> {
+        if self.memory.get_node(id).is_none() {
+            return Ok(None);
+        }
+        self.enqueue(Mutation::RemoveNode { id: *id })?;
+        Ok(self.memory.take_node(id))
+    }
+
+    fn nodes(&self) -> Box<dyn Iterator<Item = &Node> + '_> {
+        self.memory.nodes()
+    }
+
+    fn node_count(&self) -> usize {
+        self.memory.node_count()
+    }
+
+    fn insert_edge(&mut self, edge: Edge) -> Result<(), StoreError> {
+        self.enqueue(Mutation::PutEdge { edge: edge.clone() })?;
+        self.memory.put_edge(edge);
+        Ok(())
+    }
+
+    fn get_edge(&self, id: &Uuid) -> Option<&Edge> {
+        self.memory.get_edge(id)
+    }
+
+    fn remove_edge(&mut self, id: &Uuid) -> Result<Option<Edge>, StoreError> {
+        if self.memory.get_edge(id).is_none() {
+            return Ok(None);
+        }
+        self.enqueue(Mutation::RemoveEdge { id: *id })?;
+        Ok(self.memory.take_edge(id))
+    }
+
+    fn edges(&self) -> Box<dyn Iterator<Item = &Edge> + '_> {
+        self.memory.edges()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.memory.edge_count()
+    }
+
+    fn edges_for_node(
+        &self,
+        node_id: &Uuid,
+        direction: &Direction,
+        edge_types: Option<&[EdgeType]>,
+    ) -> Vec<&Edge> {
+        self.memory.edges_for_node(node_id, direction, edge_types)
+    }
+
+    fn degree(&self, node_id: &Uuid) -> usize {
+        self.memory.degree(node_id)
+    }
+
+    fn pending_writes(&self) -> Option<PendingWrites> {
+        Some(PendingWrites {
+            queued: self.queued,
+            progress: self.progress.clone(),
+        })
+    }
+}
+
+impl PendingWrites {
+    /// Waits until the writes have reached the database, or fails with the
+    /// write that stopped the writer before they did.
+    pub async fn wait(mut self) -> Result<(), StoreError> {
+        let queued = self.queued;
+        let progress = self
+            .progress
+            .wait_for(|progress| progress.written >= queued || progress.failed.is_some())
+            .await
+            .map_err(|_| StoreError::WriterStopped)?;
+        match &progress.failed {
+            Some(error) if progress.written < queued => Err(StoreError::Persist(error.clone())),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn node_from_row(row: &PgRow) -> Result<Node, sqlx::Error> {
+    Ok(Node {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        node_type: enum_from_text(row.try_get("node_type")?)?,
+        path: row.try_get("path")?,
+        language: row.try_get("language")?,
+        description: row.try_get("description")?,
+        metadata: row.try_get("metadata")?,
+        embedding: row.try_get("embedding")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn edge_from_row(row: &PgRow) -> Result<Edge, sqlx::Error> {
+    Ok(Edge {
+        id: row.try_get("id")?,
+        source_id: row.try_get("source_id")?,
+        target_id: row.try_get("target_id")?,
+        edge_type: enum_from_text(row.try_get("edge_type")?)?,
+        weight: row.try_get("weight")?,
+        metadata: row.try_get("metadata")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn enum_to_text<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => String::new(),
+    }
+}
+
+fn enum_from_text<T: DeserializeOwned>(text: String) -> Result<T, sqlx::Error> {
+    serde_json::from_value(serde_json::Value::String(text))
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BulkUpsertRequest, EdgeType, NodeKey, NodeType, UpsertNode, UpsertStatus};
+    use crate::services::graph::KnowledgeGraph;
+
+    // Tests share one database, so they take this lock before truncating it.
+    static DB_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    // These tests need a real database, named by KG_TEST_DATABASE_URL, e.g.
+    // postgres://postgres@localhost:5432/kg_test. They are ignored by default;
+    // run them with `cargo test -- --ignored`.
+    async fn test_store() -> (tokio::sync::MutexGuard<'static, ()>, PgPool) {
+        let url = std::env::var("KG_TEST_DATABASE_URL")
+            .expect("KG_TEST_DATABASE_URL must name a test database");
+        let guard = DB_LOCK.lock().await;
+        let pool = PostgresStore::connect(&url).await.unwrap();
+        sqlx::query("TRUNCATE kg_edges, kg_nodes")
+            .execute(&pool)
+            .await
+            .unwrap();
+        (guard, pool)
+    }
+
+    async fn open(pool: &PgPool, graph: &str) -> PostgresStore {
+        PostgresStore::open(pool, graph).await.unwrap()
+    }
+
+    async fn flush(store: &PostgresStore) -> Result<(), StoreError> {
+        store.pending_writes().unwrap().wait().await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs KG_TEST_DATABASE_URL"]
+    async fn test_graph_survives_reload() {
+        let (_guard, pool) = test_store().await;
+
+        let node1 = Node::new("handleAuth".to_string(), NodeType::Function)
+            .with_path("src/auth.ts".to_string())
+            .with_metadata(serde_json::json!({ "line": 10 }))
+            .with_embedding(vec![0.5, -1.0, 2.0]);
+        let node2 = Node::new("processPayment".to_string(), NodeType::Function);
+        let node3 = Node::new("orphan".to_string(), NodeType::Variable);
+        let edge = Edge::new(node1.id, node2.id, EdgeType::Calls).with_weight(2.5);
+
+        let mut store = open(&pool, "default").await;
+        store.insert_node(node1.clone()).unwrap();
+        store.insert_node(node2.clone()).unwrap();
+        store.insert_node(node3.clone()).unwrap();
+        store.insert_edge(edge.clone()).unwrap();
+        store.remove_node(&node3.id).unwrap();
+        flush(&store).await.unwrap();
+
+        let reopened = open(&pool, "default").await;
+        let graph = KnowledgeGraph::with_store(Box::new(reopened));
+        assert_eq!(graph.list_nodes().len(), 2);
+        assert!(graph.get_node(&node3.id).is_none());
+
+        let loaded = graph.get_node(&node1.id).unwrap();
+        assert_eq!(loaded.path.as_deref(), Some("src/auth.ts"));
+        assert_eq!(loaded.metadata["line"], 10);
+        assert_eq!(loaded.embedding, Some(vec![0.5, -1.0, 2.0]));
+        assert!(graph.get_node(&node2.id).unwrap().embedding.is_none());
+
+        let loaded_edge = graph.get_edge(&edge.id).unwrap();
+        assert_eq!(loaded_edge.edge_type, EdgeType::Calls);
+        assert_eq!(loaded_edge.weight, 2.5);
+
+        let path = graph.find_path(&node1.id, &node2.id).unwrap();
+        assert_eq!(path.path, vec![node1.id, node2.id]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs KG_TEST_DATABASE_URL"]
+    async fn test_removed_edges_are_deleted() {
+        let (_guard, pool) = test_store().await;
+
+        let node1 = Node::new("A".to_string(), NodeType::Function);
+        let node2 = Node::new("B".to_string(), NodeType::Function);
+        let edge = Edge::new(node1.id, node2.id, EdgeType::Calls);
+
+        let mut store = open(&pool, "default").await;
+        store.insert_node(node1).unwrap();
+        store.insert_node(node2).unwrap();
+        store.insert_edge(edge.clone()).unwrap();
+        store.remove_edge(&edge.id).unwrap();
+        flush(&store).await.unwrap();
+
+        let store = open(&pool, "default").await;
+        assert_eq!(store.node_count(), 2);
+        assert_eq!(store.edge_count(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs KG_TEST_DATABASE_URL"]
+    async fn test_graphs_are_isolated() {
+        let (_guard, pool) = test_store().await;
+
+        // The same natural-key node in two graphs shares an id.
+        let node = Node::new("charge".to_string(), NodeType::Function);
+        let other = Node::new("refund".to_string(), NodeType::Function);
+        let edge = Edge::new(node.id, other.id, EdgeType::Calls);
+
+        let mut acme = open(&pool, "acme").await;
+        acme.insert_node(node.clone()).unwrap();
+        acme.insert_node(other.clone()).unwrap();
+        acme.insert_edge(edge.clone()).unwrap();
+        let mut globex = open(&pool, "globex/billing").await;
+        globex.insert_node(node.clone()).unwrap();
+        globex.remove_node(&node.id).unwrap();
+        flush(&acme).await.unwrap();
+        flush(&globex).await.unwrap();
+
+        let acme = open(&pool, "acme").await;
+        assert_eq!((acme.node_count(), acme.edge_count()), (2, 1));
+        let globex = open(&pool, "globex/billing").await;
+        assert_eq!(globex.node_count(), 0);
+        assert_eq!(PostgresStore::graphs(&pool).await.unwrap(), vec!["acme"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs KG_TEST_DATABASE_URL"]
+    async fn test_remove_deletes_one_graph() {
+        let (_guard, pool) = test_store().await;
+
+        let node = Node::new("charge".to_string(), NodeType::Function);
+        let edge = Edge::new(node.id, node.id, EdgeType::Calls);
+        for graph in ["acme", "globex"] {
+            let mut store = open(&pool, graph).await;
+            store.insert_node(node.clone()).unwrap();
+            store.insert_edge(edge.clone()).unwrap();
+            flush(&store).await.unwrap();
+        }
+
+        PostgresStore::remove(&pool, "acme").await.unwrap();
+        let acme = open(&pool, "acme").await;
+        assert_eq!((acme.node_count(), acme.edge_count()), (0, 0));
+        let globex = open(&pool, "globex").await;
+        assert_eq!((globex.node_count(), globex.edge_count()), (1, 1));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs KG_TEST_DATABASE_URL"]
+    async fn test_failed_write_is_reported() {
+        let (_guard, pool) = test_store().await;
+
+        // Endpoints the database has never seen break the foreign key.
+        let edge = Edge::new(Uuid::new_v4(), Uuid::new_v4(), EdgeType::Calls);
+        let mut store = open(&pool, "default").await;
+        store.insert_edge(edge).unwrap();
+        assert!(matches!(flush(&store).await, Err(StoreError::Persist(_))));
+
+        let node = Node::new("A".to_string(), NodeType::Function);
+        let refused = store.insert_node(node.clone());
+        assert!(matches!(refused, Err(StoreError::Persist(_))));
+        assert!(store.get_node(&node.id).is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs KG_TEST_DATABASE_URL"]
+    async fn test_large_batch_is_accepted() {
+        let (_guard, pool) = test_store().await;
+
+        // The single-threaded test runtime does not run the writer until
+        // the test yields, so the whole batch is queued at once.
+        let mut graph = KnowledgeGraph::with_store(Box::new(open(&pool, "default").await));
+        let nodes = (0..5_000)
+            .map(|i| UpsertNode {
+                key: NodeKey {
+                    repository: "billing".to_string(),
+                    path: None,
+                    qualified_name: format!("billing.f{}", i),
+                    node_type: NodeType::Function,
+                },
+                name: None,
+                language: None,
+                description: None,
+                metadata: None,
+                embedding: None,
+            })
+            .collect();
+        let request = BulkUpsertRequest {
+            nodes,
+            edges: Vec::new(),
+        };
+        let report = graph.bulk_upsert(request).unwrap();
+        assert!(report
+            .nodes
+            .iter()
+            .all(|item| item.status == UpsertStatus::Created));
+        graph.pending_writes().unwrap().wait().await.unwrap();
+
+        assert_eq!(open(&pool, "default").await.node_count(), 5_000);
+    }
+}
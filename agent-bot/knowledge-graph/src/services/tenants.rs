@@ -0,0 +1,386 @@
+//! Named graphs, one per tenant.
+//!
+//! Each org, and optionally each repository within an org, has its own
+//! `KnowledgeGraph` behind its own lock, so a long write in one tenant never
+//! blocks another and no query can reach records outside its tenant's graph.
+//!
+//! Graphs are created by writes, opened from storage on first use, and
+//! dropped from memory again once idle. Reads never create a graph.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Mutex, OnceCell, RwLock};
+
+use crate::models::{GraphInfo, Tenant};
+use crate::services::graph::KnowledgeGraph;
+use crate::services::schema::GraphSchema;
+use crate::services::store::Storage;
+
+type SharedGraph = Arc<RwLock<KnowledgeGraph>>;
+
+/// A graph that is loaded, or being loaded, with when it was last handed out.
+struct Slot {
+    cell: Arc<OnceCell<SharedGraph>>,
+    last_used: Instant,
+}
+
+impl Slot {
+    fn new() -> Self {
+        Self {
+            cell: Arc::default(),
+            last_used: Instant::now(),
+        }
+    }
+
+    /// Whether no request holds the graph or is waiting for it.
+    fn unused(&self) -> bool {
+        Arc::strong_count(&self.cell) == 1
+            && self
+                .cell
+                .get()
+                .is_none_or(|graph| Arc::strong_count(graph) == 1)
+    }
+}
+
+pub struct Tenants {
+    storage: Storage,
+    /// Org that owns repositories registered without one. Startup ingestion
+    /// writes to its graph.
+    pub default_org: String,
+    /// Shared by every graph.
+    pub schema: Arc<GraphSchema>,
+    graphs: Mutex<HashMap<String, Slot>>,
+}
+
+impl Tenants {
+    pub fn new(storage: Storage, default_org: String, schema: GraphSchema) -> Self {
+        Self {
+            storage,
+            default_org,
+            schema: Arc::new(schema),
+            graphs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The graph of `tenant`, opened from storage on first use and created
+    /// if it does not exist. Concurrent first requests wait for a single
+    /// load.
+    pub async fn graph(&self, tenant: &Tenant) -> anyhow::Result<SharedGraph> {
+        let name = tenant.graph_name();
+        let cell = {
+            let mut graphs = self.graphs.lock().await;
+            let slot = graphs.entry(name.clone()).or_insert_with(Slot::new);
+            slot.last_used = Instant::now();
+            slot.cell.clone()
+        };
+        let graph = cell.get_or_try_init(|| self.open(&name)).await?;
+        Ok(graph.clone())
+    }
+
+    async fn open(&self, name: &str) -> anyhow::Result<SharedGraph> {
+        let store = self.storage.open_graph(name).await?;
+        let graph = KnowledgeGraph::with_store(store).with_schema(self.schema.clone());
+        let stats = graph.get_stats();
+        tracing::info!(
+            "Opened graph {} with {} nodes and {} edges",
+            name,
+            stats.total_nodes,
+            stats.total_edges
+        );
+        Ok(Arc::new(RwLock::new(graph)))
+    }
+
+    /// The graph of `tenant` if it is loaded or stored; never creates one.
+    pub async fn existing(&self, tenant: &Tenant) -> anyhow::Result<Option<SharedGraph>> {
+        let name = tenant.graph_name();
+        let known = self.graphs.lock().await.contains_key(&name);
+        if !known && !self.storage.contains(&name).await? {
+            return Ok(None);
+        }
+        self.graph(tenant).await.map(Some)
+    }
+
+    /// The graph of `tenant` if it is loaded in memory.
+    pub async fn loaded_graph(&self, tenant: &Tenant) -> Option<SharedGraph> {
+        let graphs = self.graphs.lock().await;
+        graphs.get(&tenant.graph_name())?.cell.get().cloned()
+    }
+
+    /// Drops graphs unused for `idle` from memory; they are reopened from
+    /// storage on next use. Graphs a request still holds are kept, and
+    /// nothing is evicted from memory storage, which has no other copy.
+    /// Returns the names evicted.
+    pub async fn evict_idle(&self, idle: Duration) -> Vec<String> {
+        if matches!(self.storage, Storage::Memory) {
+            return Vec::new();
+        }
+        let mut graphs = self.graphs.lock().await;
+        let idle_names: Vec<String> = graphs
+            .iter()
+            .filter(|(_, slot)| slot.last_used.elapsed() >= idle)
+            .filter(|(_, slot)| slot.unused())
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &idle_names {
+            graphs.remove(name);
+            tracing::info!("Evicted idle graph {}", name);
+        }
+        idle_names
+    }
+
+    /// Waits for the queued writes of every loaded graph; run on shutdown.
+    /// Failures are logged, as the writes can no longer be reported.
+    pub async fn flush(&self) {
+        for (name, graph) in self.loaded().await {
+            let Some(pending) = graph.read().await.pending_writes() else {
+                continue;
+            };
+            if let Err(err) = pending.wait().await {
+                tracing::error!("Unsaved writes to graph {}: {}", name, err);
+            }
+        }
+    }
+
+    /// Graphs loaded in memory, by name.
+    async fn loaded(&self) -> HashMap<String, SharedGraph> {
+        self.graphs
+            .lock()
+            .await
+            .iter()
+            .filter_map(|(name, slot)| Some((name.clone(), slot.cell.get()?.clone())))
+            .collect()
+    }
+
+    /// Every stored or loaded graph of `org_id`, sorted by name. Sizes are
+    /// reported for loaded graphs only, so listing never loads one.
+    pub async fn list(&self, org_id: &str) -> anyhow::Result<Vec<GraphInfo>> {
+        let loaded = self.loaded().await;
+        let mut names: BTreeSet<String> = self.storage.graph_names().await?.into_iter().collect();
+        names.extend(loaded.keys().cloned());
+
+        let mut graphs = Vec::new();
+        for name in names {
+            let Some(tenant) = Tenant::from_graph_name(&name) else {
+                continue;
+            };
+            if tenant.org_id != org_id {
+                continue;
+            }
+            let (nodes, edges) = match loaded.get(&name) {
+                Some(graph) => {
+                    let stats = graph.read().await.get_stats();
+                    (Some(stats.total_nodes), Some(stats.total_edges))
+                }
+                None => (None, None),
+            };
+            graphs.push(GraphInfo {
+                loaded: loaded.contains_key(&name),
+                name,
+                tenant,
+                nodes,
+                edges,
+            });
+        }
+        Ok(graphs)
+    }
+
+    /// Deletes `tenant`'s graph with its stored records; `false` when there
+    /// was no such graph. The next request for the tenant starts empty.
+    pub async fn remove(&self, tenant: &Tenant) -> anyhow::Result<bool> {
+        let name = tenant.graph_name();
+        // The tenant gets a fresh slot whose load waits for the removal, so
+        // requests arriving meanwhile never see records about to be deleted.
+        let (old, cell) = {
+            let mut graphs = self.graphs.lock().await;
+            let old = graphs.remove(&name).map(|slot| slot.cell);
+            if old.is_none() && !self.storage.contains(&name).await? {
+                return Ok(false);
+            }
+            let slot = Slot::new();
+            let cell = slot.cell.clone();
+            graphs.insert(name.clone(), slot);
+            (old, cell)
+        };
+
+        let mut removed = Ok(());
+        let _ = cell
+            .get_or_try_init(|| async {
+                removed = self.clear(old, &name).await;
+                // The slot is left empty: requests waiting on it load the
+                // emptied graph themselves.
+                Err::<SharedGraph, ()>(())
+            })
+            .await;
+        drop(cell);
+
+        // Like reads, a removal leaves no graph behind unless a request
+        // took the new one.
+        let mut graphs = self.graphs.lock().await;
+        if graphs.get(&name).is_some_and(Slot::unused) {
+            graphs.remove(&name);
+        }
+        removed?;
+        tracing::info!("Removed graph {}", name);
+        Ok(true)
+    }
+
+    async fn clear(
+        &self,
+        old: Option<Arc<OnceCell<SharedGraph>>>,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        if let Some(old) = old {
+            let old = old.get_or_try_init(|| self.open(name)).await?;
+            let pending = {
+                let mut old = old.write().await;
+                let pending = old.pending_writes();
+                // Requests still holding the graph write to a copy that is
+                // never stored.
+                *old = KnowledgeGraph::new();
+                pending
+            };
+            if let Some(pending) = pending {
+                pending.wait().await?;
+            }
+        }
+        self.storage.remove_graph(name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::{Node, NodeType};
+
+    fn open(dir: &std::path::Path) -> Tenants {
+        let storage = Storage::File {
+            dir: dir.to_path_buf(),
+        };
+        Tenants::new(storage, "default".to_string(), GraphSchema::default())
+    }
+
+    #[tokio::test]
+    async fn test_tenant_graphs_are_isolated() {
+        let dir = std::env::temp_dir().join(format!("kg-tenants-{}", Uuid::new_v4()));
+        let tenants = open(&dir);
+        let acme = Tenant::new("acme", None).unwrap();
+        let acme_billing = Tenant::new("acme", Some("billing")).unwrap();
+        let globex = Tenant::new("globex", None).unwrap();
+
+        let node = Node::new("charge".to_string(), NodeType::Function);
+        tenants
+            .graph(&acme)
+            .await
+            .unwrap()
+            .write()
+            .await
+            .add_node(node.clone())
+            .unwrap();
+        let billing = tenants.graph(&acme_billing).await.unwrap();
+        let refund = Node::new("refund".to_string(), NodeType::Function);
+        billing.write().await.add_node(refund).unwrap();
+
+        let other = tenants.graph(&globex).await.unwrap();
+        assert!(other.read().await.get_node(&node.id).is_none());
+        assert_eq!(billing.read().await.list_nodes().len(), 1);
+
+        let names: Vec<String> = tenants
+            .list("acme")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|info| info.name)
+            .collect();
+        assert_eq!(names, vec!["acme", "acme/billing"]);
+
+        // A fresh instance reads the same graphs back from storage.
+        let reopened = open(&dir);
+        let listed = reopened.list("acme").await.unwrap();
+        assert!(listed.iter().all(|info| !info.loaded));
+        let graph = reopened.graph(&acme).await.unwrap();
+        assert!(graph.read().await.get_node(&node.id).is_some());
+
+        assert!(reopened.remove(&acme_billing).await.unwrap());
+        assert!(!reopened.remove(&acme_billing).await.unwrap());
+        let names: Vec<String> = reopened
+            .list("acme")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|info| info.name)
+            .collect();
+        assert_eq!(names, vec!["acme"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_graph_lifecycle() {
+        let dir = std::env::temp_dir().join(format!("kg-tenants-{}", Uuid::new_v4()));
+        let tenants = open(&dir);
+        let acme = Tenant::new("acme", None).unwrap();
+
+        // Reads of an unknown tenant neither create nor store a graph.
+        assert!(tenants.existing(&acme).await.unwrap().is_none());
+        assert!(tenants.list("acme").await.unwrap().is_empty());
+        assert!(!dir.join("acme.log").exists());
+
+        let node = Node::new("charge".to_string(), NodeType::Function);
+        let graph = tenants.graph(&acme).await.unwrap();
+        graph.write().await.add_node(node.clone()).unwrap();
+        assert!(tenants.evict_idle(Duration::ZERO).await.is_empty());
+        drop(graph);
+        assert_eq!(tenants.evict_idle(Duration::ZERO).await, vec!["acme"]);
+        assert!(!tenants.list("acme").await.unwrap()[0].loaded);
+
+        let reopened = tenants.existing(&acme).await.unwrap().unwrap();
+        assert!(reopened.read().await.get_node(&node.id).is_some());
+        assert!(tenants.evict_idle(Duration::from_secs(60)).await.is_empty());
+
+        let memory = Tenants::new(
+            Storage::Memory,
+            "default".to_string(),
+            GraphSchema::default(),
+        );
+        drop(memory.graph(&acme).await.unwrap());
+        assert!(memory.evict_idle(Duration::ZERO).await.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_removed_graph_is_detached() {
+        let dir = std::env::temp_dir().join(format!("kg-tenants-{}", Uuid::new_v4()));
+        let tenants = open(&dir);
+        let acme = Tenant::new("acme", None).unwrap();
+
+        let held = tenants.graph(&acme).await.unwrap();
+        let node = Node::new("charge".to_string(), NodeType::Function);
+        held.write().await.add_node(node.clone()).unwrap();
+        assert!(tenants.remove(&acme).await.unwrap());
+        assert!(tenants.list("acme").await.unwrap().is_empty());
+
+        // A request that held the graph across the removal writes nowhere.
+        held.write().await.add_node(node.clone()).unwrap();
+        assert!(tenants.existing(&acme).await.unwrap().is_none());
+        let graph = tenants.graph(&acme).await.unwrap();
+        assert!(graph.read().await.get_node(&node.id).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tenant_names() {
+        assert!(Tenant::new("acme", Some("billing-api")).is_ok());
+        assert!(Tenant::new("", None).is_err());
+        assert!(Tenant::new("../etc", None).is_err());
+        assert!(Tenant::new("acme", Some("a/b")).is_err());
+        let tenant = Tenant::from_graph_name("acme/billing").unwrap();
+        assert_eq!(tenant.repository.as_deref(), Some("billing"));
+        assert_eq!(tenant.graph_name(), "acme/billing");
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::models::{GraphRecord, ImportLineError, ImportOptions, ImportReport};
+use crate::services::graph::KnowledgeGraph;
+use crate::services::store::StoreError;
+use crate::services::vector::embedding_error;
+
+/// Failures listed individually in an import report.
+const MAX_REPORTED_ERRORS: usize = 100;
+/// Longest import line accepted; longer lines fail without being buffered.
+pub const MAX_LINE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Record ids in export order: all nodes, then all edges, each sorted so
+/// exports of the same graph are byte-for-byte comparable.
+pub fn export_order(graph: &KnowledgeGraph) -> Vec<GraphRecordId> {
+    let mut nodes: Vec<Uuid> = graph.list_nodes().iter().map(|n| n.id).collect();
+    let mut edges: Vec<Uuid> = graph.list_edges().iter().map(|e| e.id).collect();
+    nodes.sort();
+    edges.sort();
+    nodes
+        .into_iter()
+        .map(GraphRecordId::Node)
+        .chain(edges.into_iter().map(GraphRecordId::Edge))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum GraphRecordId {
+    Node(Uuid),
+    Edge(Uuid),
+}
+
+/// Appends the NDJSON line for `id` to `out`; records deleted since
+/// `export_order` ran are skipped.
+pub fn write_record(graph: &KnowledgeGraph, id: &GraphRecordId, out: &mut Vec<u8>) {
+    let record = match id {
+        GraphRecordId::Node(id) => graph.get_node(id).cloned().map(GraphRecord::Node),
+        GraphRecordId::Edge(id) => graph.get_edge(id).cloned().map(GraphRecord::Edge),
+    };
+    if let Some(record) = record {
+        if serde_json::to_writer(&mut *out, &record).is_ok() {
+            out.push(b'\n');
+        }
+    }
+}
+
+/// Splits a streamed body into lines, scanning each byte once and holding
+/// at most `MAX_LINE_BYTES` of an unfinished line.
+#[derive(Default)]
+pub struct LineSplitter {
+    buffer: Vec<u8>,
+    /// Bytes of `buffer` known to hold no newline.
+    scanned: usize,
+    /// Whether the current line has outgrown the limit and is being skipped.
+    oversized: bool,
+}
+
+impl LineSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lines completed by `chunk`, `None` standing for one over the limit.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Option<String>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = self.buffer[self.scanned..].iter().position(|b| *b == b'\n') {
+            let end = self.scanned + offset;
+            lines.push(self.line(start, end));
+            start = end + 1;
+            self.scanned = start;
+        }
+        self.buffer.drain(..start);
+        self.scanned = self.buffer.len();
+        if self.buffer.len() > MAX_LINE_BYTES {
+            self.buffer.clear();
+            self.scanned = 0;
+            self.oversized = true;
+        }
+        lines
+    }
+
+    /// The last line, if the body did not end with a newline.
+    pub fn finish(mut self) -> Option<Option<String>> {
+        if self.buffer.is_empty() && !self.oversized {
+            return None;
+        }
+        Some(self.line(0, self.buffer.len()))
+    }
+
+    fn line(&mut self, start: usize, end: usize) -> Option<String> {
+        let oversized = std::mem::take(&mut self.oversized) || end - start > MAX_LINE_BYTES;
+        (!oversized).then(|| String::from_utf8_lossy(&self.buffer[start..end]).into_owned())
+    }
+}
+
+/// Incremental NDJSON import. Lines are fed in order, in batches of any
+/// size, so the body never has to be held in memory; only the id mapping
+/// grows with the input.
+pub struct Importer {
+    options: ImportOptions,
+    /// File id -> graph id, for nodes and edges seen so far.
+    id_map: HashMap<Uuid, Uuid>,
+    /// Node ids a dry run would have created.
+    pending_nodes: HashSet<Uuid>,
+    report: ImportReport,
+}
+
+impl Importer {
+    pub fn new(options: ImportOptions) -> Self {
+        let report = ImportReport {
+            dry_run: options.dry_run,
+            ..Default::default()
+        };
+        Self {
+            options,
+            id_map: HashMap::new(),
+            pending_nodes: HashSet::new(),
+            report,
+        }
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.options.dry_run
+    }
+
+    /// Validates one line against `graph`; used directly for dry runs.
+    pub fn check_line(&mut self, graph: &KnowledgeGraph, line_no: usize, line: &str) {
+        if let Some(record) = self.prepare(graph, line_no, line) {
+            if let GraphRecord::Node(node) = &record {
+                self.pending_nodes.insert(node.id);
+            }
+            self.count(&record);
+        }
+    }
+
+    /// Validates one line and applies it to `graph`. A store failure ends
+    /// the import; the lines before it stay applied.
+    pub fn import_line(
+        &mut self,
+        graph: &mut KnowledgeGraph,
+        line_no: usize,
+        line: &str,
+    ) -> Result<(), StoreError> {
+        let Some(record) = self.prepare(graph, line_no, line) else {
+            return Ok(());
+        };
+        self.count(&record);
+        match record {
+            GraphRecord::Node(node) => {
+                graph.add_node(node)?;
+            }
+            GraphRecord::Edge(edge) => {
+                graph.add_edge(edge)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a line that was too long to read.
+    pub fn skip_long_line(&mut self, line_no: usize) {
+        self.fail(
+            line_no,
+            format!("Line is longer than {} bytes", MAX_LINE_BYTES),
+        );
+    }
+
+    pub fn finish(self) -> ImportReport {
+        self.report
+    }
+
+    /// Failures are recorded in the report and yield `None`.
+    fn prepare(
+        &mut self,
+        graph: &KnowledgeGraph,
+        line_no: usize,
+        line: &str,
+    ) -> Option<GraphRecord> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let result = serde_json::from_str::<GraphRecord>(line)
+            .map_err(|err| format!("Invalid record: {}", err))
+            .and_then(|record| self.remap(graph, record));
+        match result {
+            Ok(record) => Some(record),
+            Err(error) => {
+                self.fail(line_no, error);
+                None
+            }
+        }
+    }
+
+    fn fail(&mut self, line_no: usize, error: String) {
+        self.report.failed += 1;
+        if self.report.errors.len() < MAX_REPORTED_ERRORS {
+            self.report.errors.push(ImportLineError {
+                line: line_no,
+                error,
+            });
+        }
+    }
+
+    fn remap(
+        &mut self,
+        graph: &KnowledgeGraph,
+        record: GraphRecord,
+    ) -> Result<GraphRecord, String> {
+        match record {
+            GraphRecord::Node(mut node) => {
+                let dimension = graph.embedding_dimension();
+                if let Some(error) = embedding_error(node.embedding.as_deref(), dimension) {
+                    return Err(error);
+                }
+                graph
+                    .check_node(&node)
+                    .map_err(|violation| violation.to_string())?;
+                if self.options.remap_ids {
+                    let id = Uuid::new_v4();
+                    self.id_map.insert(node.id, id);
+                    node.id = id;
+                }
+                Ok(GraphRecord::Node(node))
+            }
+            GraphRecord::Edge(mut edge) => {
+                edge.source_id = self.resolve_node(graph, &edge.source_id)?;
+                edge.target_id = self.resolve_node(graph, &edge.target_id)?;
+                if self.options.remap_ids {
+                    edge.id = Uuid::new_v4();
+                }
+                graph
+                    .check_edge(&edge)
+                    .map_err(|violation| violation.to_string())?;
+                Ok(GraphRecord::Edge(edge))
+            }
+        }
+    }
+
+    /// Prefers a node imported earlier in this file over one in the graph.
+    fn resolve_node(&self, graph: &KnowledgeGraph, id: &Uuid) -> Result<Uuid, String> {
+        let mapped = self.id_map.get(id).copied().unwrap_or(*id);
+        if graph.get_node(&mapped).is_some() || self.pending_nodes.contains(&mapped) {
+            Ok(mapped)
+        } else {
+            Err(format!("Edge endpoint {} not found", id))
+        }
+    }
+
+    fn count(&mut self, record: &GraphRecord) {
+        match record {
+            GraphRecord::Node(_) => self.report.nodes += 1,
+            GraphRecord::Edge(_) => self.report.edges += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Edge, EdgeType, Node, NodeType};
+
+    fn export(graph: &KnowledgeGraph) -> String {
+        let mut out = Vec::new();
+        for id in export_order(graph) {
+            write_record(graph, &id, &mut out);
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    fn sample() -> KnowledgeGraph {
+        let mut graph = KnowledgeGraph::new();
+        let a = graph
+            .add_node(Node::new("handleAuth".to_string(), NodeType::Function))
+            .unwrap();
+        let b = graph
+            .add_node(Node::new("processPayment".to_string(), NodeType::Function))
+            .unwrap();
+        graph
+            .add_edge(Edge::new(a, b, EdgeType::Calls).with_weight(2.0))
+            .unwrap();
+        graph
+    }
+
+    fn import(graph: &mut KnowledgeGraph, ndjson: &str, options: ImportOptions) -> ImportReport {
+        let mut importer = Importer::new(options);
+        for (index, line) in ndjson.lines().enumerate() {
+            if importer.dry_run() {
+                importer.check_line(graph, index + 1, line);
+            } else {
+                importer.import_line(graph, index + 1, line).unwrap();
+            }
+        }
+        importer.finish()
+    }
+
+    #[test]
+    fn test_export_lists_nodes_before_edges() {
+        let ndjson = export(&sample());
+        let types: Vec<String> = ndjson
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["type"].to_string()
+            })
+            .collect();
+        assert_eq!(types, vec!["\"node\"", "\"node\"", "\"edge\""]);
+    }
+
+    #[test]
+    fn test_round_trip_preserves_ids() {
+        let source = sample();
+        let ndjson = export(&source);
+
+        let mut target = KnowledgeGraph::new();
+        let report = import(&mut target, &ndjson, ImportOptions::default());
+        assert_eq!((report.nodes, report.edges, report.failed), (2, 1, 0));
+        assert_eq!(export(&target), ndjson);
+    }
+
+    #[test]
+    fn test_remap_ids_imports_a_second_copy() {
+        let mut graph = sample();
+        let ndjson = export(&graph);
+
+        let options = ImportOptions {
+            dry_run: false,
+            remap_ids: true,
+        };
+        let report = import(&mut graph, &ndjson, options);
+        assert_eq!(report.failed, 0);
+        assert_eq!(graph.get_stats().total_nodes, 4);
+        assert_eq!(graph.get_stats().total_edges, 2);
+
+        let copies: Vec<&Node> = graph
+            .list_nodes()
+            .into_iter()
+            .filter(|n| n.name == "handleAuth")
+            .collect();
+        assert_eq!(copies.len(), 2);
+        for copy in copies {
+            assert_eq!(graph.edge_count_for_node(&copy.id), 1);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_validates_without_changes() {
+        let ndjson = export(&sample());
+        let orphan = serde_json::to_string(&GraphRecord::Edge(Edge::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            EdgeType::Calls,
+        )))
+        .unwrap();
+        let input = format!("{}{}\nnot json\n", ndjson, orphan);
+
+        let mut graph = KnowledgeGraph::new();
+        let options = ImportOptions {
+            dry_run: true,
+            remap_ids: false,
+        };
+        let report = import(&mut graph, &input, options);
+        assert!(report.dry_run);
+        assert_eq!((report.nodes, report.edges, report.failed), (2, 1, 2));
+        assert_eq!(report.errors[0].line, 4);
+        assert!(report.errors[0].error.contains("not found"));
+        assert_eq!(report.errors[1].line, 5);
+        assert_eq!(graph.get_stats().total_nodes, 0);
+    }
+
+    #[test]
+    fn test_line_splitter_skips_long_lines() {
+        let mut splitter = LineSplitter::new();
+        assert_eq!(splitter.push(b"{\"a\"").len(), 0);
+        let lines = splitter.push(b": 1}\n\n{\"b\"");
+        assert_eq!(
+            lines,
+            vec![Some("{\"a\": 1}".to_string()), Some(String::new())]
+        );
+
+        let long = vec![b'x'; MAX_LINE_BYTES / 2 + 1];
+        assert!(splitter.push(&long).is_empty());
+        assert!(splitter.push(&long).is_empty());
+        assert!(splitter.buffer.is_empty());
+        assert_eq!(splitter.push(b"x\nlast"), vec![None]);
+        assert_eq!(splitter.finish(), Some(Some("last".to_string())));
+    }
+}
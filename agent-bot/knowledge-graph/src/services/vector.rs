@@ -0,0 +1,478 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Links per node on the upper layers; layer 0 keeps twice as many.
+const M: usize = 16;
+const EF_CONSTRUCTION: usize = 100;
+pub const DEFAULT_EF_SEARCH: usize = 64;
+
+struct Point {
+    /// Unit-length copy of the embedding, so cosine similarity is a dot product.
+    vector: Vec<f32>,
+    /// Neighbor ids per layer, `links[0]` being the densest.
+    links: Vec<Vec<Uuid>>,
+    /// Ids whose `links` hold this point, per layer, so a removal only
+    /// visits the points it has to reconnect.
+    linked_from: Vec<HashSet<Uuid>>,
+}
+
+/// In-memory HNSW index over node embeddings, scored by cosine similarity.
+///
+/// The dimension is fixed by the first vector inserted. Layer assignment is
+/// derived from the node id, so rebuilding from the same nodes yields the
+/// same graph.
+#[derive(Default)]
+pub struct VectorIndex {
+    dimension: Option<usize>,
+    points: HashMap<Uuid, Point>,
+    entry: Option<Uuid>,
+}
+
+/// A candidate and its distance (`1 - cosine`), ordered by distance then id.
+#[derive(Clone, Copy, PartialEq)]
+struct Scored(f32, Uuid);
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .total_cmp(&other.0)
+            .then_with(|| self.1.cmp(&other.1))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl VectorIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Indexes `vector` under `id`, replacing any previous vector for it.
+    /// Vectors of the wrong dimension or with zero length are ignored.
+    pub fn insert(&mut self, id: Uuid, vector: &[f32]) {
+        self.remove(&id);
+        if self
+            .dimension
+            .is_some_and(|dimension| dimension != vector.len())
+        {
+            tracing::warn!(
+                "Ignoring {}-dimensional embedding for {}; index holds {} dimensions",
+                vector.len(),
+                id,
+                self.dimension.unwrap_or_default()
+            );
+            return;
+        }
+        let Some(vector) = normalize(vector) else {
+            return;
+        };
+        self.dimension = Some(vector.len());
+
+        let level = level_for(&id);
+        let point = Point {
+            vector,
+            links: vec![Vec::new(); level + 1],
+            linked_from: vec![HashSet::new(); level + 1],
+        };
+        let Some(entry) = self.entry else {
+            self.points.insert(id, point);
+            self.entry = Some(id);
+            return;
+        };
+        let vector = &point.vector;
+
+        let top = self.points[&entry].links.len() - 1;
+        let mut entry_points = vec![entry];
+        for layer in (level + 1..=top).rev() {
+            entry_points = self.closest(vector, &entry_points, 1, layer);
+        }
+
+        let mut links = vec![Vec::new(); level + 1];
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(vector, &entry_points, EF_CONSTRUCTION, layer);
+            links[layer] = found.iter().take(max_links(layer)).map(|s| s.1).collect();
+            entry_points = found.into_iter().map(|s| s.1).collect();
+        }
+
+        self.points.insert(id, point);
+        for (layer, neighbors) in links.into_iter().enumerate() {
+            self.set_links(id, layer, neighbors.clone());
+            for neighbor in neighbors {
+                self.link(neighbor, id, layer);
+            }
+        }
+        if level > top {
+            self.entry = Some(id);
+        }
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        let Some(point) = self.points.remove(id) else {
+            return;
+        };
+        if self.points.is_empty() {
+            self.entry = None;
+            self.dimension = None;
+            return;
+        }
+        for (layer, links) in point.links.iter().enumerate() {
+            for neighbor in links {
+                if let Some(neighbor) = self.points.get_mut(neighbor) {
+                    neighbor.linked_from[layer].remove(id);
+                }
+            }
+        }
+
+        // Links are not always symmetric, so reconnect every point that
+        // pointed at the removed one, offering the removed point's own links
+        // as replacement candidates.
+        for (layer, sources) in point.linked_from.iter().enumerate() {
+            for other in sources {
+                let candidates: HashSet<Uuid> = self.points[other].links[layer]
+                    .iter()
+                    .chain(&point.links[layer])
+                    .filter(|c| *c != id && *c != other)
+                    .copied()
+                    .collect();
+                let pruned = self.prune(other, candidates, layer);
+                self.set_links(*other, layer, pruned);
+            }
+        }
+
+        // Only removing the entry point scans the index.
+        if self.entry == Some(*id) {
+            self.entry = self
+                .points
+                .iter()
+                .max_by(|a, b| {
+                    a.1.links
+                        .len()
+                        .cmp(&b.1.links.len())
+                        .then_with(|| b.0.cmp(a.0))
+                })
+                .map(|(id, _)| *id);
+        }
+    }
+
+    /// The nearest indexed ids to `query` that satisfy `accept`, most similar
+    /// first, with their cosine similarity.
+    ///
+    /// The beam is widened until `k` accepted results are found or the whole
+    /// index has been considered, so selective filters still fill the page.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        accept: impl Fn(&Uuid) -> bool,
+    ) -> Vec<(Uuid, f32)> {
+        if k == 0 || self.dimension != Some(query.len()) {
+            return Vec::new();
+        }
+        let (Some(query), Some(entry)) = (normalize(query), self.entry) else {
+            return Vec::new();
+        };
+
+        let top = self.points[&entry].links.len() - 1;
+        let mut entry_points = vec![entry];
+        for layer in (1..=top).rev() {
+            entry_points = self.closest(&query, &entry_points, 1, layer);
+        }
+
+        let mut ef = ef.max(k);
+        loop {
+            let found = self.search_layer(&query, &entry_points, ef, 0);
+            let considered = found.len();
+            let accepted: Vec<(Uuid, f32)> = found
+                .into_iter()
+                .filter(|s| accept(&s.1))
+                .take(k)
+                .map(|s| (s.1, 1.0 - s.0))
+                .collect();
+            if accepted.len() == k || considered >= self.points.len() || ef >= self.points.len() {
+                return accepted;
+            }
+            ef *= 2;
+        }
+    }
+
+    fn distance(&self, query: &[f32], id: &Uuid) -> f32 {
+        1.0 - dot(query, &self.points[id].vector)
+    }
+
+    fn closest(&self, query: &[f32], entry_points: &[Uuid], ef: usize, layer: usize) -> Vec<Uuid> {
+        self.search_layer(query, entry_points, ef, layer)
+            .into_iter()
+            .map(|s| s.1)
+            .collect()
+    }
+
+    /// Beam search on one layer; returns up to `ef` candidates, nearest first.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[Uuid],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<Uuid> = entry_points.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Scored> = BinaryHeap::new();
+        for id in entry_points {
+            let scored = Scored(self.distance(query, id), *id);
+            candidates.push(Reverse(scored));
+            found.push(scored);
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|worst| current.0 > worst.0) {
+                break;
+            }
+            let Some(links) = self.points[&current.1].links.get(layer) else {
+                continue;
+            };
+            for neighbor in links {
+                if !visited.insert(*neighbor) {
+                    continue;
+                }
+                let scored = Scored(self.distance(query, neighbor), *neighbor);
+                if found.len() < ef || found.peek().is_some_and(|worst| scored < *worst) {
+                    candidates.push(Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Replaces the links of `id` on `layer`, keeping `linked_from` in step.
+    fn set_links(&mut self, id: Uuid, layer: usize, links: Vec<Uuid>) {
+        let Some(point) = self.points.get_mut(&id) else {
+            return;
+        };
+        let previous = std::mem::replace(&mut point.links[layer], links);
+        for neighbor in previous {
+            if let Some(neighbor) = self.points.get_mut(&neighbor) {
+                neighbor.linked_from[layer].remove(&id);
+            }
+        }
+        for neighbor in self.points[&id].links[layer].clone() {
+            if let Some(neighbor) = self.points.get_mut(&neighbor) {
+                neighbor.linked_from[layer].insert(id);
+            }
+        }
+    }
+
+    fn link(&mut self, from: Uuid, to: Uuid, layer: usize) {
+        let Some(links) = self.points.get(&from).and_then(|p| p.links.get(layer)) else {
+            return;
+        };
+        if links.contains(&to) {
+            return;
+        }
+        let mut candidates: HashSet<Uuid> = links.iter().copied().collect();
+        candidates.insert(to);
+        let pruned = self.prune(&from, candidates, layer);
+        self.set_links(from, layer, pruned);
+    }
+
+    /// Keeps the `max_links(layer)` candidates nearest to `id`.
+    fn prune(&self, id: &Uuid, candidates: HashSet<Uuid>, layer: usize) -> Vec<Uuid> {
+        let vector = &self.points[id].vector;
+        let mut scored: Vec<Scored> = candidates
+            .into_iter()
+            .filter(|c| self.points.get(c).is_some_and(|p| p.links.len() > layer))
+            .map(|c| Scored(self.distance(vector, &c), c))
+            .collect();
+        scored.sort();
+        scored.truncate(max_links(layer));
+        scored.into_iter().map(|s| s.1).collect()
+    }
+}
+
+/// Why `embedding` cannot be indexed alongside embeddings of `dimension`,
+/// if it can't.
+pub fn embedding_error(embedding: Option<&[f32]>, dimension: Option<usize>) -> Option<String> {
+    let embedding = embedding?;
+    if embedding.is_empty() || embedding.iter().all(|x| *x == 0.0) {
+        return Some("Embedding must be a non-zero vector".to_string());
+    }
+    if embedding.iter().any(|x| !x.is_finite()) {
+        return Some("Embedding values must be finite".to_string());
+    }
+    match dimension {
+        Some(dimension) if dimension != embedding.len() => Some(format!(
+            "Embedding has {} dimensions, expected {}",
+            embedding.len(),
+            dimension
+        )),
+        _ => None,
+    }
+}
+
+fn max_links(layer: usize) -> usize {
+    if layer == 0 {
+        M * 2
+    } else {
+        M
+    }
+}
+
+/// HNSW layer, drawn from the id bits.
+fn level_for(id: &Uuid) -> usize {
+    let bits = (id.as_u128() as u64) ^ ((id.as_u128() >> 64) as u64);
+    let uniform = ((bits >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+    let multiplier = 1.0 / (M as f64).ln();
+    (-uniform.ln() * multiplier) as usize
+}
+
+fn normalize(vector: &[f32]) -> Option<Vec<f32>> {
+    let norm = dot(vector, vector).sqrt();
+    if vector.is_empty() || !norm.is_normal() {
+        return None;
+    }
+    Some(vector.iter().map(|x| x / norm).collect())
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seeded ids and vectors, since ids fix each point's layer.
+    fn vectors(count: usize, dimension: usize) -> Vec<(Uuid, Vec<f32>)> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..count)
+            .map(|_| {
+                let id = Uuid::from_u64_pair(next(), next());
+                let vector = (0..dimension)
+                    .map(|_| (next() % 2000) as f32 / 1000.0 - 1.0)
+                    .collect();
+                (id, vector)
+            })
+            .collect()
+    }
+
+    fn brute_force(points: &[(Uuid, Vec<f32>)], query: &[f32], k: usize) -> Vec<Uuid> {
+        let query = normalize(query).unwrap();
+        let mut scored: Vec<Scored> = points
+            .iter()
+            .map(|(id, v)| Scored(1.0 - dot(&query, &normalize(v).unwrap()), *id))
+            .collect();
+        scored.sort();
+        scored.into_iter().take(k).map(|s| s.1).collect()
+    }
+
+    #[test]
+    fn test_nearest_neighbors_match_brute_force() {
+        let points = vectors(1000, 16);
+        let mut index = VectorIndex::new();
+        for (id, vector) in &points {
+            index.insert(*id, vector);
+        }
+
+        let mut hits = 0;
+        for (_, query) in points.iter().take(50) {
+            let expected = brute_force(&points, query, 10);
+            let found: Vec<Uuid> = index
+                .search(query, 10, DEFAULT_EF_SEARCH, |_| true)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            hits += found.iter().filter(|id| expected.contains(id)).count();
+        }
+        // Recall@10 over 50 queries.
+        assert!(hits as f64 / 500.0 > 0.95, "recall {}", hits as f64 / 500.0);
+    }
+
+    #[test]
+    fn test_remove_and_filter() {
+        let points = vectors(200, 8);
+        let mut index = VectorIndex::new();
+        for (id, vector) in &points {
+            index.insert(*id, vector);
+        }
+        let (first, query) = &points[0];
+
+        let top = index.search(query, 1, DEFAULT_EF_SEARCH, |_| true);
+        assert_eq!(top[0].0, *first);
+        assert!((top[0].1 - 1.0).abs() < 1e-5);
+
+        index.remove(first);
+        assert_eq!(index.len(), 199);
+        assert!(index
+            .search(query, 10, DEFAULT_EF_SEARCH, |_| true)
+            .iter()
+            .all(|(id, _)| id != first));
+
+        // A filter accepting three ids still returns all three.
+        let allowed: HashSet<Uuid> = points[100..103].iter().map(|(id, _)| *id).collect();
+        let filtered = index.search(query, 5, 8, |id| allowed.contains(id));
+        assert_eq!(filtered.len(), 3);
+
+        for (id, _) in points.iter().step_by(2) {
+            index.remove(id);
+        }
+        assert_eq!(index.len(), 100);
+        for (id, point) in &index.points {
+            for (layer, links) in point.links.iter().enumerate() {
+                for neighbor in links {
+                    assert!(index.points[neighbor].linked_from[layer].contains(id));
+                }
+            }
+            for (layer, sources) in point.linked_from.iter().enumerate() {
+                let linked = |source: &Uuid| index.points[source].links[layer].contains(id);
+                assert!(sources.iter().all(linked));
+            }
+        }
+        let (kept, query) = &points[1];
+        assert_eq!(
+            index.search(query, 1, DEFAULT_EF_SEARCH, |_| true)[0].0,
+            *kept
+        );
+    }
+
+    #[test]
+    fn test_dimension_mismatch_is_ignored() {
+        let mut index = VectorIndex::new();
+        index.insert(Uuid::new_v4(), &[1.0, 0.0]);
+        index.insert(Uuid::new_v4(), &[1.0, 0.0, 0.0]);
+        index.insert(Uuid::new_v4(), &[0.0, 0.0]);
+        assert_eq!(index.len(), 1);
+        assert!(index
+            .search(&[1.0, 0.0, 0.0], 1, DEFAULT_EF_SEARCH, |_| true)
+            .is_empty());
+    }
+}
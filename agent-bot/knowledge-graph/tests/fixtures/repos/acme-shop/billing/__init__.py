@@ -0,0 +1 @@
+"""Billing package."""
@@ -0,0 +1,10 @@
+from .audit import record
+
+
+class PaymentService:
+    def __init__(self, gateway):
+        self.gateway = gateway
+
+    def retry(self, amount):
+        record("retry")
+        return self.gateway.charge(amount)
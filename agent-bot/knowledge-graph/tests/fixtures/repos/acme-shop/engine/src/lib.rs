@@ -0,0 +1,7 @@
+pub mod store;
+
+use crate::store::Store;
+
+pub fn run() {
+    Store::new().save("k");
+}
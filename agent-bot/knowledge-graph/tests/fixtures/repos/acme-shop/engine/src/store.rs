@@ -0,0 +1,13 @@
+pub struct Store {
+    items: Vec<String>,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Store { items: Vec::new() }
+    }
+
+    pub fn save(&mut self, item: &str) {
+        self.items.push(item.to_string());
+    }
+}
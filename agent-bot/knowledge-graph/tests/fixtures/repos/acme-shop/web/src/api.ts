@@ -0,0 +1,5 @@
+export class ApiClient {
+  async post(path: string, body: unknown) {
+    return fetch(path, { method: "POST", body: JSON.stringify(body) });
+  }
+}
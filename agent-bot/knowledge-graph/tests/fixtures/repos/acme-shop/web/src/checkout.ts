@@ -0,0 +1,7 @@
+import { ApiClient } from "./api";
+
+const client = new ApiClient();
+
+export async function submit(order: Order) {
+  return client.post("/orders", order);
+}
@@ -5,6 +5,8 @@ from pydantic_settings import BaseSettings
 class Settings(BaseSettings):
     port: int = 9005
     knowledge_graph_url: str = "http://knowledge-graph:4000"
+    org_id: str = "default"
+    api_key: str = ""
     request_timeout: float = 30.0
 
     model_config = {"env_prefix": "KG_"}
@@ -9,6 +9,9 @@ class KnowledgeGraphClient:
         settings = get_settings()
         self._base_url = settings.knowledge_graph_url.rstrip("/")
         self._timeout = settings.request_timeout
+        self._headers = {"X-Org-Id": settings.org_id}
+        if settings.api_key:
+            self._headers["Authorization"] = f"Bearer {settings.api_key}"
         self._client: httpx.AsyncClient | None = None
 
     async def _get_client(self) -> httpx.AsyncClient:
@@ -16,6 +19,7 @@ class KnowledgeGraphClient:
             self._client = httpx.AsyncClient(
                 base_url=self._base_url,
                 timeout=self._timeout,
+                headers=self._headers,
             )
         return self._client
 